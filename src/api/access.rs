@@ -0,0 +1,462 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use sea_orm::TransactionTrait;
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::{
+    auth::{Principal, Role, Scope},
+    db::entities::{
+        namespace_role::{self, Grantee},
+        team, team_member,
+    },
+    error::Error,
+    extractor::NamespacePath,
+    AppState,
+};
+
+/// Body of a role grant
+#[derive(Debug, Deserialize)]
+pub struct RoleGrant {
+    pub grantee: Grantee,
+    pub role: Role,
+}
+
+/// A team and its members
+#[derive(Debug, Serialize)]
+pub struct TeamInfo {
+    pub team: team::Model,
+    pub members: Vec<String>,
+}
+
+// GET list the roles granted in a namespace
+#[instrument]
+pub async fn list_roles(
+    State(state): State<AppState>,
+    NamespacePath(namespace): NamespacePath,
+) -> Result<Json<Vec<namespace_role::Model>>, Error> {
+    let grants = namespace_role::Entity::find_grants(&state.db, &namespace).await?;
+    Ok(Json(grants))
+}
+
+// PUT grant a role in a namespace
+#[instrument(skip(principal))]
+pub async fn grant_role(
+    State(state): State<AppState>,
+    principal: Principal,
+    NamespacePath(namespace): NamespacePath,
+    Json(grant): Json<RoleGrant>,
+) -> Result<Json<namespace_role::Model>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Owner, &namespace)
+        .await?;
+    let model = namespace_role::Entity::grant(
+        &state.db,
+        &namespace,
+        &grant.grantee,
+        grant.role,
+        &principal.subject,
+    )
+    .await?;
+    info!(
+        "Granted {} in {} to {:?}",
+        grant.role, namespace, grant.grantee
+    );
+    Ok(Json(model))
+}
+
+// DELETE revoke a role in a namespace
+#[instrument(skip(principal))]
+pub async fn revoke_role(
+    State(state): State<AppState>,
+    principal: Principal,
+    NamespacePath(namespace): NamespacePath,
+    Json(grantee): Json<Grantee>,
+) -> Result<Json<namespace_role::Model>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Owner, &namespace)
+        .await?;
+    let txn = state.db.begin().await?;
+    let removed = namespace_role::Entity::revoke(&txn, &namespace, &grantee)
+        .await?
+        .ok_or_else(|| Error::NoRole {
+            grantee: format!("{} {}", grantee.kind(), grantee.name()),
+            namespace: namespace.clone(),
+        })?;
+    txn.commit().await?;
+    info!("Revoked role in {} from {:?}", namespace, grantee);
+    Ok(Json(removed))
+}
+
+// POST transfer the ownership of a namespace
+#[instrument(skip(principal))]
+pub async fn transfer_ownership(
+    State(state): State<AppState>,
+    principal: Principal,
+    NamespacePath(namespace): NamespacePath,
+    Json(grantee): Json<Grantee>,
+) -> Result<Json<namespace_role::Model>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Owner, &namespace)
+        .await?;
+    let txn = state.db.begin().await?;
+    let owner =
+        namespace_role::Entity::transfer(&txn, &namespace, &grantee, &principal.subject).await?;
+    txn.commit().await?;
+    info!(
+        "Ownership of {} transferred to {:?} by {}",
+        namespace, grantee, principal.subject
+    );
+    Ok(Json(owner))
+}
+
+// PUT create a team - teams are set up by registry administrators
+#[instrument(skip(principal))]
+pub async fn create_team(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(name): Path<String>,
+) -> Result<(StatusCode, Json<TeamInfo>), Error> {
+    principal.authorize_admin()?;
+    if let Some(existing) = team::Entity::find_by_name(&state.db, &name).await? {
+        return Ok((StatusCode::OK, Json(team_info(&state, existing).await?)));
+    }
+    let txn = state.db.begin().await?;
+    let team = team::Entity::create(&txn, &name, &principal.subject).await?;
+    txn.commit().await?;
+    info!("Team {} created by {}", name, principal.subject);
+    Ok((StatusCode::CREATED, Json(team_info(&state, team).await?)))
+}
+
+// GET list the members of a team
+#[instrument]
+pub async fn team_members(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<TeamInfo>, Error> {
+    let team = find_team(&state, &name).await?;
+    Ok(Json(team_info(&state, team).await?))
+}
+
+// PUT add a member to a team
+#[instrument(skip(principal))]
+pub async fn add_member(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path((name, subject)): Path<(String, String)>,
+) -> Result<Json<TeamInfo>, Error> {
+    let team = find_team(&state, &name).await?;
+    authorize_team(&state, &principal, &team).await?;
+    team_member::Entity::add(&state.db, team.id, &subject).await?;
+    info!("{} added {} to team {}", principal.subject, subject, name);
+    Ok(Json(team_info(&state, team).await?))
+}
+
+// DELETE remove a member from a team
+#[instrument(skip(principal))]
+pub async fn remove_member(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path((name, subject)): Path<(String, String)>,
+) -> Result<Json<TeamInfo>, Error> {
+    let team = find_team(&state, &name).await?;
+    authorize_team(&state, &principal, &team).await?;
+    team_member::Entity::remove(&state.db, team.id, &subject).await?;
+    info!(
+        "{} removed {} from team {}",
+        principal.subject, subject, name
+    );
+    Ok(Json(team_info(&state, team).await?))
+}
+
+/// Teams are managed by their members (and registry administrators)
+async fn authorize_team(
+    state: &AppState,
+    principal: &Principal,
+    team: &team::Model,
+) -> Result<(), Error> {
+    if principal.authorize_admin().is_ok()
+        || team_member::Entity::is_member(&state.db, team.id, &principal.subject).await?
+    {
+        Ok(())
+    } else {
+        Err(Error::MissingRole {
+            required: "member".to_string(),
+            namespace: format!("team {}", team.name),
+        })
+    }
+}
+
+async fn find_team(state: &AppState, name: &str) -> Result<team::Model, Error> {
+    team::Entity::find_by_name(&state.db, name)
+        .await?
+        .ok_or_else(|| Error::UnknownTeam(name.to_string()))
+}
+
+async fn team_info(state: &AppState, team: team::Model) -> Result<TeamInfo, Error> {
+    let members = team_member::Entity::find_members(&state.db, team.id)
+        .await?
+        .into_iter()
+        .map(|m| m.subject)
+        .collect();
+    Ok(TeamInfo { team, members })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use axum::http::{Method, StatusCode};
+    use serde_json::{json, Value};
+
+    /// Creates a non-admin token for the given user
+    async fn user_token(app: &axum::Router, subject: &str, scopes: &[&str]) -> String {
+        let body = json!({ "name": subject, "subject": subject, "scopes": scopes });
+        let (status, created) = send_json(app, Method::POST, "/api/v0/tokens", Some(body)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        created["secret"].as_str().unwrap().to_string()
+    }
+
+    async fn publish_as(app: &axum::Router, token: &str, oci: &str) -> StatusCode {
+        let pkg = serde_json::to_value(test_pkg(oci, oci.as_bytes())).unwrap();
+        let uri = format!("/api/v0/publish/{oci}");
+        send_as(app, Some(token), Method::PUT, &uri, Some(pkg))
+            .await
+            .0
+    }
+
+    async fn call(
+        app: &axum::Router,
+        token: &str,
+        method: Method,
+        uri: &str,
+        body: Value,
+    ) -> StatusCode {
+        send_as(app, Some(token), method, uri, Some(body)).await.0
+    }
+
+    #[tokio::test]
+    async fn first_publish_claims_namespace() {
+        let app = test_app().await;
+        let alice = user_token(&app, "alice", &["publish", "yank"]).await;
+        let bob = user_token(&app, "bob", &["publish", "yank"]).await;
+
+        assert_eq!(
+            publish_as(&app, &alice, "org/counter:1.0.0").await,
+            StatusCode::CREATED
+        );
+        let (_, roles) = send_json(&app, Method::GET, "/api/v0/roles/org", None).await;
+        assert_eq!(roles[0]["grantee"], "alice");
+        assert_eq!(roles[0]["role"], "owner");
+
+        // Bob has no role - neither in the namespace, nor below it
+        assert_eq!(
+            publish_as(&app, &bob, "org/counter:1.1.0").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            publish_as(&app, &bob, "org/team/flipper:1.0.0").await,
+            StatusCode::FORBIDDEN
+        );
+
+        // Publishers may publish, but not yank
+        let grant = json!({ "grantee": { "user": "bob" }, "role": "publisher" });
+        assert_eq!(
+            call(&app, &alice, Method::PUT, "/api/v0/roles/org", grant).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            publish_as(&app, &bob, "org/team/flipper:1.0.0").await,
+            StatusCode::CREATED
+        );
+        let yank = json!({ "reason": "test" });
+        assert_eq!(
+            call(
+                &app,
+                &bob,
+                Method::PUT,
+                "/api/v0/yank/org/counter:1.0.0",
+                yank.clone()
+            )
+            .await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call(
+                &app,
+                &alice,
+                Method::PUT,
+                "/api/v0/yank/org/counter:1.0.0",
+                yank
+            )
+            .await,
+            StatusCode::OK
+        );
+
+        // Only owners manage roles
+        let grant = json!({ "grantee": { "user": "bob" }, "role": "owner" });
+        assert_eq!(
+            call(&app, &bob, Method::PUT, "/api/v0/roles/org", grant).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn claim_does_not_take_over_owned_subnamespaces() {
+        let app = test_app().await;
+        let alice = user_token(&app, "alice", &["publish"]).await;
+        let bob = user_token(&app, "bob", &["publish"]).await;
+
+        assert_eq!(
+            publish_as(&app, &bob, "acme/team/counter:1.0.0").await,
+            StatusCode::CREATED
+        );
+        // Owning `acme` would make alice an owner of `acme/team` as well
+        assert_eq!(
+            publish_as(&app, &alice, "acme/counter:1.0.0").await,
+            StatusCode::CONFLICT
+        );
+        let (_, roles) = send_json(&app, Method::GET, "/api/v0/roles/acme", None).await;
+        assert!(roles.as_array().unwrap().is_empty());
+        assert_eq!(
+            publish_as(&app, &alice, "acme/team/flipper:1.0.0").await,
+            StatusCode::FORBIDDEN
+        );
+
+        // The owner of all namespaces below may claim the parent
+        assert_eq!(
+            publish_as(&app, &bob, "acme/counter:1.0.0").await,
+            StatusCode::CREATED
+        );
+        let (_, roles) = send_json(&app, Method::GET, "/api/v0/roles/acme", None).await;
+        assert_eq!(roles[0]["grantee"], "bob");
+    }
+
+    #[tokio::test]
+    async fn team_roles() {
+        let app = test_app().await;
+        let alice = user_token(&app, "alice", &["publish"]).await;
+        let carol = user_token(&app, "carol", &["publish"]).await;
+        assert_eq!(
+            publish_as(&app, &alice, "acme/widget:1.0.0").await,
+            StatusCode::CREATED
+        );
+
+        // Unknown teams cannot be granted roles
+        let grant = json!({ "grantee": { "team": "core" }, "role": "maintainer" });
+        assert_eq!(
+            call(
+                &app,
+                &alice,
+                Method::PUT,
+                "/api/v0/roles/acme",
+                grant.clone()
+            )
+            .await,
+            StatusCode::NOT_FOUND
+        );
+
+        let (status, _) =
+            send_as(&app, Some(&alice), Method::PUT, "/api/v0/teams/core", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, Method::PUT, "/api/v0/teams/core", None).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send(&app, Method::PUT, "/api/v0/teams/core/members/alice", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            call(&app, &alice, Method::PUT, "/api/v0/roles/acme", grant).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            publish_as(&app, &carol, "acme/widget:1.1.0").await,
+            StatusCode::FORBIDDEN
+        );
+
+        // Carol can only add herself through a member of the team
+        let (status, _) = send_as(
+            &app,
+            Some(&carol),
+            Method::PUT,
+            "/api/v0/teams/core/members/carol",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, team) = send_as(
+            &app,
+            Some(&alice),
+            Method::PUT,
+            "/api/v0/teams/core/members/carol",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let team: Value = serde_json::from_slice(&team).unwrap();
+        assert_eq!(team["members"], json!(["alice", "carol", TEST_SUBJECT]));
+        assert_eq!(
+            publish_as(&app, &carol, "acme/widget:1.1.0").await,
+            StatusCode::CREATED
+        );
+    }
+
+    #[tokio::test]
+    async fn ownership_transfer_and_multiple_owners() {
+        let app = test_app().await;
+        let alice = user_token(&app, "alice", &["publish"]).await;
+        let bob = user_token(&app, "bob", &["publish"]).await;
+        assert_eq!(
+            publish_as(&app, &alice, "org/counter:1.0.0").await,
+            StatusCode::CREATED
+        );
+
+        // The last owner cannot be removed
+        let me = json!({ "user": "alice" });
+        assert_eq!(
+            call(
+                &app,
+                &alice,
+                Method::DELETE,
+                "/api/v0/roles/org",
+                me.clone()
+            )
+            .await,
+            StatusCode::CONFLICT
+        );
+
+        // With a second owner, it can
+        let grant = json!({ "grantee": { "user": "bob" }, "role": "owner" });
+        assert_eq!(
+            call(&app, &alice, Method::PUT, "/api/v0/roles/org", grant).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            call(&app, &alice, Method::DELETE, "/api/v0/roles/org", me).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            publish_as(&app, &alice, "org/counter:1.1.0").await,
+            StatusCode::FORBIDDEN
+        );
+
+        // Transfer replaces all owners
+        let to_alice = json!({ "user": "alice" });
+        assert_eq!(
+            call(&app, &bob, Method::POST, "/api/v0/transfer/org", to_alice).await,
+            StatusCode::OK
+        );
+        let (_, roles) = send_json(&app, Method::GET, "/api/v0/roles/org", None).await;
+        let roles = roles.as_array().unwrap();
+        assert_eq!(roles.len(), 1);
+        assert_eq!(roles[0]["grantee"], "alice");
+        assert_eq!(
+            publish_as(&app, &bob, "org/counter:1.2.0").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            publish_as(&app, &alice, "org/counter:1.2.0").await,
+            StatusCode::CREATED
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::Deserialize;
+use tracing::instrument;
+
+use crate::{
+    api::pagination::{Page, PageQuery},
+    db::entities::index::{self, NamespaceSummary, RepositorySummary},
+    error::Error,
+    AppState,
+};
+
+/// Restricts a listing to a namespace subtree
+#[derive(Debug, Default, Deserialize)]
+pub struct SubtreeFilter {
+    /// Namespace, whose subtree is listed (e.g. `org` includes `org/team/project`)
+    pub prefix: Option<String>,
+}
+
+/// Options of the repository listing of a namespace
+#[derive(Debug, Default, Deserialize)]
+pub struct RepositoryScope {
+    /// Includes the repositories of all namespaces below the namespace
+    #[serde(default)]
+    pub subtree: bool,
+}
+
+// GET list all namespaces
+#[instrument]
+pub async fn list_namespaces(
+    State(state): State<AppState>,
+    Query(filter): Query<SubtreeFilter>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<NamespaceSummary>>, Error> {
+    let after: Option<String> = page.after()?;
+    let limit = page.limit();
+    let namespaces = index::Entity::list_namespaces(
+        &state.db,
+        normalize(filter.prefix.as_deref()),
+        after.as_deref(),
+        limit as u64 + 1,
+    )
+    .await?;
+    Ok(Json(Page::new(
+        namespaces,
+        limit,
+        |n: &NamespaceSummary| n.namespace.clone(),
+    )))
+}
+
+// GET list the repositories of a namespace
+#[instrument]
+pub async fn list_namespace_repositories(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+    Query(scope): Query<RepositoryScope>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<RepositorySummary>>, Error> {
+    // Namespaces may contain slashes, so the route captures `{namespace}/repositories` as a whole
+    let decoded_path = urlencoding::decode(&path)?;
+    let namespace = decoded_path
+        .trim_matches('/')
+        .strip_suffix("/repositories")
+        .filter(|ns| !ns.is_empty())
+        .ok_or(Error::InvalidPath)?;
+
+    let (subtree, exact) = if scope.subtree {
+        (Some(namespace), None)
+    } else {
+        (None, Some(namespace))
+    };
+    list_repositories(&state, subtree, exact, &page)
+        .await
+        .map(Json)
+}
+
+// GET list all repositories of the registry
+#[instrument]
+pub async fn catalog(
+    State(state): State<AppState>,
+    Query(filter): Query<SubtreeFilter>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<RepositorySummary>>, Error> {
+    list_repositories(&state, normalize(filter.prefix.as_deref()), None, &page)
+        .await
+        .map(Json)
+}
+
+async fn list_repositories(
+    state: &AppState,
+    subtree: Option<&str>,
+    exact: Option<&str>,
+    page: &PageQuery,
+) -> Result<Page<RepositorySummary>, Error> {
+    let after: Option<(String, String)> = page.after()?;
+    let limit = page.limit();
+    let repositories = index::Entity::list_repositories(
+        &state.db,
+        subtree,
+        exact,
+        after
+            .as_ref()
+            .map(|(ns, repo)| (ns.as_str(), repo.as_str())),
+        limit as u64 + 1,
+    )
+    .await?;
+    Ok(Page::new(repositories, limit, |r: &RepositorySummary| {
+        (r.namespace.clone(), r.repository.clone())
+    }))
+}
+
+/// Strips surrounding slashes and ignores empty prefixes
+fn normalize(prefix: Option<&str>) -> Option<&str> {
+    prefix
+        .map(|p| p.trim_matches('/'))
+        .filter(|p| !p.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use axum::http::{Method, StatusCode};
+    use serde_json::Value;
+
+    async fn seed(app: &axum::Router) {
+        for oci in [
+            "org/counter:1.0.0",
+            "org/counter:1.1.0",
+            "org/team/flipper:0.1.0",
+            "org/team/project/ledger:2.0.0",
+            "organization/other:1.0.0",
+            "acme/widget:0.3.0",
+        ] {
+            let pkg = test_pkg(oci, oci.as_bytes());
+            assert_eq!(publish(app, oci, &pkg).await, StatusCode::CREATED);
+        }
+    }
+
+    /// Follows all cursors and collects the items
+    async fn collect(app: &axum::Router, uri: &str) -> Vec<Value> {
+        let sep = if uri.contains('?') { '&' } else { '?' };
+        let mut next = format!("{uri}{sep}limit=2");
+        let mut items = Vec::new();
+        loop {
+            let (status, page) = send_json(app, Method::GET, &next, None).await;
+            assert_eq!(status, StatusCode::OK, "{page}");
+            items.extend(page["items"].as_array().unwrap().iter().cloned());
+            match &page["next_cursor"] {
+                Value::String(cursor) => next = format!("{uri}{sep}limit=2&cursor={cursor}"),
+                _ => return items,
+            }
+        }
+    }
+
+    fn names(items: &[Value], keys: &[&str]) -> Vec<String> {
+        items
+            .iter()
+            .map(|i| {
+                keys.iter()
+                    .map(|k| i[*k].as_str().unwrap())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn namespaces_are_paginated_and_filtered_by_subtree() {
+        let app = test_app().await;
+        seed(&app).await;
+
+        let all = collect(&app, "/api/v0/namespaces").await;
+        assert_eq!(
+            names(&all, &["namespace"]),
+            [
+                "acme",
+                "org",
+                "org/team",
+                "org/team/project",
+                "organization"
+            ]
+        );
+        assert_eq!(all[1]["repositories"], 1);
+
+        let org = collect(&app, "/api/v0/namespaces?prefix=org").await;
+        assert_eq!(
+            names(&org, &["namespace"]),
+            ["org", "org/team", "org/team/project"]
+        );
+    }
+
+    #[tokio::test]
+    async fn catalog_lists_all_repositories() {
+        let app = test_app().await;
+        seed(&app).await;
+
+        let all = collect(&app, "/api/v0/catalog").await;
+        assert_eq!(
+            names(&all, &["namespace", "repository"]),
+            [
+                "acme/widget",
+                "org/counter",
+                "org/team/flipper",
+                "org/team/project/ledger",
+                "organization/other"
+            ]
+        );
+        assert_eq!(all[1]["tags"], 2);
+
+        let team = collect(&app, "/api/v0/catalog?prefix=org/team").await;
+        assert_eq!(
+            names(&team, &["namespace", "repository"]),
+            ["org/team/flipper", "org/team/project/ledger"]
+        );
+    }
+
+    #[tokio::test]
+    async fn namespace_repositories() {
+        let app = test_app().await;
+        seed(&app).await;
+
+        let direct = collect(&app, "/api/v0/namespaces/org/team/repositories").await;
+        assert_eq!(names(&direct, &["repository"]), ["flipper"]);
+
+        let subtree = collect(
+            &app,
+            "/api/v0/namespaces/org/team/repositories?subtree=true",
+        )
+        .await;
+        assert_eq!(names(&subtree, &["repository"]), ["flipper", "ledger"]);
+
+        let (status, body) = send_json(&app, Method::GET, "/api/v0/namespaces/org", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["status"], 400);
+    }
+}
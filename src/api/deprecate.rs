@@ -0,0 +1,273 @@
+use std::str::FromStr;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderName},
+    Json,
+};
+use sea_orm::TransactionTrait;
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use crate::{
+    auth::{Principal, Role, Scope},
+    db::entities::{
+        index::{self, DeprecationInfo},
+        log_entry::{self, LogKind},
+    },
+    error::Error,
+    extractor::OciId,
+    models::OciIdentifier,
+    AppState,
+};
+
+/// Scope of a deprecation
+#[derive(Debug, Default, Deserialize)]
+pub struct DeprecationScope {
+    /// Apply to every version in the repository, instead of just the given tag
+    #[serde(default)]
+    pub all_versions: bool,
+}
+
+/// Body of a deprecation request
+#[derive(Debug, Deserialize)]
+pub struct DeprecationRequest {
+    /// Human readable message, e.g. why the package is deprecated and what to use instead
+    pub message: String,
+    /// Oci identifier of the package, that replaces the deprecated one
+    #[serde(default)]
+    pub successor: Option<String>,
+}
+
+// PUT deprecate a published version (or the whole repository)
+#[instrument(skip(principal))]
+pub async fn deprecate(
+    State(state): State<AppState>,
+    principal: Principal,
+    OciId(oid): OciId,
+    Query(scope): Query<DeprecationScope>,
+    Json(request): Json<DeprecationRequest>,
+) -> Result<Json<Vec<index::Model>>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Maintainer, &oid.namespace)
+        .await?;
+    let successor = request
+        .successor
+        .as_deref()
+        .map(OciIdentifier::from_str)
+        .transpose()
+        .map_err(|e| Error::InvalidSuccessor(e.to_string()))?;
+    let info = DeprecationInfo {
+        message: request.message,
+        successor,
+    };
+
+    let _guard = state.index_lock.lock().await;
+    let txn = state.db.begin().await?;
+    let entries =
+        index::Entity::set_deprecation(&txn, &oid, scope.all_versions, Some(info)).await?;
+    if entries.is_empty() {
+        return Err(Error::NotPublished(oid.to_string()));
+    }
+    for entry in &entries {
+        log_entry::Entity::append_index(&txn, LogKind::Deprecate, entry, &principal.subject)
+            .await?;
+    }
+    txn.commit().await?;
+    info!("Deprecated {} version(s) of {}", entries.len(), oid);
+    Ok(Json(entries))
+}
+
+// DELETE revert the deprecation of a published version (or the whole repository)
+#[instrument(skip(principal))]
+pub async fn undeprecate(
+    State(state): State<AppState>,
+    principal: Principal,
+    OciId(oid): OciId,
+    Query(scope): Query<DeprecationScope>,
+) -> Result<Json<Vec<index::Model>>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Maintainer, &oid.namespace)
+        .await?;
+    let _guard = state.index_lock.lock().await;
+    let txn = state.db.begin().await?;
+    let entries = index::Entity::set_deprecation(&txn, &oid, scope.all_versions, None).await?;
+    if entries.is_empty() {
+        return Err(Error::NotPublished(oid.to_string()));
+    }
+    for entry in &entries {
+        log_entry::Entity::append_index(&txn, LogKind::Undeprecate, entry, &principal.subject)
+            .await?;
+    }
+    txn.commit().await?;
+    info!(
+        "Reverted deprecation of {} version(s) of {}",
+        entries.len(),
+        oid
+    );
+    Ok(Json(entries))
+}
+
+/// Builds the `Deprecation` (RFC 9745) and successor `Link` headers for a deprecated package
+pub fn deprecation_headers(
+    deprecated_at: Option<chrono::DateTime<chrono::Utc>>,
+    successor: Option<&str>,
+) -> Vec<(HeaderName, String)> {
+    let mut headers = Vec::new();
+    let Some(deprecated_at) = deprecated_at else {
+        return headers;
+    };
+    headers.push((
+        HeaderName::from_static("deprecation"),
+        format!("@{}", deprecated_at.timestamp()),
+    ));
+    if let Some(successor) = successor {
+        headers.push((
+            header::LINK,
+            format!("</api/v0/packages/{successor}>; rel=\"successor-version\""),
+        ));
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn deprecate_single_version() {
+        let app = test_app().await;
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm-1"),
+        )
+        .await;
+        publish(
+            &app,
+            "borderless/counter:1.1.0",
+            &test_pkg("counter", b"\0asm-2"),
+        )
+        .await;
+
+        let body = json!({
+            "message": "storage layout is broken",
+            "successor": "borderless/counter:1.1.0"
+        });
+        let (status, entries) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/deprecate/borderless/counter:1.0.0",
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(entries.as_array().unwrap().len(), 1);
+        assert_eq!(entries[0]["deprecated"], true);
+
+        let response = get(&app, "/api/v0/packages/borderless/counter:1.0.0").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()["deprecation"]
+            .to_str()
+            .unwrap()
+            .starts_with('@'));
+        assert_eq!(
+            response.headers()["link"],
+            "</api/v0/packages/borderless/counter:1.1.0>; rel=\"successor-version\""
+        );
+        let summary = json_body(response).await;
+        assert_eq!(summary["deprecation_message"], "storage layout is broken");
+        assert_eq!(summary["deprecation_successor"], "borderless/counter:1.1.0");
+
+        // Download of the deprecated blob carries the header as well
+        let digest = summary["digest"].as_str().unwrap();
+        let response = get(&app, &format!("/api/v0/blobs/{digest}")).await;
+        assert!(response.headers().contains_key("deprecation"));
+
+        // The successor is untouched
+        let response = get(&app, "/api/v0/packages/borderless/counter:1.1.0").await;
+        assert!(!response.headers().contains_key("deprecation"));
+    }
+
+    #[tokio::test]
+    async fn invalid_successor_is_rejected() {
+        let app = test_app().await;
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm-1"),
+        )
+        .await;
+
+        let body = json!({ "message": "broken", "successor": "not an identifier" });
+        let (status, error) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/deprecate/borderless/counter:1.0.0",
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid successor"));
+
+        // Nothing was deprecated
+        let response = get(&app, "/api/v0/packages/borderless/counter:1.0.0").await;
+        assert!(!response.headers().contains_key("deprecation"));
+    }
+
+    #[tokio::test]
+    async fn deprecate_all_versions() {
+        let app = test_app().await;
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm-1"),
+        )
+        .await;
+        publish(
+            &app,
+            "borderless/counter:1.1.0",
+            &test_pkg("counter", b"\0asm-2"),
+        )
+        .await;
+        publish(
+            &app,
+            "borderless/flipper:1.0.0",
+            &test_pkg("flipper", b"\0asm-3"),
+        )
+        .await;
+
+        let body = json!({ "message": "use flipper instead" });
+        let (status, entries) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/deprecate/borderless/counter:1.0.0?all_versions=true",
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(entries.as_array().unwrap().len(), 2);
+
+        let (_, hits) = send_json(&app, Method::GET, "/api/v0/search?deprecated=false", None).await;
+        assert_eq!(hits.as_array().unwrap().len(), 1);
+
+        let (status, entries) = send_json(
+            &app,
+            Method::DELETE,
+            "/api/v0/deprecate/borderless/counter:1.0.0?all_versions=true",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|e| e["deprecated"] == false && e["deprecation_message"].is_null()));
+    }
+}
@@ -0,0 +1,343 @@
+//! Routes of the transparency log
+//!
+//! Every publish, yank and deprecation is appended to the log. Auditors fetch signed tree heads and verify with
+//! consistency proofs, that the log only grows - and with inclusion proofs, that an entry is part of it.
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::{
+    api::pagination::{Page, PageQuery},
+    db::entities::log_entry,
+    error::Error,
+    transparency::{self, TreeHead},
+    AppState,
+};
+
+/// Tree head, signed by the log key of the registry
+#[derive(Debug, Serialize)]
+pub struct SignedTreeHead {
+    #[serde(flatten)]
+    pub head: TreeHead,
+    /// Base64 encoded ed25519 signature of [`TreeHead::message`]
+    pub signature: String,
+    /// Base64 encoded public log key
+    pub public_key: String,
+}
+
+/// Entry of the log together with its leaf index
+#[derive(Debug, Serialize)]
+pub struct IndexedEntry {
+    pub index: u64,
+    #[serde(flatten)]
+    pub entry: log_entry::Model,
+}
+
+/// Parameters of an inclusion proof
+#[derive(Debug, Deserialize)]
+pub struct InclusionQuery {
+    /// Leaf index of the entry
+    pub index: u64,
+    /// Size of the tree, the proof is computed for - defaults to the current size
+    pub tree_size: Option<u64>,
+}
+
+/// Audit path from a leaf to the root of the tree
+#[derive(Debug, Serialize)]
+pub struct InclusionProof {
+    pub index: u64,
+    pub tree_size: u64,
+    pub leaf_hash: String,
+    pub root_hash: String,
+    pub audit_path: Vec<String>,
+}
+
+/// Parameters of a consistency proof
+#[derive(Debug, Deserialize)]
+pub struct ConsistencyQuery {
+    /// Size of the older tree
+    pub first: u64,
+    /// Size of the newer tree - defaults to the current size
+    pub second: Option<u64>,
+}
+
+/// Proof, that the older tree is a prefix of the newer tree
+#[derive(Debug, Serialize)]
+pub struct ConsistencyProof {
+    pub first: u64,
+    pub second: u64,
+    pub first_root: String,
+    pub second_root: String,
+    pub proof: Vec<String>,
+}
+
+// GET signed head of the transparency log
+#[instrument]
+pub async fn tree_head(State(state): State<AppState>) -> Result<Json<SignedTreeHead>, Error> {
+    let size = log_entry::Entity::tree_size(&state.db).await?;
+    let leaves = log_entry::Entity::leaf_hashes(&state.db, size).await?;
+    let head = TreeHead {
+        tree_size: leaves.len() as u64,
+        root_hash: String::from(transparency::root_hash(&leaves)),
+        timestamp: chrono::Utc::now().timestamp(),
+    };
+    let signature = head.sign(&state.log_key);
+    Ok(Json(SignedTreeHead {
+        head,
+        signature,
+        public_key: BASE64_STANDARD.encode(state.log_key.verifying_key().as_bytes()),
+    }))
+}
+
+// GET list the entries of the transparency log
+#[instrument]
+pub async fn entries(
+    State(state): State<AppState>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Page<IndexedEntry>>, Error> {
+    let start = page.after::<u64>()?.map(|last| last + 1).unwrap_or(0);
+    let limit = page.limit();
+    let entries = log_entry::Entity::entries(&state.db, start, limit as u64 + 1)
+        .await?
+        .into_iter()
+        .zip(start..)
+        .map(|(entry, index)| IndexedEntry { index, entry })
+        .collect();
+    Ok(Json(Page::new(entries, limit, |e: &IndexedEntry| e.index)))
+}
+
+// GET inclusion proof of a log entry
+#[instrument]
+pub async fn inclusion_proof(
+    State(state): State<AppState>,
+    Query(query): Query<InclusionQuery>,
+) -> Result<Json<InclusionProof>, Error> {
+    let size = log_entry::Entity::tree_size(&state.db).await?;
+    let tree_size = query.tree_size.unwrap_or(size);
+    if tree_size > size {
+        return Err(Error::InvalidProofRequest(format!(
+            "tree size {tree_size} exceeds the log size {size}"
+        )));
+    }
+    if query.index >= tree_size {
+        return Err(Error::InvalidProofRequest(format!(
+            "index {} is not part of a tree of size {tree_size}",
+            query.index
+        )));
+    }
+    let leaves = log_entry::Entity::leaf_hashes(&state.db, tree_size).await?;
+    let index = query.index as usize;
+    Ok(Json(InclusionProof {
+        index: query.index,
+        tree_size,
+        leaf_hash: String::from(leaves[index]),
+        root_hash: String::from(transparency::root_hash(&leaves)),
+        audit_path: hex_hashes(transparency::inclusion_proof(&leaves, index)),
+    }))
+}
+
+// GET consistency proof between two sizes of the log
+#[instrument]
+pub async fn consistency_proof(
+    State(state): State<AppState>,
+    Query(query): Query<ConsistencyQuery>,
+) -> Result<Json<ConsistencyProof>, Error> {
+    let size = log_entry::Entity::tree_size(&state.db).await?;
+    let second = query.second.unwrap_or(size);
+    if query.first == 0 || query.first > second || second > size {
+        return Err(Error::InvalidProofRequest(format!(
+            "sizes must satisfy 0 < first <= second <= {size}"
+        )));
+    }
+    let leaves = log_entry::Entity::leaf_hashes(&state.db, second).await?;
+    let first = query.first as usize;
+    Ok(Json(ConsistencyProof {
+        first: query.first,
+        second,
+        first_root: String::from(transparency::root_hash(&leaves[..first])),
+        second_root: String::from(transparency::root_hash(&leaves)),
+        proof: hex_hashes(transparency::consistency_proof(&leaves, first)),
+    }))
+}
+
+fn hex_hashes(hashes: Vec<borderless_hash::Hash256>) -> Vec<String> {
+    hashes.into_iter().map(String::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use crate::transparency;
+    use axum::http::{Method, StatusCode};
+    use base64::prelude::*;
+    use borderless_hash::Hash256;
+    use ed25519_dalek::{Signature, VerifyingKey};
+    use serde_json::{json, Value};
+
+    fn decode_hashes(value: &Value) -> Vec<Hash256> {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|h| Hash256::try_from(hex::decode(h.as_str().unwrap()).unwrap()).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn changes_are_logged() {
+        let app = test_app().await;
+        let wasm = b"\0asm-counter";
+        publish(&app, "borderless/counter:1.0.0", &test_pkg("counter", wasm)).await;
+        publish(
+            &app,
+            "borderless/counter:1.1.0",
+            &test_pkg("counter", b"\0asm-v2"),
+        )
+        .await;
+        // Re-publishing the same package is not a change
+        publish(&app, "borderless/counter:1.0.0", &test_pkg("counter", wasm)).await;
+        send(
+            &app,
+            Method::PUT,
+            "/api/v0/yank/borderless/counter:1.0.0",
+            Some(json!({ "reason": "broken" })),
+        )
+        .await;
+        send(
+            &app,
+            Method::PUT,
+            "/api/v0/deprecate/borderless/counter:1.1.0?all_versions=true",
+            Some(json!({ "message": "use counter-v2" })),
+        )
+        .await;
+
+        let (status, page) = send_json(&app, Method::GET, "/api/v0/log/entries", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let items = page["items"].as_array().unwrap();
+        let kinds: Vec<_> = items.iter().map(|e| e["kind"].as_str().unwrap()).collect();
+        assert_eq!(
+            kinds,
+            ["publish", "publish", "yank", "deprecate", "deprecate"]
+        );
+        assert_eq!(items[0]["index"], 0);
+        assert_eq!(items[0]["oci"], "borderless/counter:1.0.0");
+        assert_eq!(items[0]["digest"], String::from(Hash256::digest(wasm)));
+        assert_eq!(items[2]["digest"], items[0]["digest"]);
+        assert_eq!(items[2]["actor"], TEST_SUBJECT);
+
+        // The leaf hash covers the published leaf content
+        let leaf = items[1]["leaf"].as_str().unwrap();
+        assert_eq!(
+            items[1]["leaf_hash"],
+            String::from(transparency::leaf_hash(leaf.as_bytes()))
+        );
+
+        let (_, page) = send_json(&app, Method::GET, "/api/v0/log/entries?limit=2", None).await;
+        let cursor = page["next_cursor"].as_str().unwrap();
+        let (_, page) = send_json(
+            &app,
+            Method::GET,
+            &format!("/api/v0/log/entries?limit=2&cursor={cursor}"),
+            None,
+        )
+        .await;
+        assert_eq!(page["items"][0]["index"], 2);
+    }
+
+    #[tokio::test]
+    async fn tree_head_is_signed() {
+        let app = test_app().await;
+        let (_, head) = send_json(&app, Method::GET, "/api/v0/log/head", None).await;
+        assert_eq!(head["tree_size"], 0);
+        assert_eq!(head["root_hash"], String::from(Hash256::empty()));
+
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm"),
+        )
+        .await;
+        let (status, head) = send_json(&app, Method::GET, "/api/v0/log/head", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(head["tree_size"], 1);
+
+        let key: [u8; 32] = BASE64_STANDARD
+            .decode(head["public_key"].as_str().unwrap())
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let key = VerifyingKey::from_bytes(&key).unwrap();
+        let signature: [u8; 64] = BASE64_STANDARD
+            .decode(head["signature"].as_str().unwrap())
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let message = format!(
+            "{}:{}:{}",
+            head["tree_size"],
+            head["root_hash"].as_str().unwrap(),
+            head["timestamp"]
+        );
+        key.verify_strict(message.as_bytes(), &Signature::from_bytes(&signature))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn proofs() {
+        let app = test_app().await;
+        for i in 0..5 {
+            let oci = format!("borderless/counter:1.{i}.0");
+            let wasm = format!("\0asm-{i}");
+            publish(&app, &oci, &test_pkg("counter", wasm.as_bytes())).await;
+        }
+        let (_, head) = send_json(&app, Method::GET, "/api/v0/log/head", None).await;
+
+        let (status, proof) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/log/proof/inclusion?index=4",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(proof["tree_size"], 5);
+        assert_eq!(proof["root_hash"], head["root_hash"]);
+        // The last leaf of a tree of 5 is combined with the root of the first 4
+        let path = decode_hashes(&proof["audit_path"]);
+        assert_eq!(path.len(), 1);
+
+        let (_, old) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/log/proof/inclusion?index=0&tree_size=3",
+            None,
+        )
+        .await;
+        let (status, proof) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/log/proof/consistency?first=3",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(proof["second"], 5);
+        assert_eq!(proof["first_root"], old["root_hash"]);
+        assert_eq!(proof["second_root"], head["root_hash"]);
+        assert!(!proof["proof"].as_array().unwrap().is_empty());
+
+        for uri in [
+            "/api/v0/log/proof/inclusion?index=5",
+            "/api/v0/log/proof/inclusion?index=0&tree_size=6",
+            "/api/v0/log/proof/consistency?first=0",
+            "/api/v0/log/proof/consistency?first=4&second=3",
+        ] {
+            let (status, _) = send(&app, Method::GET, uri, None).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+}
@@ -0,0 +1,12 @@
+//! Routes to manage already published packages
+pub mod access;
+pub mod catalog;
+pub mod deprecate;
+pub mod log;
+pub mod oci;
+pub mod pagination;
+pub mod resolve;
+pub mod signing;
+pub mod tags;
+pub mod tokens;
+pub mod yank;
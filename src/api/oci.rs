@@ -0,0 +1,1875 @@
+//! OCI distribution api (`/v2/`)
+//!
+//! Packages are exposed as OCI artifacts (see [`crate::oci`]), so standard tooling like ORAS can push and pull them.
+//! Clients authenticate with basic auth and an API token as password. Pushes go through the same checks as
+//! publishes via the registry api.
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{AppendHeaders, IntoResponse, Response},
+    Json,
+};
+use sea_orm::{DatabaseConnection, ModelTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, instrument, warn};
+
+use crate::{
+    auth::{Principal, Role, Scope},
+    blobs::{self, Encoding},
+    db::entities::{
+        index, namespace_role, oci_blob,
+        oci_manifest::{self, ManifestBlob},
+        oci_referrer, oci_upload, tag_pointer,
+    },
+    error::Error,
+    models::{OciIdentifier, Tag},
+    oci::{self, ImageManifest, OciArtifact},
+    publish_package,
+    signing::SIGNATURE_HEADER,
+    AppState,
+};
+
+/// Header, that carries the digest of manifests and blobs
+pub const CONTENT_DIGEST_HEADER: &str = "docker-content-digest";
+
+/// Default limit for the body of a single upload request - a chunk or a monolithic upload
+pub const DEFAULT_MAX_UPLOAD_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default number of entries in tag and repository listings
+const DEFAULT_LIST_SIZE: usize = 100;
+
+/// Upper bound for the number of entries in tag and repository listings
+const MAX_LIST_SIZE: usize = 1000;
+
+/// Interval, in which abandoned uploads are discarded
+const UPLOAD_EXPIRY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Error in the format of the distribution spec
+#[derive(Debug)]
+pub struct OciError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl OciError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        OciError {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn name_invalid(name: &str) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            "NAME_INVALID",
+            format!("invalid repository name {name} - expected <namespace>/<repository>"),
+        )
+    }
+}
+
+impl From<Error> for OciError {
+    fn from(e: Error) -> Self {
+        let code = match &e {
+            Error::NotPublished(_) | Error::UnknownManifest(_) | Error::Yanked { .. } => {
+                "MANIFEST_UNKNOWN"
+            }
+            Error::UnknownBlob(_) | Error::NoPkg(_) => "BLOB_UNKNOWN",
+            Error::UnknownUpload(_) => "BLOB_UPLOAD_UNKNOWN",
+            Error::InvalidRange { .. } => "BLOB_UPLOAD_INVALID",
+            Error::InvalidDigest | Error::DigestMismatch { .. } => "DIGEST_INVALID",
+            Error::InvalidManifest(_)
+            | Error::FloatingTag(_)
+            | Error::InvalidSignature
+            | Error::SignatureRequired(_) => "MANIFEST_INVALID",
+            Error::Oci(_) => "NAME_INVALID",
+            Error::Unauthorized(_) => "UNAUTHORIZED",
+            Error::Forbidden { .. } | Error::MissingRole { .. } | Error::Conflict { .. } => {
+                "DENIED"
+            }
+            _ => "UNKNOWN",
+        };
+        let status = match code {
+            "MANIFEST_UNKNOWN" | "BLOB_UNKNOWN" | "BLOB_UPLOAD_UNKNOWN" => StatusCode::NOT_FOUND,
+            "DIGEST_INVALID" | "MANIFEST_INVALID" | "NAME_INVALID" => StatusCode::BAD_REQUEST,
+            _ => e.status(),
+        };
+        OciError::new(status, code, e.to_string())
+    }
+}
+
+impl IntoResponse for OciError {
+    fn into_response(self) -> Response {
+        let body = Json(json!({
+            "errors": [{ "code": self.code, "message": self.message }]
+        }));
+        if self.status == StatusCode::UNAUTHORIZED {
+            let challenge = [(
+                header::WWW_AUTHENTICATE,
+                "Basic realm=\"borderless-registry\"",
+            )];
+            return (self.status, challenge, body).into_response();
+        }
+        (self.status, body).into_response()
+    }
+}
+
+/// Pagination of tag and repository listings
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    /// Maximum number of entries
+    pub n: Option<usize>,
+    /// Last entry of the previous page
+    pub last: Option<String>,
+}
+
+impl ListQuery {
+    fn size(&self) -> usize {
+        self.n.unwrap_or(DEFAULT_LIST_SIZE).clamp(1, MAX_LIST_SIZE)
+    }
+}
+
+/// Filter of the referrers listing
+#[derive(Debug, Default, Deserialize)]
+pub struct ReferrersFilter {
+    /// Only list artifacts of this type
+    #[serde(rename = "artifactType")]
+    pub artifact_type: Option<String>,
+}
+
+/// Parameters of blob uploads
+#[derive(Debug, Default, Deserialize)]
+pub struct UploadQuery {
+    /// Digest of the uploaded blob - completes the upload
+    pub digest: Option<String>,
+    /// Digest of a blob, that should be mounted from another repository
+    pub mount: Option<String>,
+}
+
+/// Response of the tag listing
+#[derive(Debug, Serialize)]
+pub struct TagList {
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+/// Response of the repository listing
+#[derive(Debug, Serialize)]
+pub struct Catalog {
+    pub repositories: Vec<String>,
+}
+
+/// Resource, that a `/v2/<name>/...` path refers to
+#[derive(Debug, PartialEq)]
+enum Resource<'a> {
+    Manifest(&'a str),
+    Blob(&'a str),
+    Upload(Option<&'a str>),
+    Referrers(&'a str),
+    Tags,
+}
+
+/// Repository name and resource of a `/v2/` path
+#[derive(Debug, PartialEq)]
+struct Target<'a> {
+    namespace: &'a str,
+    repository: &'a str,
+    resource: Resource<'a>,
+}
+
+impl<'a> Target<'a> {
+    fn parse(path: &'a str) -> Result<Self, OciError> {
+        let unknown = || {
+            OciError::new(
+                StatusCode::NOT_FOUND,
+                "UNSUPPORTED",
+                format!("unknown endpoint /v2/{path}"),
+            )
+        };
+        let (name, resource) = if let Some(name) = path.strip_suffix("/tags/list") {
+            (name, Resource::Tags)
+        } else if let Some((name, rest)) = path.rsplit_once("/blobs/uploads") {
+            match rest.trim_start_matches('/') {
+                "" => (name, Resource::Upload(None)),
+                id if rest.starts_with('/') && !id.contains('/') => {
+                    (name, Resource::Upload(Some(id)))
+                }
+                _ => return Err(unknown()),
+            }
+        } else if let Some((name, digest)) = path.rsplit_once("/referrers/") {
+            (name, Resource::Referrers(digest))
+        } else if let Some((name, reference)) = path.rsplit_once("/manifests/") {
+            (name, Resource::Manifest(reference))
+        } else if let Some((name, digest)) = path.rsplit_once("/blobs/") {
+            (name, Resource::Blob(digest))
+        } else {
+            return Err(unknown());
+        };
+        let (namespace, repository) = name
+            .rsplit_once('/')
+            .filter(|(ns, repo)| !ns.is_empty() && !repo.is_empty())
+            .ok_or_else(|| OciError::name_invalid(name))?;
+        Ok(Target {
+            namespace,
+            repository,
+            resource,
+        })
+    }
+
+    fn name(&self) -> String {
+        format!("{}/{}", self.namespace, self.repository)
+    }
+
+    fn oci(&self, reference: &str) -> OciIdentifier {
+        OciIdentifier::new(
+            self.namespace.to_string(),
+            self.repository.to_string(),
+            Tag::parse(reference),
+        )
+    }
+}
+
+// GET api version check
+#[instrument]
+pub async fn version() -> Response {
+    (
+        [(
+            HeaderName::from_static("docker-distribution-api-version"),
+            "registry/2.0",
+        )],
+        Json(json!({})),
+    )
+        .into_response()
+}
+
+// GET list all repositories
+#[instrument]
+pub async fn catalog(
+    State(state): State<AppState>,
+    Query(query): Query<ListQuery>,
+) -> Result<Response, OciError> {
+    let size = query.size();
+    let after = query
+        .last
+        .as_deref()
+        .map(|last| last.rsplit_once('/').unwrap_or(("", last)));
+    let repositories =
+        index::Entity::list_repositories(&state.db, None, None, after, size as u64 + 1).await?;
+    let mut repositories: Vec<_> = repositories
+        .into_iter()
+        .map(|r| format!("{}/{}", r.namespace, r.repository))
+        .collect();
+    let next = truncate(&mut repositories, size)
+        .map(|last| format!("</v2/_catalog?n={size}&last={last}>; rel=\"next\""));
+    Ok((
+        AppendHeaders(link_header(next)),
+        Json(Catalog { repositories }),
+    )
+        .into_response())
+}
+
+// GET fetch manifests, blobs, tag listings and the status of uploads
+#[instrument(skip(principal))]
+pub async fn get(
+    State(state): State<AppState>,
+    principal: Result<Principal, Error>,
+    Path(path): Path<String>,
+    Query(query): Query<ListQuery>,
+    Query(filter): Query<ReferrersFilter>,
+) -> Result<Response, OciError> {
+    let target = Target::parse(&path)?;
+    match target.resource {
+        Resource::Manifest(reference) => get_manifest(&state, &target, reference).await,
+        Resource::Blob(digest) => get_blob(&state, digest).await,
+        Resource::Tags => list_tags(&state, &target, query).await,
+        Resource::Referrers(digest) => list_referrers(&state, &target, digest, filter).await,
+        Resource::Upload(Some(id)) => {
+            authorize_upload(&state, &principal?, &target).await?;
+            let name = target.name();
+            let upload = oci_upload::Entity::find_session(&state.db, &name, id).await?;
+            Ok(upload_progress(StatusCode::NO_CONTENT, &name, &upload))
+        }
+        Resource::Upload(None) => Err(method_not_allowed()),
+    }
+}
+
+// POST start a blob upload
+#[instrument(skip(principal, body))]
+pub async fn post(
+    State(state): State<AppState>,
+    principal: Result<Principal, Error>,
+    Path(path): Path<String>,
+    Query(query): Query<UploadQuery>,
+    body: Bytes,
+) -> Result<Response, OciError> {
+    let target = Target::parse(&path)?;
+    let Resource::Upload(None) = target.resource else {
+        return Err(method_not_allowed());
+    };
+    authorize_upload(&state, &principal?, &target).await?;
+    let name = target.name();
+
+    // Blobs are stored once for the whole registry, so mounting an existing blob is free
+    if let Some(digest) = query.mount {
+        let _guard = state.index_lock.lock().await;
+        if has_blob(&state, &digest).await? {
+            oci_blob::Entity::touch(&state.db, &digest).await?;
+            return Ok(blob_created(&name, &digest));
+        }
+    }
+    // Monolithic upload in a single request
+    if let Some(digest) = query.digest {
+        check_digest(&digest, &body)?;
+        let _guard = state.index_lock.lock().await;
+        oci_blob::Entity::store(&state.db, state.blobs.as_ref(), &digest, body.to_vec()).await?;
+        return Ok(blob_created(&name, &digest));
+    }
+
+    let upload = oci_upload::Entity::start(&state.db, &name).await?;
+    Ok(upload_progress(StatusCode::ACCEPTED, &name, &upload))
+}
+
+// PATCH append a chunk to a blob upload
+#[instrument(skip(principal, body))]
+pub async fn patch(
+    State(state): State<AppState>,
+    principal: Result<Principal, Error>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, OciError> {
+    let target = Target::parse(&path)?;
+    let Resource::Upload(Some(id)) = target.resource else {
+        return Err(method_not_allowed());
+    };
+    authorize_upload(&state, &principal?, &target).await?;
+    let name = target.name();
+
+    let txn = state.db.begin().await.map_err(Error::from)?;
+    let upload = oci_upload::Entity::find_session(&txn, &name, id).await?;
+    // Without a range, the chunk is appended to the end (streamed upload)
+    let offset = match headers.get(header::CONTENT_RANGE) {
+        Some(range) => chunk_offset(range.to_str().ok(), body.len(), &upload)?,
+        None => upload.size as u64,
+    };
+    let upload = oci_upload::Entity::append(&txn, upload, offset, body.to_vec()).await?;
+    txn.commit().await.map_err(Error::from)?;
+    Ok(upload_progress(StatusCode::ACCEPTED, &name, &upload))
+}
+
+// DELETE cancel a blob upload
+#[instrument(skip(principal))]
+pub async fn delete(
+    State(state): State<AppState>,
+    principal: Result<Principal, Error>,
+    Path(path): Path<String>,
+) -> Result<Response, OciError> {
+    let target = Target::parse(&path)?;
+    let Resource::Upload(Some(id)) = target.resource else {
+        return Err(method_not_allowed());
+    };
+    authorize_upload(&state, &principal?, &target).await?;
+    let upload = oci_upload::Entity::find_session(&state.db, &target.name(), id).await?;
+    upload.delete(&state.db).await.map_err(Error::from)?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// PUT complete a blob upload or push a manifest
+#[instrument(skip(principal, body))]
+pub async fn put(
+    State(state): State<AppState>,
+    principal: Result<Principal, Error>,
+    Path(path): Path<String>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, OciError> {
+    let target = Target::parse(&path)?;
+    let principal = principal?;
+    principal.authorize(Scope::Publish, target.namespace)?;
+    match target.resource {
+        Resource::Upload(Some(id)) => {
+            authorize_upload(&state, &principal, &target).await?;
+            let name = target.name();
+            let digest = query.digest.ok_or(Error::InvalidDigest)?;
+            // The final request may carry the last chunk
+            let _guard = state.index_lock.lock().await;
+            let txn = state.db.begin().await.map_err(Error::from)?;
+            let upload = oci_upload::Entity::find_session(&txn, &name, id).await?;
+            let offset = upload.size as u64;
+            let upload = oci_upload::Entity::append(&txn, upload, offset, body.to_vec()).await?;
+            let data = oci_upload::Entity::assemble(&txn, &upload).await?;
+            check_digest(&digest, &data)?;
+            oci_blob::Entity::store(&txn, state.blobs.as_ref(), &digest, data).await?;
+            upload.delete(&txn).await.map_err(Error::from)?;
+            txn.commit().await.map_err(Error::from)?;
+            Ok(blob_created(&name, &digest))
+        }
+        Resource::Manifest(reference) => {
+            put_manifest(&state, &principal, &target, reference, &headers, body).await
+        }
+        _ => Err(method_not_allowed()),
+    }
+}
+
+/// Uploads into a claimed namespace require the publisher role
+///
+/// An unclaimed namespace is claimed by the first manifest, that is pushed into it - so its blobs
+/// only need the scope, like the first publish does.
+async fn authorize_upload(
+    state: &AppState,
+    principal: &Principal,
+    target: &Target<'_>,
+) -> Result<(), Error> {
+    if namespace_role::Entity::is_claimed(&state.db, target.namespace).await? {
+        principal
+            .authorize_in(&state.db, Scope::Publish, Role::Publisher, target.namespace)
+            .await
+    } else {
+        principal.authorize(Scope::Publish, target.namespace)
+    }
+}
+
+async fn get_manifest(
+    state: &AppState,
+    target: &Target<'_>,
+    reference: &str,
+) -> Result<Response, OciError> {
+    if oci::is_digest(reference) {
+        let (media_type, content) = find_manifest(state, target, reference)
+            .await?
+            .ok_or_else(|| Error::UnknownManifest(reference.to_string()))?;
+        return Ok(content_response(&media_type, reference, content));
+    }
+    let oid = tag_pointer::Entity::resolve(&state.db, target.oci(reference)).await?;
+    let entry = index::Entity::find_by_oci(&oid)
+        .one(&state.db)
+        .await
+        .map_err(Error::from)?
+        .ok_or_else(|| Error::NotPublished(oid.to_string()))?;
+    // Yanked versions can only be fetched by their digest
+    if entry.yank {
+        return Err(Error::Yanked {
+            oci: oid.to_string(),
+            reason: entry.yank_reason.unwrap_or_default(),
+        }
+        .into());
+    }
+    // Packages, that were published before the OCI api existed, have no manifest
+    let manifest = oci_manifest::Entity::find_for_index(&state.db, entry.id)
+        .await?
+        .ok_or_else(|| Error::UnknownManifest(oid.to_string()))?;
+    Ok(content_response(
+        &manifest.media_type,
+        &manifest.digest,
+        manifest.content,
+    ))
+}
+
+async fn get_blob(state: &AppState, digest: &str) -> Result<Response, OciError> {
+    if !oci::is_digest(digest) {
+        return Err(Error::InvalidDigest.into());
+    }
+    let blob = find_blob(state, digest)
+        .await?
+        .ok_or_else(|| Error::UnknownBlob(digest.to_string()))?;
+    let headers = [
+        (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+        (
+            HeaderName::from_static(CONTENT_DIGEST_HEADER),
+            digest.to_string(),
+        ),
+    ];
+    let length = blob
+        .size
+        .map(|size| (header::CONTENT_LENGTH, size.to_string()));
+    Ok((headers, AppendHeaders(length), blob.body).into_response())
+}
+
+async fn list_tags(
+    state: &AppState,
+    target: &Target<'_>,
+    query: ListQuery,
+) -> Result<Response, OciError> {
+    let name = target.name();
+    let mut tags: Vec<String> =
+        index::Entity::find_tags(&state.db, target.namespace, target.repository)
+            .await?
+            .into_iter()
+            .filter(|t| !t.yank)
+            .map(|t| t.tag)
+            .collect();
+    if tags.is_empty() {
+        return Err(OciError::new(
+            StatusCode::NOT_FOUND,
+            "NAME_UNKNOWN",
+            format!("repository {name} is not known to the registry"),
+        ));
+    }
+    tags.sort();
+    if let Some(last) = &query.last {
+        tags.retain(|t| t > last);
+    }
+    let size = query.size();
+    let next = truncate(&mut tags, size)
+        .map(|last| format!("</v2/{name}/tags/list?n={size}&last={last}>; rel=\"next\""));
+    Ok((
+        AppendHeaders(link_header(next)),
+        Json(TagList { name, tags }),
+    )
+        .into_response())
+}
+
+async fn put_manifest(
+    state: &AppState,
+    principal: &Principal,
+    target: &Target<'_>,
+    reference: &str,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<Response, OciError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    let manifest = ImageManifest::parse(&body, content_type)?;
+
+    // Artifacts with a subject are attached to an existing manifest
+    if manifest.subject.is_some() {
+        return put_referrer(state, principal, target, reference, manifest, body).await;
+    }
+    // Packages are identified by their tag
+    if oci::is_digest(reference) {
+        return Err(Error::InvalidManifest("packages must be pushed by tag".into()).into());
+    }
+    manifest.check_package()?;
+
+    let config = referenced_blob(state, &manifest.config).await?;
+    let wasm = referenced_blob(state, manifest.layer()).await?;
+    let pkg = oci::package_from_config(&config, &wasm)?;
+
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .map(|v| v.to_str().map_err(|_| Error::InvalidSignature))
+        .transpose()?;
+    let oid = target.oci(reference);
+    let artifact = OciArtifact::new(manifest, body.to_vec(), config);
+    let mut digest = artifact.digest.clone();
+    publish_package(state, principal, &oid, signature, pkg, Some(artifact)).await?;
+
+    // Re-publishing the same package keeps the manifest of the first publish
+    if let Some(entry) = index::Entity::find_by_oci(&oid)
+        .one(&state.db)
+        .await
+        .map_err(Error::from)?
+    {
+        if let Some(stored) = oci_manifest::Entity::find_for_index(&state.db, entry.id).await? {
+            digest = stored.digest;
+        }
+    }
+    info!("Pushed manifest {} as {}", digest, oid);
+    let location = format!("/v2/{}/manifests/{digest}", target.name());
+    Ok((
+        StatusCode::CREATED,
+        [
+            (header::LOCATION, location),
+            (HeaderName::from_static(CONTENT_DIGEST_HEADER), digest),
+        ],
+    )
+        .into_response())
+}
+
+async fn put_referrer(
+    state: &AppState,
+    principal: &Principal,
+    target: &Target<'_>,
+    reference: &str,
+    manifest: ImageManifest,
+    body: Bytes,
+) -> Result<Response, OciError> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Publisher, target.namespace)
+        .await?;
+    let digest = oci::digest(&body);
+    if reference != digest {
+        return Err(Error::InvalidManifest(
+            "artifacts with a subject must be pushed by their digest".into(),
+        )
+        .into());
+    }
+    let Some(subject) = &manifest.subject else {
+        return Err(Error::InvalidManifest("manifest has no subject".into()).into());
+    };
+    if find_manifest(state, target, &subject.digest)
+        .await?
+        .is_none()
+    {
+        return Err(Error::InvalidManifest(format!("unknown subject {}", subject.digest)).into());
+    }
+    // The garbage collection deletes unreferenced blobs under the index lock,
+    // so the blobs cannot disappear between this check and the insert of the referrer
+    let _guard = state.index_lock.lock().await;
+    referenced_blob(state, &manifest.config).await?;
+    for layer in &manifest.layers {
+        referenced_blob(state, layer).await?;
+    }
+
+    let subject = subject.digest.clone();
+    oci_referrer::Entity::store(
+        &state.db,
+        target.namespace,
+        target.repository,
+        &manifest,
+        body.to_vec(),
+        &principal.subject,
+    )
+    .await?;
+    info!(
+        "Attached {} artifact {} to {}",
+        manifest.artifact_type(),
+        digest,
+        subject
+    );
+    let location = format!("/v2/{}/manifests/{digest}", target.name());
+    Ok((
+        StatusCode::CREATED,
+        [
+            (header::LOCATION, location),
+            (HeaderName::from_static(CONTENT_DIGEST_HEADER), digest),
+            (HeaderName::from_static("oci-subject"), subject),
+        ],
+    )
+        .into_response())
+}
+
+async fn list_referrers(
+    state: &AppState,
+    target: &Target<'_>,
+    digest: &str,
+    filter: ReferrersFilter,
+) -> Result<Response, OciError> {
+    if !oci::is_digest(digest) {
+        return Err(Error::InvalidDigest.into());
+    }
+    let referrers = oci_referrer::Entity::find_for_subject(
+        &state.db,
+        target.namespace,
+        target.repository,
+        digest,
+        filter.artifact_type.as_deref(),
+    )
+    .await?;
+    let index = oci::ImageIndex::new(referrers.iter().map(|r| r.descriptor()).collect());
+    let filters = filter.artifact_type.map(|_| {
+        (
+            HeaderName::from_static("oci-filters-applied"),
+            "artifactType",
+        )
+    });
+    // The index media type replaces the plain json content type
+    Ok((
+        [(header::CONTENT_TYPE, oci::INDEX_MEDIA_TYPE)],
+        AppendHeaders(filters),
+        Json(index),
+    )
+        .into_response())
+}
+
+/// Returns media type and content of a package manifest or referrer in a repository
+async fn find_manifest(
+    state: &AppState,
+    target: &Target<'_>,
+    digest: &str,
+) -> Result<Option<(String, Vec<u8>)>, Error> {
+    if let Some((manifest, _)) =
+        oci_manifest::Entity::find_by_digest(&state.db, target.namespace, target.repository, digest)
+            .await?
+    {
+        return Ok(Some((manifest.media_type, manifest.content)));
+    }
+    let referrer = oci_referrer::Entity::find_by_digest(
+        &state.db,
+        target.namespace,
+        target.repository,
+        digest,
+    )
+    .await?;
+    Ok(referrer.map(|r| (r.media_type, r.content)))
+}
+
+/// Periodically discards uploads, that were inactive for longer than `ttl`
+pub async fn expire_uploads(db: DatabaseConnection, ttl: chrono::Duration) {
+    let mut interval = tokio::time::interval(UPLOAD_EXPIRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        match oci_upload::Entity::expire(&db, chrono::Utc::now() - ttl).await {
+            Ok(0) => {}
+            Ok(expired) => info!("Discarded {expired} abandoned upload(s)"),
+            Err(e) => warn!("Failed to discard abandoned uploads: {e}"),
+        }
+    }
+}
+
+/// Content of a blob - the body is streamed from the blob store, unless it is a config
+struct Blob {
+    /// Size of the (uncompressed) content, if it is known
+    size: Option<u64>,
+    body: Body,
+}
+
+/// Returns a blob, that is either referenced by a manifest or was uploaded
+async fn find_blob(state: &AppState, digest: &str) -> Result<Option<Blob>, Error> {
+    let (key, size, encoding) = match oci_manifest::Entity::find_blob(&state.db, digest).await? {
+        Some(ManifestBlob::Config(config)) => {
+            return Ok(Some(Blob {
+                size: Some(config.len() as u64),
+                body: Body::from(config),
+            }))
+        }
+        Some(ManifestBlob::Layer {
+            digest,
+            size,
+            encoding,
+        }) => (blobs::parse_digest(&digest)?, size, encoding),
+        None => match oci_blob::Entity::find_by_digest(&state.db, digest).await? {
+            Some(blob) => (oci::blob_key(digest)?, blob.size, blob.encoding),
+            None => return Ok(None),
+        },
+    };
+    let stored = state.blobs.stream(&key).await?;
+    let encoding = Encoding::from_column(encoding.as_deref())?;
+    Ok(Some(Blob {
+        size: size.map(|size| size as u64),
+        body: blobs::decompress_body(encoding, stored),
+    }))
+}
+
+/// Checks, if a blob is referenced by a manifest or was uploaded - without reading it
+async fn has_blob(state: &AppState, digest: &str) -> Result<bool, Error> {
+    Ok(oci_manifest::Entity::find_blob(&state.db, digest)
+        .await?
+        .is_some()
+        || oci_blob::Entity::find_by_digest(&state.db, digest)
+            .await?
+            .is_some())
+}
+
+/// Returns the content of a blob, that a pushed manifest references
+async fn referenced_blob(
+    state: &AppState,
+    descriptor: &oci::Descriptor,
+) -> Result<Vec<u8>, OciError> {
+    let blob = find_blob(state, &descriptor.digest).await?.ok_or_else(|| {
+        OciError::new(
+            StatusCode::BAD_REQUEST,
+            "MANIFEST_BLOB_UNKNOWN",
+            format!("blob {} was not uploaded", descriptor.digest),
+        )
+    })?;
+    let data = to_bytes(blob.body, usize::MAX)
+        .await
+        .map_err(std::io::Error::other)
+        .map_err(Error::from)?
+        .to_vec();
+    if data.len() as u64 != descriptor.size {
+        return Err(Error::InvalidManifest(format!(
+            "size of blob {} does not match its descriptor",
+            descriptor.digest
+        ))
+        .into());
+    }
+    Ok(data)
+}
+
+fn check_digest(digest: &str, data: &[u8]) -> Result<(), Error> {
+    let computed = oci::digest(data);
+    if computed != digest {
+        return Err(Error::DigestMismatch {
+            declared: digest.to_string(),
+            computed,
+        });
+    }
+    Ok(())
+}
+
+/// Offset of a chunk with the given `Content-Range` (`<start>-<end>`, both inclusive)
+fn chunk_offset(
+    range: Option<&str>,
+    length: usize,
+    upload: &oci_upload::Model,
+) -> Result<u64, Error> {
+    let invalid = || Error::InvalidRange {
+        expected: upload.size as u64,
+    };
+    let range = range.ok_or_else(invalid)?;
+    let range = range.strip_prefix("bytes ").unwrap_or(range);
+    let range = range.split('/').next().unwrap_or(range);
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    let start: u64 = start.trim().parse().map_err(|_| invalid())?;
+    let end: u64 = end.trim().parse().map_err(|_| invalid())?;
+    if end < start || end - start + 1 != length as u64 {
+        return Err(invalid());
+    }
+    Ok(start)
+}
+
+/// Response with the location and progress of an upload
+fn upload_progress(status: StatusCode, name: &str, upload: &oci_upload::Model) -> Response {
+    let headers = [
+        (
+            header::LOCATION,
+            format!("/v2/{name}/blobs/uploads/{}", upload.id),
+        ),
+        (header::RANGE, format!("0-{}", (upload.size - 1).max(0))),
+        (
+            HeaderName::from_static("docker-upload-uuid"),
+            upload.id.clone(),
+        ),
+        (header::CONTENT_LENGTH, "0".to_string()),
+    ];
+    (status, headers).into_response()
+}
+
+fn blob_created(name: &str, digest: &str) -> Response {
+    (
+        StatusCode::CREATED,
+        [
+            (header::LOCATION, format!("/v2/{name}/blobs/{digest}")),
+            (
+                HeaderName::from_static(CONTENT_DIGEST_HEADER),
+                digest.to_string(),
+            ),
+        ],
+    )
+        .into_response()
+}
+
+fn content_response(media_type: &str, digest: &str, content: Vec<u8>) -> Response {
+    let headers = [
+        (header::CONTENT_TYPE, media_type.to_string()),
+        (header::CONTENT_LENGTH, content.len().to_string()),
+        (
+            HeaderName::from_static(CONTENT_DIGEST_HEADER),
+            digest.to_string(),
+        ),
+    ];
+    (headers, Body::from(content)).into_response()
+}
+
+/// Truncates a listing to `size` entries and returns the last entry, if there are more
+fn truncate(entries: &mut Vec<String>, size: usize) -> Option<String> {
+    if entries.len() > size {
+        entries.truncate(size);
+        entries.last().cloned()
+    } else {
+        None
+    }
+}
+
+fn link_header(next: Option<String>) -> Vec<(HeaderName, String)> {
+    next.map(|link| (header::LINK, link)).into_iter().collect()
+}
+
+fn method_not_allowed() -> OciError {
+    OciError::new(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "UNSUPPORTED",
+        "the operation is not supported",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::entities::token, test_util::*};
+    use axum::{
+        body::to_bytes,
+        http::{Method, Request},
+        Router,
+    };
+    use base64::prelude::*;
+    use sea_orm::{ActiveModelTrait, Set};
+    use serde_json::Value;
+    use tower::util::ServiceExt;
+
+    /// Sends a request like an OCI client, with basic auth if a token is given
+    async fn oci_request(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        content_type: Option<&str>,
+        body: Vec<u8>,
+    ) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            let credentials = BASE64_STANDARD.encode(format!("oras:{token}"));
+            request = request.header(header::AUTHORIZATION, format!("Basic {credentials}"));
+        }
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::from(body)).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, bytes.to_vec())
+    }
+
+    fn error_code(body: &[u8]) -> String {
+        let body: Value = serde_json::from_slice(body).unwrap();
+        body["errors"][0]["code"].as_str().unwrap().to_string()
+    }
+
+    fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+        headers.get(name).unwrap().to_str().unwrap()
+    }
+
+    /// Manifest like ORAS creates it, including annotations, that the registry does not know about
+    fn oras_manifest(config: &[u8], wasm: &[u8]) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "schemaVersion": 2,
+            "mediaType": oci::MANIFEST_MEDIA_TYPE,
+            "artifactType": oci::ARTIFACT_TYPE,
+            "config": {
+                "mediaType": oci::CONFIG_MEDIA_TYPE,
+                "digest": oci::digest(config),
+                "size": config.len(),
+            },
+            "layers": [{
+                "mediaType": oci::WASM_MEDIA_TYPE,
+                "digest": oci::digest(wasm),
+                "size": wasm.len(),
+                "annotations": { "org.opencontainers.image.title": "counter.wasm" },
+            }],
+            "annotations": { "org.opencontainers.image.created": "2026-10-18T00:00:00Z" },
+        }))
+        .unwrap()
+    }
+
+    /// Uploads a blob in a session (POST + PUT), like most clients do
+    async fn upload_blob(app: &Router, name: &str, data: &[u8]) -> StatusCode {
+        let (status, headers, _) = oci_request(
+            app,
+            Method::POST,
+            &format!("/v2/{name}/blobs/uploads/"),
+            Some(TEST_TOKEN),
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let location = header_str(&headers, "location");
+        let (status, headers, _) = oci_request(
+            app,
+            Method::PUT,
+            &format!("{location}?digest={}", oci::digest(data)),
+            Some(TEST_TOKEN),
+            Some("application/octet-stream"),
+            data.to_vec(),
+        )
+        .await;
+        if status == StatusCode::CREATED {
+            assert_eq!(
+                header_str(&headers, CONTENT_DIGEST_HEADER),
+                oci::digest(data)
+            );
+        }
+        status
+    }
+
+    #[test]
+    fn parse_targets() {
+        let target = Target::parse("org/team/counter/manifests/1.0.0").unwrap();
+        assert_eq!(target.namespace, "org/team");
+        assert_eq!(target.repository, "counter");
+        assert_eq!(target.resource, Resource::Manifest("1.0.0"));
+        assert_eq!(
+            Target::parse("ns/repo/blobs/sha256:abc").unwrap().resource,
+            Resource::Blob("sha256:abc")
+        );
+        assert_eq!(
+            Target::parse("ns/repo/blobs/uploads/").unwrap().resource,
+            Resource::Upload(None)
+        );
+        assert_eq!(
+            Target::parse("ns/repo/blobs/uploads/1234")
+                .unwrap()
+                .resource,
+            Resource::Upload(Some("1234"))
+        );
+        assert_eq!(
+            Target::parse("ns/repo/tags/list").unwrap().resource,
+            Resource::Tags
+        );
+        assert_eq!(
+            Target::parse("ns/repo/referrers/sha256:abc")
+                .unwrap()
+                .resource,
+            Resource::Referrers("sha256:abc")
+        );
+        assert!(Target::parse("repo/manifests/1.0.0").is_err());
+        assert!(Target::parse("ns/repo/unknown").is_err());
+    }
+
+    #[tokio::test]
+    async fn version_check() {
+        let app = test_app().await;
+        let (status, headers, _) =
+            oci_request(&app, Method::GET, "/v2/", None, None, Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            header_str(&headers, "docker-distribution-api-version"),
+            "registry/2.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn push_and_pull() {
+        let app = test_app().await;
+        let wasm = b"\0asm-counter";
+        let config = oci::package_config(&test_pkg("counter", wasm));
+        let name = "borderless/counter";
+
+        // Clients check for existing blobs first
+        let blob_uri = format!("/v2/{name}/blobs/{}", oci::digest(wasm));
+        let (status, _, _) =
+            oci_request(&app, Method::HEAD, &blob_uri, None, None, Vec::new()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        assert_eq!(upload_blob(&app, name, &config).await, StatusCode::CREATED);
+        let (status, _, _) = oci_request(
+            &app,
+            Method::POST,
+            &format!("/v2/{name}/blobs/uploads/?digest={}", oci::digest(wasm)),
+            Some(TEST_TOKEN),
+            Some("application/octet-stream"),
+            wasm.to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let (status, headers, _) =
+            oci_request(&app, Method::HEAD, &blob_uri, None, None, Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            header_str(&headers, "content-length"),
+            wasm.len().to_string()
+        );
+
+        let manifest = oras_manifest(&config, wasm);
+        let (status, headers, _) = oci_request(
+            &app,
+            Method::PUT,
+            &format!("/v2/{name}/manifests/1.0.0"),
+            Some(TEST_TOKEN),
+            Some(oci::MANIFEST_MEDIA_TYPE),
+            manifest.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let digest = oci::digest(&manifest);
+        assert_eq!(header_str(&headers, CONTENT_DIGEST_HEADER), digest);
+        assert_eq!(
+            header_str(&headers, "location"),
+            format!("/v2/{name}/manifests/{digest}")
+        );
+
+        // The pushed manifest is served byte for byte - by tag and by digest
+        for reference in ["1.0.0", "latest", digest.as_str()] {
+            let uri = format!("/v2/{name}/manifests/{reference}");
+            let (status, headers, body) =
+                oci_request(&app, Method::GET, &uri, None, None, Vec::new()).await;
+            assert_eq!(status, StatusCode::OK, "{reference}");
+            assert_eq!(body, manifest);
+            assert_eq!(
+                header_str(&headers, "content-type"),
+                oci::MANIFEST_MEDIA_TYPE
+            );
+            assert_eq!(header_str(&headers, CONTENT_DIGEST_HEADER), digest);
+        }
+        let (status, headers, _) = oci_request(
+            &app,
+            Method::HEAD,
+            &format!("/v2/{name}/manifests/1.0.0"),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(header_str(&headers, CONTENT_DIGEST_HEADER), digest);
+
+        let (status, _, body) =
+            oci_request(&app, Method::GET, &blob_uri, None, None, Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, wasm);
+        let config_uri = format!("/v2/{name}/blobs/{}", oci::digest(&config));
+        let (_, _, body) =
+            oci_request(&app, Method::GET, &config_uri, None, None, Vec::new()).await;
+        assert_eq!(body, config);
+
+        // The package is a regular registry package
+        let (status, summary) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/packages/borderless/counter:1.0.0",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(summary["name"], "counter");
+
+        // Pushing the same manifest again is idempotent
+        let (status, headers, _) = oci_request(
+            &app,
+            Method::PUT,
+            &format!("/v2/{name}/manifests/1.0.0"),
+            Some(TEST_TOKEN),
+            Some(oci::MANIFEST_MEDIA_TYPE),
+            manifest,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(header_str(&headers, CONTENT_DIGEST_HEADER), digest);
+    }
+
+    #[tokio::test]
+    async fn registry_publishes_are_pullable() {
+        let app = test_app().await;
+        let wasm = b"\0asm-counter";
+        publish(&app, "borderless/counter:1.0.0", &test_pkg("counter", wasm)).await;
+
+        let (status, headers, body) = oci_request(
+            &app,
+            Method::GET,
+            "/v2/borderless/counter/manifests/1.0.0",
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            header_str(&headers, CONTENT_DIGEST_HEADER),
+            oci::digest(&body)
+        );
+        let manifest: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(manifest["artifactType"], oci::ARTIFACT_TYPE);
+        assert_eq!(manifest["layers"][0]["digest"], oci::digest(wasm));
+
+        let config_digest = manifest["config"]["digest"].as_str().unwrap();
+        let (_, _, config) = oci_request(
+            &app,
+            Method::GET,
+            &format!("/v2/borderless/counter/blobs/{config_digest}"),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        let pkg = oci::package_from_config(&config, wasm).unwrap();
+        assert_eq!(pkg.name, "counter");
+    }
+
+    #[tokio::test]
+    async fn listings() {
+        let app = test_app().await;
+        for (oci, wasm) in [
+            ("borderless/counter:1.0.0", "\0asm-1"),
+            ("borderless/counter:1.1.0", "\0asm-2"),
+            ("borderless/counter:2.0.0", "\0asm-3"),
+            ("acme/tokens:0.1.0", "\0asm-4"),
+        ] {
+            publish(&app, oci, &test_pkg("pkg", wasm.as_bytes())).await;
+        }
+
+        let (status, headers, body) = oci_request(
+            &app,
+            Method::GET,
+            "/v2/borderless/counter/tags/list?n=2",
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let list: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            list,
+            json!({ "name": "borderless/counter", "tags": ["1.0.0", "1.1.0"] })
+        );
+        assert_eq!(
+            header_str(&headers, "link"),
+            "</v2/borderless/counter/tags/list?n=2&last=1.1.0>; rel=\"next\""
+        );
+        let (_, headers, body) = oci_request(
+            &app,
+            Method::GET,
+            "/v2/borderless/counter/tags/list?n=2&last=1.1.0",
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        let list: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(list["tags"], json!(["2.0.0"]));
+        assert!(headers.get("link").is_none());
+
+        let (_, _, body) =
+            oci_request(&app, Method::GET, "/v2/_catalog", None, None, Vec::new()).await;
+        let catalog: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            catalog["repositories"],
+            json!(["acme/tokens", "borderless/counter"])
+        );
+        let (_, _, body) = oci_request(
+            &app,
+            Method::GET,
+            "/v2/_catalog?n=1&last=acme/tokens",
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        let catalog: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(catalog["repositories"], json!(["borderless/counter"]));
+
+        let (status, _, body) = oci_request(
+            &app,
+            Method::GET,
+            "/v2/borderless/unknown/tags/list",
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error_code(&body), "NAME_UNKNOWN");
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let app = test_app().await;
+        let name = "borderless/counter";
+        let fetch = |uri: String| {
+            let app = app.clone();
+            async move { oci_request(&app, Method::GET, &uri, None, None, Vec::new()).await }
+        };
+
+        let (status, _, body) = fetch(format!("/v2/{name}/manifests/1.0.0")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error_code(&body), "MANIFEST_UNKNOWN");
+        let (status, _, body) = fetch(format!("/v2/{name}/blobs/{}", oci::digest(b"x"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error_code(&body), "BLOB_UNKNOWN");
+        let (status, _, body) = fetch("/v2/counter/manifests/1.0.0".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_code(&body), "NAME_INVALID");
+
+        // Pushes require credentials
+        let uploads = format!("/v2/{name}/blobs/uploads/");
+        let (status, headers, body) =
+            oci_request(&app, Method::POST, &uploads, None, None, Vec::new()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error_code(&body), "UNAUTHORIZED");
+        assert!(header_str(&headers, "www-authenticate").starts_with("Basic"));
+
+        // The digest must match the uploaded content
+        let (status, _, body) = oci_request(
+            &app,
+            Method::POST,
+            &format!("{uploads}?digest={}", oci::digest(b"other")),
+            Some(TEST_TOKEN),
+            None,
+            b"data".to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_code(&body), "DIGEST_INVALID");
+        let (status, _, body) = oci_request(
+            &app,
+            Method::PUT,
+            &format!("{uploads}unknown?digest={}", oci::digest(b"data")),
+            Some(TEST_TOKEN),
+            None,
+            b"data".to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error_code(&body), "BLOB_UPLOAD_UNKNOWN");
+
+        // Manifests must reference uploaded blobs
+        let wasm = b"\0asm-counter";
+        let config = oci::package_config(&test_pkg("counter", wasm));
+        let manifest_uri = format!("/v2/{name}/manifests/1.0.0");
+        let (status, _, body) = oci_request(
+            &app,
+            Method::PUT,
+            &manifest_uri,
+            Some(TEST_TOKEN),
+            Some(oci::MANIFEST_MEDIA_TYPE),
+            oras_manifest(&config, wasm),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_code(&body), "MANIFEST_BLOB_UNKNOWN");
+
+        // .. and describe a package
+        let (status, _, body) = oci_request(
+            &app,
+            Method::PUT,
+            &manifest_uri,
+            Some(TEST_TOKEN),
+            Some(oci::MANIFEST_MEDIA_TYPE),
+            br#"{"schemaVersion":2,"config":{},"layers":[]}"#.to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_code(&body), "MANIFEST_INVALID");
+
+        // Published versions are immutable
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm-old"),
+        )
+        .await;
+        upload_blob(&app, name, &config).await;
+        upload_blob(&app, name, wasm).await;
+        let (status, _, body) = oci_request(
+            &app,
+            Method::PUT,
+            &manifest_uri,
+            Some(TEST_TOKEN),
+            Some(oci::MANIFEST_MEDIA_TYPE),
+            oras_manifest(&config, wasm),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error_code(&body), "DENIED");
+    }
+
+    /// Starts an upload session and returns its location
+    async fn start_upload(app: &Router, name: &str) -> String {
+        let (status, headers, _) = oci_request(
+            app,
+            Method::POST,
+            &format!("/v2/{name}/blobs/uploads/"),
+            Some(TEST_TOKEN),
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        header_str(&headers, "location").to_string()
+    }
+
+    /// Appends a chunk, with a content range if `offset` is given
+    async fn patch_chunk(
+        app: &Router,
+        location: &str,
+        offset: Option<usize>,
+        chunk: &[u8],
+    ) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let mut request = Request::builder()
+            .method(Method::PATCH)
+            .uri(location)
+            .header(header::AUTHORIZATION, format!("Bearer {TEST_TOKEN}"))
+            .header(header::CONTENT_TYPE, "application/octet-stream");
+        if let Some(offset) = offset {
+            let range = format!("{offset}-{}", offset + chunk.len() - 1);
+            request = request.header(header::CONTENT_RANGE, range);
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::from(chunk.to_vec())).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, bytes.to_vec())
+    }
+
+    #[tokio::test]
+    async fn chunked_upload() {
+        let app = test_app().await;
+        let name = "borderless/counter";
+        let blob = b"\0asm-a-rather-large-contract";
+        let location = start_upload(&app, name).await;
+
+        let (status, headers, _) = patch_chunk(&app, &location, Some(0), &blob[..10]).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(header_str(&headers, "range"), "0-9");
+        assert_eq!(header_str(&headers, "location"), location);
+
+        // Chunks must continue, where the upload stopped
+        let (status, _, body) = patch_chunk(&app, &location, Some(5), &blob[5..15]).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(error_code(&body), "BLOB_UPLOAD_INVALID");
+
+        // A client, that lost track of the upload, asks for its progress
+        let (status, headers, _) = oci_request(
+            &app,
+            Method::GET,
+            &location,
+            Some(TEST_TOKEN),
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(header_str(&headers, "range"), "0-9");
+
+        // Streamed chunks have no range
+        let (status, headers, _) = patch_chunk(&app, &location, None, &blob[10..20]).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(header_str(&headers, "range"), "0-19");
+
+        // The final request carries the last chunk
+        let (status, _, _) = oci_request(
+            &app,
+            Method::PUT,
+            &format!("{location}?digest={}", oci::digest(blob)),
+            Some(TEST_TOKEN),
+            Some("application/octet-stream"),
+            blob[20..].to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let (status, _, body) = oci_request(
+            &app,
+            Method::GET,
+            &format!("/v2/{name}/blobs/{}", oci::digest(blob)),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, blob);
+
+        // The session is gone
+        let (status, _, body) = patch_chunk(&app, &location, None, b"more").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error_code(&body), "BLOB_UPLOAD_UNKNOWN");
+    }
+
+    #[tokio::test]
+    async fn large_chunks() {
+        let (app, state) = test_app_with_state().await;
+        let name = "borderless/counter";
+        // Larger than the default body limit of axum
+        let blob = vec![7u8; 3 * 1024 * 1024];
+        let location = start_upload(&app, name).await;
+        let (status, _, _) = patch_chunk(&app, &location, Some(0), &blob).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let (status, _, _) = oci_request(
+            &app,
+            Method::PUT,
+            &format!("{location}?digest={}", oci::digest(&blob)),
+            Some(TEST_TOKEN),
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _, body) = oci_request(
+            &app,
+            Method::GET,
+            &format!("/v2/{name}/blobs/{}", oci::digest(&blob)),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, blob);
+
+        // The limit is configurable
+        let app = crate::router(state.with_max_upload_size(1024 * 1024));
+        let location = start_upload(&app, name).await;
+        let (status, _, _) = patch_chunk(&app, &location, Some(0), &blob).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn uploads_live_in_the_blob_store() {
+        let (app, state) = test_app_with_state().await;
+        let name = "borderless/counter";
+        let blob = b"\0asm-counter".repeat(64);
+        let digest = oci::digest(&blob);
+        assert_eq!(upload_blob(&app, name, &blob).await, StatusCode::CREATED);
+
+        let uploaded = oci_blob::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(uploaded.data, None);
+        assert_eq!(uploaded.size, Some(blob.len() as i64));
+        assert_eq!(uploaded.encoding.as_deref(), Some("zstd"));
+        let key = oci::blob_key(&digest).unwrap();
+        assert!(state.blobs.contains(&key).await.unwrap());
+
+        // Blobs uploaded before the blob store existed carry their content themselves
+        oci_blob::ActiveOciBlob {
+            id: Set(uploaded.id),
+            data: Set(Some(blob.clone())),
+            size: Set(None),
+            encoding: Set(None),
+            ..Default::default()
+        }
+        .update(&state.db)
+        .await
+        .unwrap();
+        state.blobs.delete(&key).await.unwrap();
+        let moved = oci_blob::Entity::move_legacy_blobs(&state.db, state.blobs.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(moved, 1);
+
+        let (status, headers, body) = oci_request(
+            &app,
+            Method::GET,
+            &format!("/v2/{name}/blobs/{digest}"),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            header_str(&headers, "content-length"),
+            blob.len().to_string()
+        );
+        assert_eq!(body, blob);
+    }
+
+    #[tokio::test]
+    async fn cancel_upload() {
+        let app = test_app().await;
+        let location = start_upload(&app, "borderless/counter").await;
+        patch_chunk(&app, &location, Some(0), b"\0asm").await;
+
+        let (status, _, _) = oci_request(
+            &app,
+            Method::DELETE,
+            &location,
+            Some(TEST_TOKEN),
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _, _) = patch_chunk(&app, &location, Some(4), b"-more").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn uploads_require_a_role_in_claimed_namespaces() {
+        let (app, state) = test_app_with_state().await;
+        let bob = token::NewToken {
+            name: "bob".to_string(),
+            subject: "bob".to_string(),
+            scopes: vec![Scope::Publish],
+            namespaces: None,
+            expires_at: None,
+        };
+        token::Entity::create(&state.db, bob, "brt_bob")
+            .await
+            .unwrap();
+        assert_eq!(
+            publish(
+                &app,
+                "borderless/counter:1.0.0",
+                &test_pkg("counter", b"\0asm")
+            )
+            .await,
+            StatusCode::CREATED
+        );
+
+        let location = start_upload(&app, "borderless/counter").await;
+        let digest = oci::digest(b"\0asm");
+        for (method, uri) in [
+            (
+                Method::POST,
+                "/v2/borderless/counter/blobs/uploads/".to_string(),
+            ),
+            (
+                Method::POST,
+                format!("/v2/borderless/counter/blobs/uploads/?digest={digest}"),
+            ),
+            (Method::GET, location.clone()),
+            (Method::PATCH, location.clone()),
+            (Method::PUT, format!("{location}?digest={digest}")),
+            (Method::DELETE, location.clone()),
+        ] {
+            let (status, _, body) =
+                oci_request(&app, method, &uri, Some("brt_bob"), None, b"\0asm".to_vec()).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{uri}");
+            assert_eq!(error_code(&body), "DENIED");
+        }
+
+        // Unclaimed namespaces are claimed by the first manifest
+        let (status, _, _) = oci_request(
+            &app,
+            Method::POST,
+            "/v2/bob/counter/blobs/uploads/",
+            Some("brt_bob"),
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn uploads_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::db::DbConfig {
+            path: dir.path().join("registry.db").to_string_lossy().to_string(),
+            max_connections: 2,
+            min_connections: 1,
+        };
+        let blob = b"\0asm-uploaded-over-a-flaky-link";
+
+        let db = crate::db::setup_database(&config).await.unwrap();
+        let admin = crate::db::entities::token::NewToken {
+            name: "ci".to_string(),
+            subject: TEST_SUBJECT.to_string(),
+            scopes: vec![Scope::Admin],
+            namespaces: None,
+            expires_at: None,
+        };
+        crate::db::entities::token::Entity::create(&db, admin, TEST_TOKEN)
+            .await
+            .unwrap();
+        let blobs: std::sync::Arc<dyn crate::blobs::BlobStore> = std::sync::Arc::new(
+            crate::blobs::KvBlobStore::open(&dir.path().join("blobs")).unwrap(),
+        );
+        let app = crate::router(AppState::new(db.clone(), blobs.clone()));
+        let location = start_upload(&app, "borderless/counter").await;
+        patch_chunk(&app, &location, Some(0), &blob[..8]).await;
+        drop(app);
+        db.close().await.unwrap();
+
+        let db = crate::db::setup_database(&config).await.unwrap();
+        let app = crate::router(AppState::new(db, blobs));
+        let (status, headers, _) = patch_chunk(&app, &location, Some(8), &blob[8..]).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(
+            header_str(&headers, "range"),
+            format!("0-{}", blob.len() - 1)
+        );
+        let (status, _, _) = oci_request(
+            &app,
+            Method::PUT,
+            &format!("{location}?digest={}", oci::digest(blob)),
+            Some(TEST_TOKEN),
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn abandoned_uploads_expire() {
+        let (app, state) = test_app_with_state().await;
+        let location = start_upload(&app, "borderless/counter").await;
+        patch_chunk(&app, &location, Some(0), b"\0asm").await;
+
+        let hour_ago = chrono::Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(
+            oci_upload::Entity::expire(&state.db, hour_ago)
+                .await
+                .unwrap(),
+            0
+        );
+        let later = chrono::Utc::now() + chrono::Duration::seconds(1);
+        assert_eq!(
+            oci_upload::Entity::expire(&state.db, later).await.unwrap(),
+            1
+        );
+
+        let (status, _, _) = patch_chunk(&app, &location, Some(4), b"-more").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    /// Artifact manifest like `oras attach` creates it
+    fn referrer_manifest(subject: &[u8], artifact_type: &str, layer: &[u8]) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "schemaVersion": 2,
+            "mediaType": oci::MANIFEST_MEDIA_TYPE,
+            "artifactType": artifact_type,
+            "config": {
+                "mediaType": "application/vnd.oci.empty.v1+json",
+                "digest": oci::digest(b"{}"),
+                "size": 2,
+            },
+            "layers": [{
+                "mediaType": "application/octet-stream",
+                "digest": oci::digest(layer),
+                "size": layer.len(),
+            }],
+            "subject": {
+                "mediaType": oci::MANIFEST_MEDIA_TYPE,
+                "digest": oci::digest(subject),
+                "size": subject.len(),
+            },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn referrers() {
+        let app = test_app().await;
+        let name = "borderless/counter";
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm-counter"),
+        )
+        .await;
+        let (_, _, package) = oci_request(
+            &app,
+            Method::GET,
+            &format!("/v2/{name}/manifests/1.0.0"),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        let subject = oci::digest(&package);
+        let referrers_uri = format!("/v2/{name}/referrers/{subject}");
+
+        // Nothing is attached yet
+        let (status, headers, body) =
+            oci_request(&app, Method::GET, &referrers_uri, None, None, Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(header_str(&headers, "content-type"), oci::INDEX_MEDIA_TYPE);
+        let index: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(index["manifests"], json!([]));
+
+        assert_eq!(upload_blob(&app, name, b"{}").await, StatusCode::CREATED);
+        let mut pushed = Vec::new();
+        for (artifact_type, layer) in [
+            (
+                "application/vnd.dev.cosign.simplesigning.v1+json",
+                &b"signature"[..],
+            ),
+            ("application/spdx+json", &b"sbom"[..]),
+        ] {
+            assert_eq!(upload_blob(&app, name, layer).await, StatusCode::CREATED);
+            let manifest = referrer_manifest(&package, artifact_type, layer);
+            let digest = oci::digest(&manifest);
+            let (status, headers, _) = oci_request(
+                &app,
+                Method::PUT,
+                &format!("/v2/{name}/manifests/{digest}"),
+                Some(TEST_TOKEN),
+                Some(oci::MANIFEST_MEDIA_TYPE),
+                manifest.clone(),
+            )
+            .await;
+            assert_eq!(status, StatusCode::CREATED);
+            assert_eq!(header_str(&headers, "oci-subject"), subject);
+            pushed.push((digest, manifest));
+        }
+
+        let (_, headers, body) =
+            oci_request(&app, Method::GET, &referrers_uri, None, None, Vec::new()).await;
+        assert!(headers.get("oci-filters-applied").is_none());
+        let index: Value = serde_json::from_slice(&body).unwrap();
+        let manifests = index["manifests"].as_array().unwrap();
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(manifests[0]["digest"], pushed[0].0);
+        assert_eq!(manifests[1]["artifactType"], "application/spdx+json");
+
+        let (_, headers, body) = oci_request(
+            &app,
+            Method::GET,
+            &format!("{referrers_uri}?artifactType=application/spdx%2Bjson"),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(header_str(&headers, "oci-filters-applied"), "artifactType");
+        let index: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(index["manifests"].as_array().unwrap().len(), 1);
+        assert_eq!(index["manifests"][0]["digest"], pushed[1].0);
+
+        // Attached artifacts are served by their digest
+        let (status, _, body) = oci_request(
+            &app,
+            Method::GET,
+            &format!("/v2/{name}/manifests/{}", pushed[0].0),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, pushed[0].1);
+    }
+
+    #[tokio::test]
+    async fn referrer_errors() {
+        let app = test_app().await;
+        let name = "borderless/counter";
+        assert_eq!(upload_blob(&app, name, b"{}").await, StatusCode::CREATED);
+        assert_eq!(upload_blob(&app, name, b"sbom").await, StatusCode::CREATED);
+
+        // The subject must exist in the repository
+        let manifest = referrer_manifest(b"missing", "application/spdx+json", b"sbom");
+        let uri = format!("/v2/{name}/manifests/{}", oci::digest(&manifest));
+        let (status, _, body) = oci_request(
+            &app,
+            Method::PUT,
+            &uri,
+            Some(TEST_TOKEN),
+            Some(oci::MANIFEST_MEDIA_TYPE),
+            manifest.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_code(&body), "MANIFEST_INVALID");
+
+        // Referrers are pushed by digest, not by tag
+        let (status, _, body) = oci_request(
+            &app,
+            Method::PUT,
+            &format!("/v2/{name}/manifests/sbom"),
+            Some(TEST_TOKEN),
+            Some(oci::MANIFEST_MEDIA_TYPE),
+            manifest,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_code(&body), "MANIFEST_INVALID");
+
+        let (status, _, body) = oci_request(
+            &app,
+            Method::GET,
+            &format!("/v2/{name}/referrers/latest"),
+            None,
+            None,
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_code(&body), "DIGEST_INVALID");
+    }
+}
@@ -0,0 +1,100 @@
+//! Cursor based pagination for listings
+//!
+//! A cursor is the (url-safe base64 encoded) key of the last item of the previous page.
+//! Listings continue strictly after this key, so pages stay stable while new packages are published.
+use base64::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Default page size of all listings
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Upper bound for the page size of all listings
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Pagination parameters of a listing
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    /// Opaque cursor from a previous page
+    pub cursor: Option<String>,
+    /// Maximum number of items per page
+    pub limit: Option<usize>,
+}
+
+impl PageQuery {
+    /// Page size, clamped to `1..=MAX_PAGE_SIZE`
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    /// Decodes the key of the cursor
+    pub fn after<K: DeserializeOwned>(&self) -> Result<Option<K>, Error> {
+        let Some(cursor) = &self.cursor else {
+            return Ok(None);
+        };
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| Error::InvalidCursor)?;
+        let key = serde_json::from_slice(&bytes).map_err(|_| Error::InvalidCursor)?;
+        Ok(Some(key))
+    }
+}
+
+/// A single page of a listing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor of the next page - `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from up to `limit + 1` items
+    ///
+    /// The additional item only signals, that there is a next page - it is not returned.
+    pub fn new<K: Serialize>(mut items: Vec<T>, limit: usize, key: impl Fn(&T) -> K) -> Self {
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items.last().map(|last| encode_cursor(&key(last)))
+        } else {
+            None
+        };
+        Page { items, next_cursor }
+    }
+}
+
+fn encode_cursor<K: Serialize>(key: &K) -> String {
+    let json = serde_json::to_vec(key).expect("cursor keys are serializable");
+    BASE64_URL_SAFE_NO_PAD.encode(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_roundtrip() {
+        let page = Page::new(vec![1, 2, 3], 2, |i| format!("key-{i}"));
+        assert_eq!(page.items, vec![1, 2]);
+        let query = PageQuery {
+            cursor: page.next_cursor,
+            limit: None,
+        };
+        assert_eq!(query.after::<String>().unwrap(), Some("key-2".to_string()));
+
+        let last = Page::new(vec![1, 2], 2, |i| *i);
+        assert!(last.next_cursor.is_none());
+    }
+
+    #[test]
+    fn invalid_cursor() {
+        let query = PageQuery {
+            cursor: Some("not base64!".to_string()),
+            limit: None,
+        };
+        assert!(matches!(query.after::<String>(), Err(Error::InvalidCursor)));
+    }
+}
@@ -0,0 +1,214 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use semver::{Comparator, Op, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::{db::entities::index, error::Error, extractor::RepoPath, AppState};
+
+/// Query parameters of the resolve route
+#[derive(Debug, Deserialize)]
+pub struct ResolveQuery {
+    /// SemVer requirement, e.g. `^1.2`, `~0.4.1` or `>=2, <3`
+    pub req: String,
+    /// Also consider prerelease versions
+    #[serde(default)]
+    pub prerelease: bool,
+}
+
+/// The highest version, that matches a requirement
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Resolved {
+    pub oci: String,
+    pub namespace: String,
+    pub repository: String,
+    pub version: String,
+    pub digest: String,
+}
+
+// GET resolve a version requirement to the highest matching version
+#[instrument]
+pub async fn resolve(
+    State(state): State<AppState>,
+    repo: RepoPath,
+    Query(query): Query<ResolveQuery>,
+) -> Result<Json<Resolved>, Error> {
+    let req = VersionReq::parse(&query.req)?;
+    let tags = index::Entity::find_tags(&state.db, &repo.namespace, &repo.repository).await?;
+
+    let (version, tag) = tags
+        .into_iter()
+        .filter(|t| !t.yank)
+        // Keyword tags are not versions
+        .filter_map(|t| Version::parse(&t.tag).ok().map(|v| (v, t)))
+        .filter(|(v, _)| matches_req(&req, v, query.prerelease))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .ok_or_else(|| Error::NoMatchingVersion {
+            repository: format!("{}/{}", repo.namespace, repo.repository),
+            req: query.req.clone(),
+        })?;
+
+    info!(
+        "Resolved {}/{} {} to {}",
+        repo.namespace, repo.repository, query.req, version
+    );
+    Ok(Json(Resolved {
+        oci: format!("{}/{}:{}", repo.namespace, repo.repository, tag.tag),
+        namespace: repo.namespace,
+        repository: repo.repository,
+        version: version.to_string(),
+        digest: tag.digest,
+    }))
+}
+
+/// Checks if a version matches the requirement
+///
+/// Prereleases follow semver's own rules, so they only match comparators, that name a prerelease
+/// of the same version. If prereleases are explicitly requested, a prerelease also matches,
+/// if its release version matches and it is not below the lower bound of any comparator -
+/// e.g. `^1.2.0` accepts `1.3.0-rc.1`, but not `1.2.0-alpha`.
+pub fn matches_req(req: &VersionReq, version: &Version, prerelease: bool) -> bool {
+    if req.matches(version) {
+        return true;
+    }
+    if version.pre.is_empty() || !prerelease {
+        return false;
+    }
+    let release = Version::new(version.major, version.minor, version.patch);
+    req.matches(&release)
+        && req
+            .comparators
+            .iter()
+            .all(|c| above_lower_bound(c, version))
+}
+
+/// Checks that a version is not below the lower bound of a comparator
+fn above_lower_bound(comparator: &Comparator, version: &Version) -> bool {
+    match comparator.op {
+        Op::Less | Op::LessEq => true,
+        _ => {
+            let mut lower = Version::new(
+                comparator.major,
+                comparator.minor.unwrap_or(0),
+                comparator.patch.unwrap_or(0),
+            );
+            lower.pre = comparator.pre.clone();
+            *version >= lower
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    async fn app_with_versions(versions: &[&str]) -> axum::Router {
+        let app = test_app().await;
+        for version in versions {
+            let pkg = test_pkg("counter", format!("\0asm-{version}").as_bytes());
+            let status = publish(&app, &format!("org/team/counter:{version}"), &pkg).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+        app
+    }
+
+    #[tokio::test]
+    async fn resolves_highest_matching_version() {
+        let app = app_with_versions(&["1.2.0", "1.2.7", "1.10.0", "2.0.0", "0.4.1", "0.4.9"]).await;
+        let cases = [
+            ("^1.2", "1.10.0"),
+            ("~1.2", "1.2.7"),
+            ("~0.4.1", "0.4.9"),
+            (">=2, <3", "2.0.0"),
+            ("<1.10", "1.2.7"),
+        ];
+        for (req, expected) in cases {
+            let uri = format!(
+                "/api/v0/resolve/org/team/counter?req={}",
+                urlencoding::encode(req)
+            );
+            let (status, body) = send_json(&app, Method::GET, &uri, None).await;
+            assert_eq!(status, StatusCode::OK, "{req}");
+            assert_eq!(body["version"], expected, "{req}");
+            assert_eq!(body["namespace"], "org/team");
+            assert_eq!(body["oci"], format!("org/team/counter:{expected}"));
+            assert_eq!(body["digest"].as_str().unwrap().len(), 64);
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_yanked_versions() {
+        let app = app_with_versions(&["1.0.0", "1.1.0"]).await;
+        let body = json!({ "reason": "broken" });
+        send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/yank/org/team/counter:1.1.0",
+            Some(body),
+        )
+        .await;
+
+        let uri = "/api/v0/resolve/org/team/counter?req=%5E1";
+        let (_, body) = send_json(&app, Method::GET, uri, None).await;
+        assert_eq!(body["version"], "1.0.0");
+    }
+
+    #[tokio::test]
+    async fn prereleases_are_opt_in() {
+        let app = app_with_versions(&["1.0.0", "1.1.0-rc.1"]).await;
+
+        let uri = "/api/v0/resolve/org/team/counter?req=%5E1";
+        let (_, body) = send_json(&app, Method::GET, uri, None).await;
+        assert_eq!(body["version"], "1.0.0");
+
+        let uri = "/api/v0/resolve/org/team/counter?req=%5E1&prerelease=true";
+        let (_, body) = send_json(&app, Method::GET, uri, None).await;
+        assert_eq!(body["version"], "1.1.0-rc.1");
+    }
+
+    #[tokio::test]
+    async fn no_match_and_invalid_requirement() {
+        let app = app_with_versions(&["1.0.0"]).await;
+
+        let uri = "/api/v0/resolve/org/team/counter?req=%5E2";
+        let (status, _) = send_json(&app, Method::GET, uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let uri = "/api/v0/resolve/org/team/counter?req=not-a-req";
+        let (status, _) = send_json(&app, Method::GET, uri, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn prerelease_matching() {
+        let req = VersionReq::parse("^1.2").unwrap();
+        let rc = Version::parse("1.3.0-rc.1").unwrap();
+        assert!(!matches_req(&req, &rc, false));
+        assert!(matches_req(&req, &rc, true));
+        assert!(matches_req(&req, &Version::parse("1.2.5").unwrap(), false));
+
+        // A prerelease of the lower bound is older than the bound itself
+        let req = VersionReq::parse("^1.2.0").unwrap();
+        let alpha = Version::parse("1.2.0-alpha").unwrap();
+        assert!(!matches_req(&req, &alpha, false));
+        assert!(!matches_req(&req, &alpha, true));
+        assert!(matches_req(
+            &req,
+            &Version::parse("1.2.1-alpha").unwrap(),
+            true
+        ));
+
+        // Requirements, that name a prerelease, follow semver's own rules
+        let req = VersionReq::parse(">=1.2.0-alpha").unwrap();
+        assert!(matches_req(
+            &req,
+            &Version::parse("1.2.0-beta").unwrap(),
+            false
+        ));
+    }
+}
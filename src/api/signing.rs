@@ -0,0 +1,513 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{ConnectionTrait, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::{
+    auth::{Principal, Role, Scope},
+    db::entities::{
+        key_revocation, key_rotation, namespace_policy,
+        signature::{self, SignatureInfo},
+        signing_key::{self, KeyValidity},
+    },
+    error::Error,
+    extractor::{Digest, NamespacePath},
+    signing::{fingerprint, parse_public_key},
+    AppState,
+};
+
+/// Body of a key registration
+#[derive(Debug, Deserialize)]
+pub struct KeyRegistration {
+    /// Base64 encoded ed25519 public key
+    pub public_key: String,
+    #[serde(flatten)]
+    pub validity: KeyValidity,
+}
+
+/// Body of a key revocation
+#[derive(Debug, Deserialize)]
+pub struct KeyRevocation {
+    pub fingerprint: String,
+    pub reason: String,
+    /// Signatures since this point in time are untrusted - defaults to the time of the revocation
+    #[serde(default)]
+    pub compromised_since: Option<DateTime<Utc>>,
+}
+
+/// Body of a key rotation
+#[derive(Debug, Deserialize)]
+pub struct KeyRotation {
+    /// Fingerprint of the key, that is replaced
+    pub fingerprint: String,
+    /// Base64 encoded ed25519 public key of the successor
+    pub public_key: String,
+    /// Seconds, that the old key stays valid after the rotation
+    #[serde(default)]
+    pub overlap_secs: u32,
+}
+
+/// A signing key and its revocation
+#[derive(Debug, Serialize)]
+pub struct KeyInfo {
+    #[serde(flatten)]
+    pub key: signing_key::Model,
+    pub revocation: Option<key_revocation::Model>,
+}
+
+/// Result of a key rotation
+#[derive(Debug, Serialize)]
+pub struct RotationInfo {
+    pub old: signing_key::Model,
+    pub new: signing_key::Model,
+    pub rotation: key_rotation::Model,
+}
+
+/// Publish policy of a namespace
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Policy {
+    pub require_signature: bool,
+}
+
+// GET list the signing keys of a namespace
+#[instrument]
+pub async fn list_keys(
+    State(state): State<AppState>,
+    NamespacePath(namespace): NamespacePath,
+) -> Result<Json<Vec<KeyInfo>>, Error> {
+    let keys = signing_key::Entity::find_for_namespace(&state.db, &namespace)
+        .await?
+        .into_iter()
+        .map(|(key, revocation)| KeyInfo { key, revocation })
+        .collect();
+    Ok(Json(keys))
+}
+
+// PUT register a signing key for a namespace
+#[instrument(skip(principal))]
+pub async fn register_key(
+    State(state): State<AppState>,
+    principal: Principal,
+    NamespacePath(namespace): NamespacePath,
+    Json(registration): Json<KeyRegistration>,
+) -> Result<(StatusCode, Json<signing_key::Model>), Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Owner, &namespace)
+        .await?;
+    let validity = registration.validity;
+    if let (Some(from), Some(until)) = (validity.valid_from, validity.valid_until) {
+        if until <= from {
+            return Err(Error::InvalidValidity);
+        }
+    }
+    let public_key = parse_public_key(&registration.public_key)?;
+    let (key, created) = signing_key::Entity::register(
+        &state.db,
+        &namespace,
+        &registration.public_key,
+        &fingerprint(&public_key),
+        validity,
+        &principal.subject,
+    )
+    .await?;
+    if !created {
+        return Ok((StatusCode::OK, Json(key)));
+    }
+    info!("Registered key {} for {}", key.fingerprint, namespace);
+    Ok((StatusCode::CREATED, Json(key)))
+}
+
+// DELETE revoke a signing key of a namespace
+#[instrument(skip(principal))]
+pub async fn revoke_key(
+    State(state): State<AppState>,
+    principal: Principal,
+    NamespacePath(namespace): NamespacePath,
+    Json(request): Json<KeyRevocation>,
+) -> Result<Json<key_revocation::Model>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Owner, &namespace)
+        .await?;
+    if request.compromised_since.is_some_and(|t| t > Utc::now()) {
+        return Err(Error::InvalidValidity);
+    }
+    let txn = state.db.begin().await?;
+    let key = find_key(&txn, &namespace, &request.fingerprint).await?;
+    let revocation = key_revocation::Entity::revoke(
+        &txn,
+        key,
+        request.reason,
+        request.compromised_since,
+        &principal.subject,
+    )
+    .await?;
+    txn.commit().await?;
+    info!("Revoked key {} of {}", request.fingerprint, namespace);
+    Ok(Json(revocation))
+}
+
+// POST replace a signing key of a namespace by a new one
+#[instrument(skip(principal))]
+pub async fn rotate_key(
+    State(state): State<AppState>,
+    principal: Principal,
+    NamespacePath(namespace): NamespacePath,
+    Json(request): Json<KeyRotation>,
+) -> Result<Json<RotationInfo>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Owner, &namespace)
+        .await?;
+    let public_key = parse_public_key(&request.public_key)?;
+    let txn = state.db.begin().await?;
+    let old = find_key(&txn, &namespace, &request.fingerprint).await?;
+    let (old, new, rotation) = signing_key::Entity::rotate(
+        &txn,
+        old,
+        &request.public_key,
+        &fingerprint(&public_key),
+        Duration::seconds(request.overlap_secs.into()),
+        &principal.subject,
+    )
+    .await?;
+    txn.commit().await?;
+    info!(
+        "Rotated key {} of {} to {}",
+        old.fingerprint, namespace, new.fingerprint
+    );
+    Ok(Json(RotationInfo { old, new, rotation }))
+}
+
+async fn find_key(
+    db: &impl ConnectionTrait,
+    namespace: &str,
+    fingerprint: &str,
+) -> Result<signing_key::Model, Error> {
+    signing_key::Entity::find_by_fingerprint(db, namespace, fingerprint)
+        .await?
+        .ok_or_else(|| Error::UnknownKey(fingerprint.to_string()))
+}
+
+// GET the publish policy, that applies to a namespace
+#[instrument]
+pub async fn get_policy(
+    State(state): State<AppState>,
+    NamespacePath(namespace): NamespacePath,
+) -> Result<Json<Policy>, Error> {
+    let policy = namespace_policy::Entity::effective(&state.db, &namespace).await?;
+    Ok(Json(Policy {
+        require_signature: policy.is_some_and(|p| p.require_signature),
+    }))
+}
+
+// PUT set the publish policy of a namespace
+#[instrument(skip(principal))]
+pub async fn set_policy(
+    State(state): State<AppState>,
+    principal: Principal,
+    NamespacePath(namespace): NamespacePath,
+    Json(policy): Json<Policy>,
+) -> Result<Json<Policy>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Publish, Role::Owner, &namespace)
+        .await?;
+    let model = namespace_policy::Entity::set(
+        &state.db,
+        &namespace,
+        policy.require_signature,
+        &principal.subject,
+    )
+    .await?;
+    info!(
+        "Signatures are {} in {}",
+        if model.require_signature {
+            "required"
+        } else {
+            "optional"
+        },
+        namespace
+    );
+    Ok(Json(policy))
+}
+
+// GET list the signatures of a blob
+#[instrument]
+pub async fn signatures(
+    State(state): State<AppState>,
+    Digest(digest): Digest,
+) -> Result<Json<Vec<SignatureInfo>>, Error> {
+    let signatures = signature::Entity::find_by_digest(&state.db, &String::from(digest)).await?;
+    Ok(Json(signatures))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{signing::canonical_manifest, test_util::*};
+    use axum::http::{Method, StatusCode};
+    use base64::prelude::*;
+    use borderless_pkg::WasmPkg;
+    use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+    use serde_json::json;
+
+    fn sign(key: &SigningKey, pkg: &WasmPkg) -> String {
+        BASE64_STANDARD.encode(key.sign(canonical_manifest(pkg).as_bytes()).to_bytes())
+    }
+
+    async fn publish_signed(
+        app: &axum::Router,
+        oci: &str,
+        pkg: &WasmPkg,
+        signature: Option<String>,
+    ) -> StatusCode {
+        let mut request = axum::http::Request::put(format!("/api/v0/publish/{oci}"))
+            .header("authorization", format!("Bearer {TEST_TOKEN}"))
+            .header("content-type", "application/json");
+        if let Some(signature) = signature {
+            request = request.header("x-package-signature", signature);
+        }
+        let body = axum::body::Body::from(serde_json::to_vec(pkg).unwrap());
+        let response = tower::ServiceExt::oneshot(app.clone(), request.body(body).unwrap())
+            .await
+            .unwrap();
+        response.status()
+    }
+
+    #[tokio::test]
+    async fn signatures_are_verified_and_served() {
+        let app = test_app().await;
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let public_key = BASE64_STANDARD.encode(key.verifying_key().as_bytes());
+        let (status, _) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/keys/org",
+            Some(json!({ "public_key": public_key })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        // Signatures of unknown keys or other packages are rejected
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        let other = test_pkg("counter", b"\0asm-other");
+        let stranger = SigningKey::from_bytes(&[2; 32]);
+        assert_eq!(
+            publish_signed(&app, "org/counter:1.0.0", &pkg, Some(sign(&stranger, &pkg))).await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            publish_signed(&app, "org/counter:1.0.0", &pkg, Some(sign(&key, &other))).await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        // Keys of a namespace also sign the namespaces below it
+        assert_eq!(
+            publish_signed(&app, "org/team/counter:1.0.0", &pkg, Some(sign(&key, &pkg))).await,
+            StatusCode::CREATED
+        );
+
+        // Downloads expose the signature, the payload can be verified offline
+        let digest = String::from(pkg.source.digest);
+        let response = get(&app, &format!("/api/v0/blobs/{digest}")).await;
+        let header = response.headers()["x-package-signature"].to_str().unwrap();
+        assert!(header.ends_with(&sign(&key, &pkg)));
+
+        let (_, signatures) = send_json(
+            &app,
+            Method::GET,
+            &format!("/api/v0/signatures/{digest}"),
+            None,
+        )
+        .await;
+        let entry = &signatures[0];
+        assert_eq!(entry["namespace"], "org");
+        let verifying_key = VerifyingKey::from_bytes(
+            &BASE64_STANDARD
+                .decode(entry["public_key"].as_str().unwrap())
+                .unwrap()
+                .try_into()
+                .unwrap(),
+        )
+        .unwrap();
+        let signature = ed25519_dalek::Signature::from_slice(
+            &BASE64_STANDARD
+                .decode(entry["signature"].as_str().unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+        let payload = entry["payload"].as_str().unwrap();
+        assert!(verifying_key.verify(payload.as_bytes(), &signature).is_ok());
+        assert!(payload.contains(&digest));
+    }
+
+    #[tokio::test]
+    async fn policy_makes_signatures_mandatory() {
+        let app = test_app().await;
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        assert_eq!(
+            publish_signed(&app, "org/counter:1.0.0", &pkg, None).await,
+            StatusCode::CREATED
+        );
+
+        let (status, _) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/policy/org",
+            Some(json!({ "require_signature": true })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, policy) = send_json(&app, Method::GET, "/api/v0/policy/org/team", None).await;
+        assert_eq!(policy["require_signature"], true);
+
+        let pkg = test_pkg("counter", b"\0asm-counter-2");
+        assert_eq!(
+            publish_signed(&app, "org/team/counter:1.1.0", &pkg, None).await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        // Published versions are immutable, before any signature is looked at
+        assert_eq!(
+            publish_signed(&app, "org/counter:1.0.0", &pkg, None).await,
+            StatusCode::CONFLICT
+        );
+        // Other namespaces are not affected
+        assert_eq!(
+            publish_signed(&app, "acme/counter:1.1.0", &pkg, None).await,
+            StatusCode::CREATED
+        );
+    }
+
+    async fn register(app: &axum::Router, key: &SigningKey) -> String {
+        let public_key = BASE64_STANDARD.encode(key.verifying_key().as_bytes());
+        let (status, model) = send_json(
+            app,
+            Method::PUT,
+            "/api/v0/keys/org",
+            Some(json!({ "public_key": public_key })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        model["fingerprint"].as_str().unwrap().to_string()
+    }
+
+    async fn trusted(app: &axum::Router, pkg: &WasmPkg) -> bool {
+        let digest = String::from(pkg.source.digest);
+        let uri = format!("/api/v0/signatures/{digest}");
+        let (_, signatures) = send_json(app, Method::GET, &uri, None).await;
+        signatures[0]["trusted"].as_bool().unwrap()
+    }
+
+    #[tokio::test]
+    async fn rotation_and_revocation() {
+        let app = test_app().await;
+        let old_key = SigningKey::from_bytes(&[1; 32]);
+        let new_key = SigningKey::from_bytes(&[2; 32]);
+        let old_fingerprint = register(&app, &old_key).await;
+
+        let v1 = test_pkg("counter", b"\0asm-v1");
+        assert_eq!(
+            publish_signed(&app, "org/counter:1.0.0", &v1, Some(sign(&old_key, &v1))).await,
+            StatusCode::CREATED
+        );
+
+        let public_key = BASE64_STANDARD.encode(new_key.verifying_key().as_bytes());
+        let (status, rotation) = send_json(
+            &app,
+            Method::POST,
+            "/api/v0/rotate/org",
+            Some(json!({ "fingerprint": old_fingerprint, "public_key": public_key })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(rotation["old"]["valid_until"].is_string());
+        let new_fingerprint = rotation["new"]["fingerprint"].as_str().unwrap().to_string();
+
+        // Only the new key signs new packages, but old packages keep verifying
+        let v2 = test_pkg("counter", b"\0asm-v2");
+        assert_eq!(
+            publish_signed(&app, "org/counter:2.0.0", &v2, Some(sign(&old_key, &v2))).await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            publish_signed(&app, "org/counter:2.0.0", &v2, Some(sign(&new_key, &v2))).await,
+            StatusCode::CREATED
+        );
+        assert!(trusted(&app, &v1).await);
+        assert!(trusted(&app, &v2).await);
+
+        // Revoking the retired key does not affect packages signed before
+        let (status, _) = send_json(
+            &app,
+            Method::DELETE,
+            "/api/v0/keys/org",
+            Some(json!({ "fingerprint": old_fingerprint, "reason": "retired" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(trusted(&app, &v1).await);
+
+        // A compromise marks everything signed since as untrusted
+        let compromised_since = chrono::Utc::now() - chrono::Duration::hours(1);
+        let (status, _) = send_json(
+            &app,
+            Method::DELETE,
+            "/api/v0/keys/org",
+            Some(json!({
+                "fingerprint": new_fingerprint,
+                "reason": "leaked",
+                "compromised_since": compromised_since
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!trusted(&app, &v2).await);
+        let response = get(
+            &app,
+            &format!("/api/v0/blobs/{}", String::from(v2.source.digest)),
+        )
+        .await;
+        assert!(response.headers().get("x-package-signature").is_none());
+
+        let (_, keys) = send_json(&app, Method::GET, "/api/v0/keys/org", None).await;
+        assert!(keys
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|k| k["revocation"].is_object()));
+    }
+
+    #[tokio::test]
+    async fn invalid_keys_are_rejected() {
+        let app = test_app().await;
+        let (status, _) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/keys/org",
+            Some(json!({ "public_key": "not a key" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let (status, _) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/keys/org",
+            Some(json!({
+                "public_key": BASE64_STANDARD.encode(key.verifying_key().as_bytes()),
+                "valid_from": "2026-01-01T00:00:00Z",
+                "valid_until": "2025-01-01T00:00:00Z"
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = send_json(
+            &app,
+            Method::DELETE,
+            "/api/v0/keys/org",
+            Some(json!({ "fingerprint": "00", "reason": "unknown" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::{
+    api::pagination::{Page, PageQuery},
+    db::entities::{
+        index::{self, TagInfo},
+        tag_pointer,
+    },
+    error::Error,
+    extractor::RepoPath,
+    models::{tag_precedence, LATEST, NIGHTLY},
+    AppState,
+};
+
+/// A page of tags, ordered by SemVer precedence
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagPage {
+    pub namespace: String,
+    pub repository: String,
+    pub tags: Vec<TagInfo>,
+    /// Current targets of the floating tags
+    pub floating: BTreeMap<String, String>,
+    /// Cursor of the next page - `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+// GET list all tags of a repository
+#[instrument]
+pub async fn list_tags(
+    State(state): State<AppState>,
+    repo: RepoPath,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<TagPage>, Error> {
+    let after: Option<String> = query.after()?;
+    let limit = query.limit();
+
+    let mut tags = index::Entity::find_tags(&state.db, &repo.namespace, &repo.repository).await?;
+    if tags.is_empty() {
+        return Err(Error::NotPublished(format!(
+            "{}/{}",
+            repo.namespace, repo.repository
+        )));
+    }
+    tags.sort_by(|a, b| tag_precedence(&a.tag, &b.tag));
+
+    // The cursor is the last tag of the previous page, so we continue right after it
+    let start = match &after {
+        Some(after) => tags.partition_point(|t| tag_precedence(&t.tag, after).is_le()),
+        None => 0,
+    };
+    let items = tags.into_iter().skip(start).take(limit + 1).collect();
+    let page = Page::new(items, limit, |t: &TagInfo| t.tag.clone());
+
+    let mut floating = BTreeMap::new();
+    for keyword in [LATEST, NIGHTLY] {
+        if let Some(target) =
+            tag_pointer::Entity::target(&state.db, &repo.namespace, &repo.repository, keyword)
+                .await?
+        {
+            floating.insert(keyword.to_string(), target.tag);
+        }
+    }
+
+    Ok(Json(TagPage {
+        namespace: repo.namespace,
+        repository: repo.repository,
+        tags: page.items,
+        floating,
+        next_cursor: page.next_cursor,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use axum::http::{Method, StatusCode};
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn tags_are_listed_in_semver_order_with_cursor() {
+        let app = test_app().await;
+        for version in ["1.10.0", "1.2.0", "1.9.0", "0.1.0", "1.10.0-rc.1"] {
+            let pkg = test_pkg("counter", format!("\0asm-{version}").as_bytes());
+            publish(&app, &format!("org/team/counter:{version}"), &pkg).await;
+        }
+
+        let mut uri = "/api/v0/tags/org/team/counter?limit=2".to_string();
+        let mut tags = Vec::new();
+        loop {
+            let (status, page) = send_json(&app, Method::GET, &uri, None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(page["floating"]["latest"], "1.10.0");
+            assert_eq!(page["floating"]["nightly"], "1.10.0-rc.1");
+            for tag in page["tags"].as_array().unwrap() {
+                assert_eq!(tag["digest"].as_str().unwrap().len(), 64);
+                assert_eq!(tag["yank"], false);
+                tags.push(tag["tag"].as_str().unwrap().to_string());
+            }
+            match &page["next_cursor"] {
+                Value::String(cursor) => {
+                    uri = format!("/api/v0/tags/org/team/counter?limit=2&cursor={cursor}")
+                }
+                _ => break,
+            }
+        }
+        assert_eq!(
+            tags,
+            ["0.1.0", "1.2.0", "1.9.0", "1.10.0-rc.1", "1.10.0"].map(String::from)
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_cursor_is_rejected() {
+        let app = test_app().await;
+        let (status, _) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/tags/org/counter?cursor=%25%25",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn unknown_repository_is_not_found() {
+        let app = test_app().await;
+        let (status, _) = send_json(&app, Method::GET, "/api/v0/tags/org/missing", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use sea_orm::{EntityTrait, QueryOrder};
+use serde::Serialize;
+use tracing::{info, instrument};
+
+use crate::{
+    auth::{generate_secret, Principal},
+    db::entities::token::{self, NewToken},
+    error::Error,
+    AppState,
+};
+
+/// A freshly created token - the secret is only returned once
+#[derive(Debug, Serialize)]
+pub struct CreatedToken {
+    pub token: token::Model,
+    pub secret: String,
+}
+
+// POST create a new api token
+#[instrument(skip(principal))]
+pub async fn create_token(
+    State(state): State<AppState>,
+    principal: Principal,
+    Json(request): Json<NewToken>,
+) -> Result<(StatusCode, Json<CreatedToken>), Error> {
+    principal.authorize_admin()?;
+    let secret = generate_secret();
+    let token = token::Entity::create(&state.db, request, &secret).await?;
+    info!(
+        "Token {} for {} created by {}",
+        token.id, token.subject, principal.subject
+    );
+    Ok((StatusCode::CREATED, Json(CreatedToken { token, secret })))
+}
+
+// GET list all api tokens
+#[instrument(skip(principal))]
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Json<Vec<token::Model>>, Error> {
+    principal.authorize_admin()?;
+    let tokens = token::Entity::find()
+        .order_by_asc(token::Column::Id)
+        .all(&state.db)
+        .await?;
+    Ok(Json(tokens))
+}
+
+// DELETE revoke an api token
+#[instrument(skip(principal))]
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i64>,
+) -> Result<Json<token::Model>, Error> {
+    principal.authorize_admin()?;
+    let token = token::Entity::revoke(&state.db, id)
+        .await?
+        .ok_or(Error::UnknownToken(id))?;
+    info!("Token {} revoked by {}", id, principal.subject);
+    Ok(Json(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn scoped_tokens_are_enforced() {
+        let app = test_app().await;
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        let body = serde_json::to_value(&pkg).unwrap();
+
+        // Anonymous publishes are rejected
+        let (status, _) = send_as(
+            &app,
+            None,
+            Method::PUT,
+            "/api/v0/publish/org/counter:1.0.0",
+            Some(body.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, created) = send_json(
+            &app,
+            Method::POST,
+            "/api/v0/tokens",
+            Some(json!({
+                "name": "ci",
+                "subject": "release-bot",
+                "scopes": ["publish"],
+                "namespaces": ["org"]
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(created["token"].get("secret_hash").is_none());
+        let secret = created["secret"].as_str().unwrap().to_string();
+
+        // Publishing is allowed in the namespace, but nothing else
+        let (status, _) = send_as(
+            &app,
+            Some(&secret),
+            Method::PUT,
+            "/api/v0/publish/org/counter:1.0.0",
+            Some(body.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send_as(
+            &app,
+            Some(&secret),
+            Method::PUT,
+            "/api/v0/publish/other/counter:1.0.0",
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send_as(
+            &app,
+            Some(&secret),
+            Method::PUT,
+            "/api/v0/yank/org/counter:1.0.0",
+            Some(json!({ "reason": "oops" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send_as(&app, Some(&secret), Method::GET, "/api/v0/tokens", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // The publishing principal is recorded
+        let (_, hits) = send_json(&app, Method::GET, "/api/v0/search", None).await;
+        assert_eq!(hits[0]["published_by"], "release-bot");
+
+        // Revoked tokens are rejected
+        let id = created["token"]["id"].as_i64().unwrap();
+        let (status, revoked) =
+            send_json(&app, Method::DELETE, &format!("/api/v0/tokens/{id}"), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(revoked["revoked_at"].is_string());
+        let (status, _) = send_as(
+            &app,
+            Some(&secret),
+            Method::DELETE,
+            "/api/v0/deprecate/org/counter:1.0.0",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_are_rejected() {
+        let app = test_app().await;
+        let (_, created) = send_json(
+            &app,
+            Method::POST,
+            "/api/v0/tokens",
+            Some(json!({
+                "name": "old",
+                "subject": "alice",
+                "scopes": ["admin"],
+                "expires_at": "2020-01-01T00:00:00Z"
+            })),
+        )
+        .await;
+        let secret = created["secret"].as_str().unwrap();
+        let (status, _) = send_as(&app, Some(secret), Method::GET, "/api/v0/tokens", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = send_json(&app, Method::DELETE, "/api/v0/tokens/999", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
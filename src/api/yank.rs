@@ -0,0 +1,143 @@
+use axum::{extract::State, Json};
+use sea_orm::TransactionTrait;
+use tracing::{info, instrument};
+
+use crate::{
+    auth::{Principal, Role, Scope},
+    db::entities::{
+        index::{self, YankInfo},
+        log_entry::{self, LogKind},
+        tag_pointer,
+    },
+    error::Error,
+    extractor::OciId,
+    AppState,
+};
+
+// PUT yank a published version
+#[instrument(skip(principal))]
+pub async fn yank(
+    State(state): State<AppState>,
+    principal: Principal,
+    OciId(oid): OciId,
+    Json(mut info): Json<YankInfo>,
+) -> Result<Json<index::Model>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Yank, Role::Maintainer, &oid.namespace)
+        .await?;
+    info.actor = Some(principal.subject.clone());
+    let _guard = state.index_lock.lock().await;
+    let txn = state.db.begin().await?;
+    let entry = index::Entity::set_yank(&txn, &oid, Some(info))
+        .await?
+        .ok_or_else(|| Error::NotPublished(oid.to_string()))?;
+    // Yanked versions are not eligible for floating tags
+    tag_pointer::Entity::refresh(&txn, &oid.namespace, &oid.repository).await?;
+    log_entry::Entity::append_index(&txn, LogKind::Yank, &entry, &principal.subject).await?;
+    txn.commit().await?;
+    info!("Yanked package {}", oid);
+    Ok(Json(entry))
+}
+
+// DELETE unyank a published version
+#[instrument(skip(principal))]
+pub async fn unyank(
+    State(state): State<AppState>,
+    principal: Principal,
+    OciId(oid): OciId,
+) -> Result<Json<index::Model>, Error> {
+    principal
+        .authorize_in(&state.db, Scope::Yank, Role::Maintainer, &oid.namespace)
+        .await?;
+    let _guard = state.index_lock.lock().await;
+    let txn = state.db.begin().await?;
+    let entry = index::Entity::set_yank(&txn, &oid, None)
+        .await?
+        .ok_or_else(|| Error::NotPublished(oid.to_string()))?;
+    tag_pointer::Entity::refresh(&txn, &oid.namespace, &oid.repository).await?;
+    log_entry::Entity::append_index(&txn, LogKind::Unyank, &entry, &principal.subject).await?;
+    txn.commit().await?;
+    info!("Unyanked package {}", oid);
+    Ok(Json(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use axum::http::{Method, StatusCode};
+    use borderless_hash::Hash256;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn yank_and_unyank() {
+        let app = test_app().await;
+        let wasm = b"\0asm-counter";
+        publish(&app, "borderless/counter:1.0.0", &test_pkg("counter", wasm)).await;
+
+        let body = json!({ "reason": "broken storage layout" });
+        let (status, entry) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/yank/borderless/counter:1.0.0",
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(entry["yank"], true);
+        assert_eq!(entry["yank_reason"], "broken storage layout");
+        assert_eq!(entry["yanked_by"], TEST_SUBJECT);
+        assert!(entry["yanked_at"].is_string());
+
+        // Excluded from search and tag resolution ..
+        let (_, hits) = send_json(&app, Method::GET, "/api/v0/search", None).await;
+        assert!(hits.as_array().unwrap().is_empty());
+        let (status, body) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/packages/borderless/counter:1.0.0",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::GONE);
+        assert_eq!(body["error"]["details"]["reason"], "broken storage layout");
+
+        // .. but still downloadable by digest
+        let digest = String::from(Hash256::digest(wasm));
+        let (status, _) = send(&app, Method::GET, &format!("/api/v0/blobs/{digest}"), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, entry) = send_json(
+            &app,
+            Method::DELETE,
+            "/api/v0/yank/borderless/counter:1.0.0",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(entry["yank"], false);
+        assert!(entry["yank_reason"].is_null());
+
+        let (status, _) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/packages/borderless/counter:1.0.0",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn yank_unknown_package() {
+        let app = test_app().await;
+        let body = json!({ "reason": "does not exist" });
+        let (status, _) = send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/yank/borderless/counter:1.0.0",
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
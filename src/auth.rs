@@ -0,0 +1,300 @@
+//! Token based authentication for the mutating routes
+//!
+//! Clients authenticate with `Authorization: Bearer <secret>`. Only the hash of a secret is stored,
+//! so a leaked database does not leak usable tokens.
+use std::{fmt, str::FromStr};
+
+use axum::{extract::FromRequestParts, http::header::AUTHORIZATION, http::request::Parts};
+use base64::prelude::*;
+use borderless_hash::Hash256;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use sea_orm::ConnectionTrait;
+
+use crate::{
+    db::entities::{namespace_role, token},
+    error::Error,
+    AppState,
+};
+
+/// Prefix of all token secrets, which makes them easy to spot (e.g. by secret scanners)
+const SECRET_PREFIX: &str = "brt_";
+
+/// Action, that a token is allowed to perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Publish and deprecate packages - and manage owned namespaces
+    Publish,
+    /// Yank and unyank packages
+    Yank,
+    /// Everything - including token management
+    Admin,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Publish => write!(f, "publish"),
+            Scope::Yank => write!(f, "yank"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "publish" => Ok(Scope::Publish),
+            "yank" => Ok(Scope::Yank),
+            "admin" => Ok(Scope::Admin),
+            other => Err(format!("unknown scope '{other}'")),
+        }
+    }
+}
+
+/// Role of a user or team in a namespace - each role includes the permissions of the roles below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read access, no mutating actions
+    Reader,
+    /// Publish new versions
+    Publisher,
+    /// Publish, yank and deprecate versions
+    Maintainer,
+    /// Everything - including granting roles and transferring ownership
+    Owner,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Reader => write!(f, "reader"),
+            Role::Publisher => write!(f, "publisher"),
+            Role::Maintainer => write!(f, "maintainer"),
+            Role::Owner => write!(f, "owner"),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reader" => Ok(Role::Reader),
+            "publisher" => Ok(Role::Publisher),
+            "maintainer" => Ok(Role::Maintainer),
+            "owner" => Ok(Role::Owner),
+            other => Err(format!("unknown role '{other}'")),
+        }
+    }
+}
+
+/// Generates a new random token secret
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{SECRET_PREFIX}{}", hex::encode(bytes))
+}
+
+/// Hashes a token secret for storage
+///
+/// Secrets are 256 bit random values, so a fast hash is sufficient (no password hashing required).
+pub fn hash_secret(secret: &str) -> String {
+    String::from(Hash256::digest(&secret.as_bytes()))
+}
+
+/// The authenticated caller of a request
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub token_id: i64,
+    /// User or service, the token was issued for
+    pub subject: String,
+    scopes: Vec<Scope>,
+    namespaces: Option<Vec<String>>,
+}
+
+impl Principal {
+    /// Principal of maintenance commands, which act with full access on the local database
+    pub fn system(subject: &str) -> Self {
+        Principal {
+            token_id: 0,
+            subject: subject.to_string(),
+            scopes: vec![Scope::Admin],
+            namespaces: None,
+        }
+    }
+
+    /// Checks, that the principal may perform `scope` in the given namespace
+    ///
+    /// A namespace restriction also covers all namespaces below it.
+    pub fn authorize(&self, scope: Scope, namespace: &str) -> Result<(), Error> {
+        let in_namespace = match &self.namespaces {
+            None => true,
+            Some(allowed) => allowed.iter().any(|ns| {
+                namespace == ns
+                    || namespace
+                        .strip_prefix(ns.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            }),
+        };
+        if self.has_scope(scope) && in_namespace {
+            Ok(())
+        } else {
+            Err(Error::Forbidden {
+                scope: scope.to_string(),
+                namespace: Some(namespace.to_string()),
+            })
+        }
+    }
+
+    /// Checks, that the principal is an administrator of the whole registry
+    pub fn authorize_admin(&self) -> Result<(), Error> {
+        if self.has_scope(Scope::Admin) && self.namespaces.is_none() {
+            Ok(())
+        } else {
+            Err(Error::Forbidden {
+                scope: Scope::Admin.to_string(),
+                namespace: None,
+            })
+        }
+    }
+
+    /// Checks the token scope and the role of the principal in the namespace
+    ///
+    /// Registry administrators (unrestricted admin tokens) do not need a role.
+    pub async fn authorize_in(
+        &self,
+        db: &impl ConnectionTrait,
+        scope: Scope,
+        role: Role,
+        namespace: &str,
+    ) -> Result<(), Error> {
+        self.authorize(scope, namespace)?;
+        if self.authorize_admin().is_ok() {
+            return Ok(());
+        }
+        match namespace_role::Entity::effective_role(db, &self.subject, namespace).await? {
+            Some(granted) if granted >= role => Ok(()),
+            _ => Err(Error::MissingRole {
+                required: role.to_string(),
+                namespace: namespace.to_string(),
+            }),
+        }
+    }
+
+    fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin)
+    }
+}
+
+impl From<token::Model> for Principal {
+    fn from(token: token::Model) -> Self {
+        Principal {
+            token_id: token.id,
+            scopes: token.scope_list(),
+            namespaces: token.namespace_list(),
+            subject: token.subject,
+        }
+    }
+}
+
+/// Extracts the token secret from an authorization header
+///
+/// Besides bearer tokens, basic auth with the secret as password is accepted - which is what OCI clients send.
+fn credentials(header: &str) -> Option<String> {
+    if let Some(token) = header.strip_prefix("Bearer ") {
+        return Some(token.trim().to_string());
+    }
+    let decoded = BASE64_STANDARD
+        .decode(header.strip_prefix("Basic ")?.trim())
+        .ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (_user, secret) = decoded.split_once(':')?;
+    Some(secret.to_string())
+}
+
+impl FromRequestParts<AppState> for Principal {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let secret = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(credentials)
+            .ok_or(Error::Unauthorized("missing bearer token"))?;
+
+        let token = token::Entity::find_by_secret(&state.db, &secret)
+            .await?
+            .ok_or(Error::Unauthorized("unknown token"))?;
+        if !token.is_valid(chrono::Utc::now()) {
+            return Err(Error::Unauthorized("token is expired or revoked"));
+        }
+        Ok(token.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal(scopes: Vec<Scope>, namespaces: Option<Vec<&str>>) -> Principal {
+        Principal {
+            token_id: 1,
+            subject: "alice".to_string(),
+            scopes,
+            namespaces: namespaces.map(|ns| ns.into_iter().map(String::from).collect()),
+        }
+    }
+
+    #[test]
+    fn bearer_and_basic_credentials() {
+        assert_eq!(credentials("Bearer brt_abc ").as_deref(), Some("brt_abc"));
+        let basic = format!("Basic {}", BASE64_STANDARD.encode("ci:brt_abc"));
+        assert_eq!(credentials(&basic).as_deref(), Some("brt_abc"));
+        assert_eq!(credentials("Basic not-base64!"), None);
+        assert_eq!(credentials("Digest abc"), None);
+    }
+
+    #[test]
+    fn scopes_and_namespaces() {
+        let publisher = principal(vec![Scope::Publish], Some(vec!["org/team"]));
+        assert!(publisher.authorize(Scope::Publish, "org/team").is_ok());
+        assert!(publisher
+            .authorize(Scope::Publish, "org/team/project")
+            .is_ok());
+        assert!(publisher.authorize(Scope::Publish, "org/teamwork").is_err());
+        assert!(publisher.authorize(Scope::Publish, "org").is_err());
+        assert!(publisher.authorize(Scope::Yank, "org/team").is_err());
+        assert!(publisher.authorize_admin().is_err());
+
+        let admin = principal(vec![Scope::Admin], None);
+        assert!(admin.authorize(Scope::Yank, "anything").is_ok());
+        assert!(admin.authorize_admin().is_ok());
+
+        // Admins of a namespace cannot manage the registry
+        let ns_admin = principal(vec![Scope::Admin], Some(vec!["org"]));
+        assert!(ns_admin.authorize(Scope::Publish, "org/team").is_ok());
+        assert!(ns_admin.authorize_admin().is_err());
+    }
+
+    #[test]
+    fn secrets_are_random_and_hashed() {
+        let a = generate_secret();
+        let b = generate_secret();
+        assert_ne!(a, b);
+        assert!(a.starts_with(SECRET_PREFIX));
+        assert_eq!(hash_secret(&a), hash_secret(&a));
+        assert_eq!(hash_secret(&a).len(), 64);
+        assert!(!hash_secret(&a).contains(&a));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{fmt, io, str::FromStr};
+
+use async_compression::{
+    tokio::bufread::{ZstdDecoder, ZstdEncoder},
+    Level,
+};
+use axum::body::Body;
+use futures_util::TryStreamExt;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::error::Error;
+
+/// Compression level of stored blobs - the zstd default, which is a good trade-off for wasm
+const ZSTD_LEVEL: i32 = 3;
+
+/// Content coding of a stored blob, as recorded in `sources.encoding` and `oci_blobs.encoding`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Stored as-is
+    #[default]
+    Identity,
+    /// Compressed with zstd
+    Zstd,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Identity => write!(f, "identity"),
+            Encoding::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "identity" => Ok(Encoding::Identity),
+            "zstd" => Ok(Encoding::Zstd),
+            other => Err(format!("unknown encoding '{other}'")),
+        }
+    }
+}
+
+impl Encoding {
+    /// Parses the encoding column - blobs without one were stored before compression existed
+    pub fn from_column(column: Option<&str>) -> Result<Self, Error> {
+        match column {
+            Some(encoding) => encoding
+                .parse()
+                .map_err(|e: String| Error::Database(sea_orm::DbErr::Type(e))),
+            None => Ok(Encoding::Identity),
+        }
+    }
+}
+
+/// Compresses a blob for storage
+///
+/// The blob is kept as-is, if compression does not make it smaller.
+pub async fn compress(data: Vec<u8>) -> Result<(Encoding, Vec<u8>), Error> {
+    let mut compressed = Vec::new();
+    ZstdEncoder::with_quality(data.as_slice(), Level::Precise(ZSTD_LEVEL))
+        .read_to_end(&mut compressed)
+        .await?;
+    if compressed.len() < data.len() {
+        Ok((Encoding::Zstd, compressed))
+    } else {
+        Ok((Encoding::Identity, data))
+    }
+}
+
+/// Restores the original content of a stored blob, while it is streamed to the client
+pub fn decompress_body(encoding: Encoding, body: Body) -> Body {
+    match encoding {
+        Encoding::Identity => body,
+        Encoding::Zstd => {
+            let reader = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+            Body::from_stream(ReaderStream::new(ZstdDecoder::new(reader)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn roundtrip() {
+        let wasm = b"\0asm-counter".repeat(64);
+        let (encoding, stored) = compress(wasm.clone()).await.unwrap();
+        assert_eq!(encoding, Encoding::Zstd);
+        assert!(stored.len() < wasm.len());
+        let body = decompress_body(encoding, Body::from(stored));
+        assert_eq!(to_bytes(body, usize::MAX).await.unwrap().to_vec(), wasm);
+    }
+
+    #[tokio::test]
+    async fn incompressible_blobs_are_kept() {
+        let wasm = b"\0asm".to_vec();
+        let (encoding, stored) = compress(wasm.clone()).await.unwrap();
+        assert_eq!(encoding, Encoding::Identity);
+        assert_eq!(stored, wasm);
+        assert_eq!(Encoding::from_column(None).unwrap(), Encoding::Identity);
+        assert_eq!(Encoding::from_column(Some("zstd")).unwrap(), Encoding::Zstd);
+        assert!(Encoding::from_column(Some("brotli")).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use axum::body::Body;
+use borderless_hash::Hash256;
+use tokio_util::io::ReaderStream;
+
+use super::{parse_digest, BlobStore};
+use crate::error::Error;
+
+/// Directory below the root, that holds blobs while they are written
+const TMP_DIR: &str = "tmp";
+
+/// Blob store, that keeps every blob as a plain file
+///
+/// Files are sharded by the first two bytes of their digest (`ab/cd/abcd...`),
+/// so no directory grows too large and the store can be backed up with standard tools.
+#[derive(Debug, Clone)]
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    /// Opens the store in the given directory (created if missing)
+    pub fn open(root: &Path) -> Result<Self, Error> {
+        fs::create_dir_all(root.join(TMP_DIR))?;
+        Ok(Self {
+            root: root.to_path_buf(),
+        })
+    }
+
+    /// Location of the blob with the given digest
+    fn path(&self, digest: &Hash256) -> PathBuf {
+        let hex = String::from(*digest);
+        self.root.join(&hex[0..2]).join(&hex[2..4]).join(hex)
+    }
+
+    /// Writes a blob atomically - readers see either no file or the complete blob
+    fn write(root: &Path, path: &Path, data: &[u8]) -> io::Result<()> {
+        let tmp = root
+            .join(TMP_DIR)
+            .join(format!("{:016x}", rand::random::<u64>()));
+        let result = (|| {
+            let mut file = File::create(&tmp)?;
+            file.write_all(data)?;
+            file.sync_all()?;
+            let dir = path.parent().expect("blob paths are sharded");
+            fs::create_dir_all(dir)?;
+            fs::rename(&tmp, path)?;
+            // The rename itself is only durable, once the directory is synced
+            File::open(dir)?.sync_all()
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    /// Collects the digests of all blobs below the shard directories
+    fn walk(root: &Path) -> io::Result<Vec<Hash256>> {
+        let mut digests = Vec::new();
+        for shard in fs::read_dir(root)? {
+            let shard = shard?;
+            if shard.file_name() == TMP_DIR || !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for sub in fs::read_dir(shard.path())? {
+                let sub = sub?;
+                if !sub.file_type()?.is_dir() {
+                    continue;
+                }
+                for blob in fs::read_dir(sub.path())? {
+                    // Entries, that are no files named after a digest, do not belong to the store
+                    let blob = blob?;
+                    if !blob.file_type()?.is_file() {
+                        continue;
+                    }
+                    let name = blob.file_name();
+                    if let Some(digest) = name.to_str().and_then(|n| parse_digest(n).ok()) {
+                        digests.push(digest);
+                    }
+                }
+            }
+        }
+        Ok(digests)
+    }
+}
+
+#[async_trait]
+impl BlobStore for FsBlobStore {
+    async fn put(&self, digest: &Hash256, data: Vec<u8>) -> Result<(), Error> {
+        let path = self.path(digest);
+        if tokio::fs::try_exists(&path).await? {
+            return Err(Error::Dublicated(*digest));
+        }
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || Self::write(&root, &path, &data))
+            .await
+            .map_err(io::Error::other)??;
+        Ok(())
+    }
+
+    async fn get(&self, digest: &Hash256) -> Result<Vec<u8>, Error> {
+        match tokio::fs::read(self.path(digest)).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(Error::NoPkg(*digest)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn stream(&self, digest: &Hash256) -> Result<Body, Error> {
+        match tokio::fs::File::open(self.path(digest)).await {
+            Ok(file) => Ok(Body::from_stream(ReaderStream::new(file))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(Error::NoPkg(*digest)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn contains(&self, digest: &Hash256) -> Result<bool, Error> {
+        Ok(tokio::fs::try_exists(self.path(digest)).await?)
+    }
+
+    async fn delete(&self, digest: &Hash256) -> Result<(), Error> {
+        match tokio::fs::remove_file(self.path(digest)).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<Hash256>, Error> {
+        let root = self.root.clone();
+        let digests = tokio::task::spawn_blocking(move || Self::walk(&root))
+            .await
+            .map_err(io::Error::other)??;
+        Ok(digests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn sharded_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsBlobStore::open(dir.path()).unwrap();
+        let wasm = b"\0asm-counter".to_vec();
+        let digest = Hash256::digest(&wasm);
+        store.put(&digest, wasm.clone()).await.unwrap();
+
+        let hex = String::from(digest);
+        let path = dir.path().join(&hex[0..2]).join(&hex[2..4]).join(&hex);
+        assert_eq!(fs::read(path).unwrap(), wasm);
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path().join(TMP_DIR)).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn put_get_stream_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsBlobStore::open(dir.path()).unwrap();
+        let wasm = b"\0asm-counter".to_vec();
+        let digest = Hash256::digest(&wasm);
+
+        assert!(!store.contains(&digest).await.unwrap());
+        assert!(matches!(store.get(&digest).await, Err(Error::NoPkg(_))));
+        assert!(matches!(store.stream(&digest).await, Err(Error::NoPkg(_))));
+        store.put(&digest, wasm.clone()).await.unwrap();
+        assert!(store.contains(&digest).await.unwrap());
+        assert_eq!(store.get(&digest).await.unwrap(), wasm);
+        let body = store.stream(&digest).await.unwrap();
+        assert_eq!(to_bytes(body, usize::MAX).await.unwrap().to_vec(), wasm);
+        assert!(matches!(
+            store.put(&digest, wasm.clone()).await,
+            Err(Error::Dublicated(_))
+        ));
+
+        assert_eq!(store.list().await.unwrap(), vec![digest]);
+
+        // Stray files and directories are ignored
+        let hex = String::from(digest);
+        fs::write(dir.path().join("README"), b"").unwrap();
+        fs::write(dir.path().join(&hex[0..2]).join("stray"), b"").unwrap();
+        fs::write(
+            dir.path().join(&hex[0..2]).join(&hex[2..4]).join("stray"),
+            b"",
+        )
+        .unwrap();
+        fs::create_dir(
+            dir.path()
+                .join(&hex[0..2])
+                .join(&hex[2..4])
+                .join("0".repeat(64)),
+        )
+        .unwrap();
+        assert_eq!(store.list().await.unwrap(), vec![digest]);
+
+        store.delete(&digest).await.unwrap();
+        assert!(!store.contains(&digest).await.unwrap());
+        assert!(store.list().await.unwrap().is_empty());
+        store.delete(&digest).await.unwrap();
+    }
+}
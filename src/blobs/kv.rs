@@ -0,0 +1,163 @@
+use std::{fmt, path::Path};
+
+use async_trait::async_trait;
+use borderless_hash::Hash256;
+use borderless_kv_store::{backend::lmdb::Lmdb, prelude::*, Tx};
+
+use super::BlobStore;
+use crate::error::Error;
+
+/// Name of the sub-database, that holds the blobs
+const BLOBS_DB: &str = "blobs";
+
+/// Blob store on top of the LMDB backend of `borderless-kv-store`
+#[derive(Clone)]
+pub struct KvBlobStore {
+    env: Lmdb,
+    handle: <Lmdb as Db>::Handle,
+}
+
+impl KvBlobStore {
+    /// Opens the store in the given directory (created if missing)
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        std::fs::create_dir_all(path)?;
+        let env = Lmdb::new(path, 1)?;
+        let handle = env.create_sub_db(BLOBS_DB)?;
+        Ok(Self { env, handle })
+    }
+
+    /// Runs a kv-store operation on the blocking thread pool
+    async fn blocking<T, F>(&self, op: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(Lmdb, <Lmdb as Db>::Handle) -> Result<T, Error> + Send + 'static,
+    {
+        let (env, handle) = (self.env.clone(), self.handle);
+        tokio::task::spawn_blocking(move || op(env, handle))
+            .await
+            .map_err(|e| borderless_kv_store::Error::Other(e.to_string()))?
+    }
+}
+
+impl fmt::Debug for KvBlobStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KvBlobStore").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl BlobStore for KvBlobStore {
+    async fn put(&self, digest: &Hash256, data: Vec<u8>) -> Result<(), Error> {
+        let digest = *digest;
+        self.blocking(move |env, handle| {
+            let mut txn = env.begin_rw_txn()?;
+            if txn.read(&handle, &digest)?.is_some() {
+                txn.abort();
+                return Err(Error::Dublicated(digest));
+            }
+            txn.write(&handle, &digest, &data)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, digest: &Hash256) -> Result<Vec<u8>, Error> {
+        let digest = *digest;
+        self.blocking(move |env, handle| {
+            let txn = env.begin_ro_txn()?;
+            let data = txn
+                .read(&handle, &digest)?
+                .map(|data| data.to_vec())
+                .ok_or(Error::NoPkg(digest));
+            txn.commit()?;
+            data
+        })
+        .await
+    }
+
+    async fn contains(&self, digest: &Hash256) -> Result<bool, Error> {
+        let digest = *digest;
+        self.blocking(move |env, handle| {
+            let txn = env.begin_ro_txn()?;
+            let found = txn.read(&handle, &digest)?.is_some();
+            txn.commit()?;
+            Ok(found)
+        })
+        .await
+    }
+
+    async fn delete(&self, digest: &Hash256) -> Result<(), Error> {
+        let digest = *digest;
+        self.blocking(move |env, handle| {
+            let mut txn = env.begin_rw_txn()?;
+            if txn.read(&handle, &digest)?.is_some() {
+                txn.delete(&handle, &digest)?;
+            }
+            txn.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<Hash256>, Error> {
+        self.blocking(move |env, handle| {
+            let txn = env.begin_ro_txn()?;
+            let digests = {
+                // A fresh cursor starts at the first item - `iter_start` panics on an empty database
+                let mut cursor = txn.ro_cursor(&handle)?;
+                cursor
+                    .iter()
+                    .map(|(key, _)| Hash256::try_from(key.to_vec()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| Error::InvalidDigest)?
+            };
+            txn.commit()?;
+            Ok(digests)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn put_get_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KvBlobStore::open(&dir.path().join("blobs")).unwrap();
+        let wasm = b"\0asm-counter".to_vec();
+        let digest = Hash256::digest(&wasm);
+
+        assert!(!store.contains(&digest).await.unwrap());
+        assert!(matches!(store.get(&digest).await, Err(Error::NoPkg(_))));
+        store.put(&digest, wasm.clone()).await.unwrap();
+        assert!(store.contains(&digest).await.unwrap());
+        assert_eq!(store.get(&digest).await.unwrap(), wasm);
+        assert!(matches!(
+            store.put(&digest, wasm.clone()).await,
+            Err(Error::Dublicated(_))
+        ));
+
+        assert_eq!(store.list().await.unwrap(), vec![digest]);
+
+        store.delete(&digest).await.unwrap();
+        assert!(!store.contains(&digest).await.unwrap());
+        assert!(store.list().await.unwrap().is_empty());
+        store.delete(&digest).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn blobs_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = b"\0asm-counter".to_vec();
+        let digest = Hash256::digest(&wasm);
+        {
+            let store = KvBlobStore::open(dir.path()).unwrap();
+            store.put(&digest, wasm.clone()).await.unwrap();
+        }
+        let store = KvBlobStore::open(dir.path()).unwrap();
+        assert_eq!(store.get(&digest).await.unwrap(), wasm);
+    }
+}
@@ -0,0 +1,92 @@
+//! Content-addressed storage for the wasm of published packages and uploaded OCI blobs
+//!
+//! Blobs are keyed by the [`Hash256`] of their content, which is the digest that `sources` records -
+//! or the sha256 of an uploaded OCI blob (see [`crate::oci::blob_key`]).
+use std::{fmt::Debug, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use axum::body::Body;
+use borderless_hash::Hash256;
+use clap::ValueEnum;
+
+use crate::error::Error;
+
+mod encoding;
+mod fs;
+mod kv;
+
+pub use encoding::{compress, decompress_body, Encoding};
+pub use fs::FsBlobStore;
+pub use kv::KvBlobStore;
+
+/// Available blob store implementations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// LMDB database of `borderless-kv-store`
+    Kv,
+    /// Plain files, sharded by digest
+    Fs,
+}
+
+/// Opens the blob store of the given backend in a directory
+pub fn open(backend: Backend, path: &Path) -> Result<Arc<dyn BlobStore>, Error> {
+    Ok(match backend {
+        Backend::Kv => Arc::new(KvBlobStore::open(path)?),
+        Backend::Fs => Arc::new(FsBlobStore::open(path)?),
+    })
+}
+
+/// Storage backend for wasm blobs
+#[async_trait]
+pub trait BlobStore: Debug + Send + Sync {
+    /// Stores a blob under its digest
+    ///
+    /// Fails with [`Error::Dublicated`], if the store already has a blob with this digest.
+    async fn put(&self, digest: &Hash256, data: Vec<u8>) -> Result<(), Error>;
+
+    /// Returns the blob with the given digest - or [`Error::NoPkg`], if there is none
+    async fn get(&self, digest: &Hash256) -> Result<Vec<u8>, Error>;
+
+    /// Returns the blob as response body - backends may stream it without loading it into memory
+    async fn stream(&self, digest: &Hash256) -> Result<Body, Error> {
+        Ok(Body::from(self.get(digest).await?))
+    }
+
+    /// Checks, if the store has a blob with the given digest
+    async fn contains(&self, digest: &Hash256) -> Result<bool, Error>;
+
+    /// Removes the blob with the given digest - removing a missing blob is a no-op
+    async fn delete(&self, digest: &Hash256) -> Result<(), Error>;
+
+    /// Returns the digests of all stored blobs
+    async fn list(&self) -> Result<Vec<Hash256>, Error>;
+}
+
+/// Stores a blob, unless the store already has it
+///
+/// Blobs are content-addressed, so an existing blob has exactly the given content.
+pub async fn store(blobs: &dyn BlobStore, digest: &Hash256, data: Vec<u8>) -> Result<(), Error> {
+    match blobs.put(digest, data).await {
+        Ok(()) | Err(Error::Dublicated(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Compresses and stores the wasm of a source (or an uploaded blob) - returns the encoding, that has to be recorded for it
+///
+/// The digest always refers to the uncompressed content.
+pub async fn store_wasm(
+    blobs: &dyn BlobStore,
+    digest: &Hash256,
+    wasm: Vec<u8>,
+) -> Result<Encoding, Error> {
+    let (encoding, data) = compress(wasm).await?;
+    store(blobs, digest, data).await?;
+    Ok(encoding)
+}
+
+/// Parses the full base16 representation of a digest, as it is stored in the database
+pub fn parse_digest(digest: &str) -> Result<Hash256, Error> {
+    let bytes = hex::decode(digest).map_err(|_| Error::InvalidDigest)?;
+    Hash256::try_from(bytes).map_err(|_| Error::InvalidDigest)
+}
@@ -12,7 +12,7 @@ pub type ActiveAuthor = ActiveModel;
 #[sea_orm(table_name = "authors")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i64,
     pub name: String,
     pub email: Option<String>,
 }
@@ -45,7 +45,21 @@ impl ActiveAuthor {
     pub async fn from_model(
         txn: &DatabaseTransaction,
         author: borderless_pkg::Author,
-    ) -> Result<u64, Error> {
+    ) -> Result<i64, Error> {
+        // Authors are shared between packages - reuse the existing entry.
+        // The email is unique, so it identifies the author if present.
+        let existing = match &author.email {
+            Some(email) => Entity::find().filter(Column::Email.eq(email)),
+            None => Entity::find()
+                .filter(Column::Name.eq(&author.name))
+                .filter(Column::Email.is_null()),
+        }
+        .one(txn)
+        .await?;
+        if let Some(existing) = existing {
+            return Ok(existing.id);
+        }
+
         let author = ActiveAuthor {
             id: NotSet,
             name: Set(author.name),
@@ -12,7 +12,7 @@ pub type ActiveCapabilities = ActiveModel;
 #[sea_orm(table_name = "capabilities")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i64,
     pub network: bool,
     pub websocket: bool,
 }
@@ -26,7 +26,7 @@ impl ActiveCapabilities {
     pub async fn from_model(
         txn: &DatabaseTransaction,
         capabilities: borderless_pkg::Capabilities,
-    ) -> Result<u64, Error> {
+    ) -> Result<i64, Error> {
         let capabilities = ActiveCapabilities {
             id: NotSet,
             network: Set(capabilities.network),
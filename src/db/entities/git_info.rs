@@ -12,9 +12,9 @@ pub type ActiveGitInfo = ActiveModel;
 #[sea_orm(table_name = "git_info")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i64,
     pub commit_hash_short: String,
-    pub commits_past_tag: Option<u64>,
+    pub commits_past_tag: Option<i64>,
     pub tag: Option<String>,
     pub dirty: bool,
 }
@@ -28,11 +28,11 @@ impl ActiveGitInfo {
     pub async fn from_model(
         txn: &DatabaseTransaction,
         git: borderless_pkg::git_info::GitInfo,
-    ) -> Result<u64, Error> {
+    ) -> Result<i64, Error> {
         let git = ActiveGitInfo {
             id: NotSet,
             commit_hash_short: Set(git.commit_hash_short),
-            commits_past_tag: Set(git.commits_past_tag),
+            commits_past_tag: Set(git.commits_past_tag.map(|c| c as i64)),
             tag: Set(git.tag),
             dirty: Set(git.dirty),
         };
@@ -1,14 +1,27 @@
-use sea_orm::entity::prelude::*;
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Expr, Func, LikeExpr, SimpleExpr},
+    ActiveValue::Set,
+    Condition, FromQueryResult, JoinType, QueryOrder, QuerySelect,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::{error::Error, models::OciIdentifier};
+
 pub type ActiveIndex = ActiveModel;
 
+/// Default number of search results, if the client does not specify a limit
+const DEFAULT_SEARCH_LIMIT: u64 = 50;
+
+/// Upper bound for the number of search results per request
+const MAX_SEARCH_LIMIT: u64 = 500;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "registry_index")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = true)]
-    pub id: u64,
-    pub pkg_id: u64,
+    pub id: i64,
+    pub pkg_id: i64,
     pub registry: String,
     pub namespace: String,
     pub repository: String,
@@ -16,6 +29,13 @@ pub struct Model {
     pub yank: bool,
     pub deprecated: bool,
     pub created_at: DateTimeUtc,
+    pub yank_reason: Option<String>,
+    pub yanked_at: Option<DateTimeUtc>,
+    pub yanked_by: Option<String>,
+    pub deprecation_message: Option<String>,
+    pub deprecation_successor: Option<String>,
+    pub deprecated_at: Option<DateTimeUtc>,
+    pub published_by: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -35,3 +55,452 @@ impl Related<super::package::Entity> for Entity {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Oci identifier of the entry in its string representation
+    pub fn oci_string(&self) -> String {
+        let mut oci = String::new();
+        if !self.registry.is_empty() {
+            oci.push_str(&self.registry);
+            oci.push('/');
+        }
+        oci.push_str(&format!(
+            "{}/{}:{}",
+            self.namespace, self.repository, self.tag
+        ));
+        oci
+    }
+}
+
+/// Search parameters for the registry index
+///
+/// All filters are optional and combined with a logical `AND`.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct SearchQuery {
+    /// Exact namespace
+    pub namespace: Option<String>,
+    /// Prefix of the repository name
+    pub repository: Option<String>,
+    /// Exact tag
+    pub tag: Option<String>,
+    /// Yank state - yanked packages are excluded by default
+    pub yanked: Option<bool>,
+    /// Deprecation state - if not set, deprecated packages are included
+    pub deprecated: Option<bool>,
+    /// Maximum number of results
+    pub limit: Option<u64>,
+}
+
+/// Reason and actor of a yank
+#[derive(Debug, Clone, Deserialize)]
+pub struct YankInfo {
+    pub reason: String,
+    /// Set by the registry to the authenticated principal
+    #[serde(skip)]
+    pub actor: Option<String>,
+}
+
+/// A single tag of a repository
+#[derive(Debug, Clone, Serialize, Deserialize, FromQueryResult)]
+pub struct TagInfo {
+    pub id: i64,
+    pub tag: String,
+    pub digest: String,
+    pub yank: bool,
+    pub deprecated: bool,
+    pub created_at: DateTimeUtc,
+    pub yank_reason: Option<String>,
+    pub deprecation_message: Option<String>,
+    pub deprecation_successor: Option<String>,
+    /// Digest of the OCI manifest - `None` for packages without wasm code
+    pub manifest_digest: Option<String>,
+}
+
+/// A namespace of the registry
+#[derive(Debug, Clone, Serialize, Deserialize, FromQueryResult)]
+pub struct NamespaceSummary {
+    pub namespace: String,
+    /// Number of repositories directly inside the namespace
+    pub repositories: i64,
+    /// Time of the most recent publish into the namespace
+    pub updated_at: DateTimeUtc,
+}
+
+/// A repository of the registry
+#[derive(Debug, Clone, Serialize, Deserialize, FromQueryResult)]
+pub struct RepositorySummary {
+    pub namespace: String,
+    pub repository: String,
+    /// Number of tags (including yanked ones)
+    pub tags: i64,
+    /// Time of the most recent publish into the repository
+    pub updated_at: DateTimeUtc,
+}
+
+/// Message and successor of a deprecation
+#[derive(Debug, Clone)]
+pub struct DeprecationInfo {
+    pub message: String,
+    pub successor: Option<OciIdentifier>,
+}
+
+/// Summary of a published package, joined together from `registry_index`, `packages`, `meta` and `sources`
+#[derive(Debug, Clone, Serialize, Deserialize, FromQueryResult)]
+pub struct PackageSummary {
+    pub registry: String,
+    pub namespace: String,
+    pub repository: String,
+    pub tag: String,
+    pub yank: bool,
+    pub deprecated: bool,
+    pub created_at: DateTimeUtc,
+    pub name: String,
+    pub pkg_type: String,
+    pub description: Option<String>,
+    pub digest: String,
+    pub deprecation_message: Option<String>,
+    pub deprecation_successor: Option<String>,
+    pub deprecated_at: Option<DateTimeUtc>,
+    pub published_by: Option<String>,
+}
+
+impl Entity {
+    /// Selects the entry with the exact identity of the given identifier
+    pub fn find_by_oci(oid: &OciIdentifier) -> Select<Entity> {
+        Entity::find()
+            .filter(Column::Registry.eq(oid.registry_string()))
+            .filter(Column::Namespace.eq(&oid.namespace))
+            .filter(Column::Repository.eq(&oid.repository))
+            .filter(Column::Tag.eq(oid.tag.to_string()))
+    }
+
+    /// Returns the digest of the package that is published under the given identifier
+    pub async fn find_digest(
+        db: &impl ConnectionTrait,
+        oid: &OciIdentifier,
+    ) -> Result<Option<String>, Error> {
+        let digest = Self::find_by_oci(oid)
+            .select_only()
+            .column(super::source::Column::Digest)
+            .join(JoinType::InnerJoin, Relation::Package.def())
+            .join(JoinType::InnerJoin, super::package::Relation::Sources.def())
+            .into_tuple::<String>()
+            .one(db)
+            .await?;
+        Ok(digest)
+    }
+
+    /// Returns the digest of the package with the given id
+    pub async fn digest_of(
+        db: &impl ConnectionTrait,
+        pkg_id: i64,
+    ) -> Result<Option<String>, Error> {
+        let digest = super::package::Entity::find_by_id(pkg_id)
+            .select_only()
+            .column(super::source::Column::Digest)
+            .join(JoinType::InnerJoin, super::package::Relation::Sources.def())
+            .into_tuple::<String>()
+            .one(db)
+            .await?;
+        Ok(digest)
+    }
+
+    /// Returns the package summary of the given identifier
+    pub async fn find_summary(
+        db: &impl ConnectionTrait,
+        oid: &OciIdentifier,
+    ) -> Result<Option<PackageSummary>, Error> {
+        let summary = summary_select(Self::find_by_oci(oid))
+            .into_model::<PackageSummary>()
+            .one(db)
+            .await?;
+        Ok(summary)
+    }
+
+    /// Sets or clears the yank flag of a published version
+    ///
+    /// Returns `None`, if there is no package with the given identifier.
+    pub async fn set_yank(
+        db: &impl ConnectionTrait,
+        oid: &OciIdentifier,
+        yank: Option<YankInfo>,
+    ) -> Result<Option<Model>, Error> {
+        let Some(entry) = Self::find_by_oci(oid).one(db).await? else {
+            return Ok(None);
+        };
+        let mut entry: ActiveModel = entry.into();
+        match yank {
+            Some(info) => {
+                entry.yank = Set(true);
+                entry.yank_reason = Set(Some(info.reason));
+                entry.yanked_at = Set(Some(chrono::Utc::now()));
+                entry.yanked_by = Set(info.actor);
+            }
+            None => {
+                entry.yank = Set(false);
+                entry.yank_reason = Set(None);
+                entry.yanked_at = Set(None);
+                entry.yanked_by = Set(None);
+            }
+        }
+        let entry = entry.update(db).await?;
+        Ok(Some(entry))
+    }
+
+    /// Sets or clears the deprecation of a published version - or of all versions in its repository
+    ///
+    /// Returns the updated entries, which is empty if there is no matching package.
+    pub async fn set_deprecation(
+        db: &impl ConnectionTrait,
+        oid: &OciIdentifier,
+        all_versions: bool,
+        deprecation: Option<DeprecationInfo>,
+    ) -> Result<Vec<Model>, Error> {
+        let mut scope = Condition::all()
+            .add(Column::Registry.eq(oid.registry_string()))
+            .add(Column::Namespace.eq(&oid.namespace))
+            .add(Column::Repository.eq(&oid.repository));
+        if !all_versions {
+            scope = scope.add(Column::Tag.eq(oid.tag.to_string()));
+        }
+
+        let update = Entity::update_many().filter(scope.clone());
+        let update = match deprecation {
+            Some(info) => update
+                .col_expr(Column::Deprecated, Expr::value(true))
+                .col_expr(Column::DeprecationMessage, Expr::value(info.message))
+                .col_expr(
+                    Column::DeprecationSuccessor,
+                    Expr::value(info.successor.map(|s| s.to_string())),
+                )
+                .col_expr(Column::DeprecatedAt, Expr::value(chrono::Utc::now())),
+            None => update
+                .col_expr(Column::Deprecated, Expr::value(false))
+                .col_expr(Column::DeprecationMessage, Expr::value(None::<String>))
+                .col_expr(Column::DeprecationSuccessor, Expr::value(None::<String>))
+                .col_expr(Column::DeprecatedAt, Expr::value(None::<DateTimeUtc>)),
+        };
+        update.exec(db).await?;
+
+        let entries = Entity::find()
+            .filter(scope)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(entries)
+    }
+
+    /// Returns all index entries, that reference the source with the given digest
+    pub async fn find_by_digest(
+        db: &impl ConnectionTrait,
+        digest: &str,
+    ) -> Result<Vec<Model>, Error> {
+        let entries = Entity::find()
+            .join(JoinType::InnerJoin, Relation::Package.def())
+            .join(JoinType::InnerJoin, super::package::Relation::Sources.def())
+            .filter(super::source::Column::Digest.eq(digest))
+            .all(db)
+            .await?;
+        Ok(entries)
+    }
+
+    /// Returns all tags of a repository
+    pub async fn find_tags(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        repository: &str,
+    ) -> Result<Vec<TagInfo>, Error> {
+        let tags = Entity::find()
+            .select_only()
+            .column(Column::Id)
+            .column(Column::Tag)
+            .column(super::source::Column::Digest)
+            .column(Column::Yank)
+            .column(Column::Deprecated)
+            .column(Column::CreatedAt)
+            .column(Column::YankReason)
+            .column(Column::DeprecationMessage)
+            .column(Column::DeprecationSuccessor)
+            .column_as(super::oci_manifest::Column::Digest, "manifest_digest")
+            .join(JoinType::InnerJoin, Relation::Package.def())
+            .join(JoinType::InnerJoin, super::package::Relation::Sources.def())
+            .join(
+                JoinType::LeftJoin,
+                super::oci_manifest::Relation::Index.def().rev(),
+            )
+            .filter(Column::Namespace.eq(namespace))
+            .filter(Column::Repository.eq(repository))
+            .into_model::<TagInfo>()
+            .all(db)
+            .await?;
+        Ok(tags)
+    }
+
+    /// Lists the namespaces of the registry, ordered by name
+    ///
+    /// Only namespaces after `after` are returned, which allows for keyset pagination.
+    /// If `subtree` is set, only this namespace and the namespaces below it are listed.
+    pub async fn list_namespaces(
+        db: &impl ConnectionTrait,
+        subtree: Option<&str>,
+        after: Option<&str>,
+        limit: u64,
+    ) -> Result<Vec<NamespaceSummary>, Error> {
+        let mut select = Entity::find()
+            .select_only()
+            .column(Column::Namespace)
+            .column_as(
+                SimpleExpr::from(Func::count_distinct(Expr::col(Column::Repository))),
+                "repositories",
+            )
+            .column_as(Column::CreatedAt.max(), "updated_at");
+
+        if let Some(namespace) = subtree {
+            select = select.filter(subtree_condition(namespace));
+        }
+        if let Some(after) = after {
+            select = select.filter(Column::Namespace.gt(after));
+        }
+
+        let namespaces = select
+            .group_by(Column::Namespace)
+            .order_by_asc(Column::Namespace)
+            .limit(limit)
+            .into_model::<NamespaceSummary>()
+            .all(db)
+            .await?;
+        Ok(namespaces)
+    }
+
+    /// Lists the repositories of the registry, ordered by namespace and repository
+    ///
+    /// Only repositories after `after` (namespace, repository) are returned, which allows for keyset pagination.
+    /// If `subtree` is set, only repositories of this namespace and the namespaces below it are listed.
+    pub async fn list_repositories(
+        db: &impl ConnectionTrait,
+        subtree: Option<&str>,
+        exact_namespace: Option<&str>,
+        after: Option<(&str, &str)>,
+        limit: u64,
+    ) -> Result<Vec<RepositorySummary>, Error> {
+        let mut select = Entity::find()
+            .select_only()
+            .column(Column::Namespace)
+            .column(Column::Repository)
+            .column_as(Column::Id.count(), "tags")
+            .column_as(Column::CreatedAt.max(), "updated_at");
+
+        if let Some(namespace) = subtree {
+            select = select.filter(subtree_condition(namespace));
+        }
+        if let Some(namespace) = exact_namespace {
+            select = select.filter(Column::Namespace.eq(namespace));
+        }
+        if let Some((namespace, repository)) = after {
+            select = select.filter(
+                Condition::any().add(Column::Namespace.gt(namespace)).add(
+                    Condition::all()
+                        .add(Column::Namespace.eq(namespace))
+                        .add(Column::Repository.gt(repository)),
+                ),
+            );
+        }
+
+        let repositories = select
+            .group_by(Column::Namespace)
+            .group_by(Column::Repository)
+            .order_by_asc(Column::Namespace)
+            .order_by_asc(Column::Repository)
+            .limit(limit)
+            .into_model::<RepositorySummary>()
+            .all(db)
+            .await?;
+        Ok(repositories)
+    }
+
+    /// Searches the registry index
+    ///
+    /// Results are ordered by namespace and repository, with the most recent entries first.
+    pub async fn search(
+        db: &impl ConnectionTrait,
+        query: &SearchQuery,
+    ) -> Result<Vec<PackageSummary>, Error> {
+        let mut select =
+            summary_select(Entity::find()).filter(Column::Yank.eq(query.yanked.unwrap_or(false)));
+
+        if let Some(namespace) = &query.namespace {
+            select = select.filter(Column::Namespace.eq(namespace));
+        }
+        if let Some(repository) = &query.repository {
+            let pattern = format!("{}%", escape_like(repository));
+            select = select.filter(
+                Expr::col((Entity, Column::Repository)).like(LikeExpr::new(pattern).escape('\\')),
+            );
+        }
+        if let Some(tag) = &query.tag {
+            select = select.filter(Column::Tag.eq(tag));
+        }
+        if let Some(deprecated) = query.deprecated {
+            select = select.filter(Column::Deprecated.eq(deprecated));
+        }
+
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .min(MAX_SEARCH_LIMIT);
+
+        let results = select
+            .order_by_asc(Column::Namespace)
+            .order_by_asc(Column::Repository)
+            .order_by_desc(Column::CreatedAt)
+            .limit(limit)
+            .into_model::<PackageSummary>()
+            .all(db)
+            .await?;
+        Ok(results)
+    }
+}
+
+/// Selects the columns of a [`PackageSummary`]
+fn summary_select(select: Select<Entity>) -> Select<Entity> {
+    select
+        .select_only()
+        .column(Column::Registry)
+        .column(Column::Namespace)
+        .column(Column::Repository)
+        .column(Column::Tag)
+        .column(Column::Yank)
+        .column(Column::Deprecated)
+        .column(Column::CreatedAt)
+        .column(super::package::Column::Name)
+        .column(super::package::Column::PkgType)
+        .column(super::meta::Column::Description)
+        .column(super::source::Column::Digest)
+        .column(Column::DeprecationMessage)
+        .column(Column::DeprecationSuccessor)
+        .column(Column::DeprecatedAt)
+        .column(Column::PublishedBy)
+        .join(JoinType::InnerJoin, Relation::Package.def())
+        .join(JoinType::InnerJoin, super::package::Relation::Meta.def())
+        .join(JoinType::InnerJoin, super::package::Relation::Sources.def())
+}
+
+/// Matches a namespace and all namespaces below it (e.g. `org` matches `org` and `org/team`, but not `organization`)
+fn subtree_condition(namespace: &str) -> Condition {
+    let pattern = format!("{}/%", escape_like(namespace));
+    Condition::any()
+        .add(Column::Namespace.eq(namespace))
+        .add(Expr::col((Entity, Column::Namespace)).like(LikeExpr::new(pattern).escape('\\')))
+}
+
+/// Escapes the wildcard characters of a `LIKE` pattern
+pub(crate) fn escape_like(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
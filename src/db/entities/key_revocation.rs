@@ -0,0 +1,91 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+};
+use serde::{Deserialize, Serialize};
+
+use super::signing_key;
+use crate::error::Error;
+
+pub type ActiveKeyRevocation = ActiveModel;
+
+/// Revocation of a signing key
+///
+/// Signatures created before the revocation stay trusted, unless the key was compromised earlier -
+/// then all signatures since `compromised_since` are untrusted.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "key_revocations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub key_id: i64,
+    pub reason: String,
+    pub compromised_since: Option<DateTimeUtc>,
+    pub revoked_by: String,
+    pub revoked_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::signing_key::Entity",
+        from = "Column::KeyId",
+        to = "super::signing_key::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    SigningKey,
+}
+
+impl Related<super::signing_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SigningKey.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Point in time, from which on signatures of the key are untrusted
+    pub fn untrusted_since(&self) -> DateTimeUtc {
+        self.compromised_since.unwrap_or(self.revoked_at)
+    }
+}
+
+impl Entity {
+    /// Revokes a key and ends its validity - revoking a key twice returns the original revocation
+    pub async fn revoke(
+        db: &impl ConnectionTrait,
+        key: signing_key::Model,
+        reason: String,
+        compromised_since: Option<DateTimeUtc>,
+        revoked_by: &str,
+    ) -> Result<Model, Error> {
+        let existing = Entity::find()
+            .filter(Column::KeyId.eq(key.id))
+            .one(db)
+            .await?;
+        if let Some(existing) = existing {
+            return Ok(existing);
+        }
+
+        let now = chrono::Utc::now();
+        let key_id = key.id;
+        if key.valid_until.is_none_or(|until| until > now) {
+            let mut key: signing_key::ActiveSigningKey = key.into();
+            key.valid_until = Set(Some(now));
+            key.update(db).await?;
+        }
+        let revocation = ActiveKeyRevocation {
+            id: NotSet,
+            key_id: Set(key_id),
+            reason: Set(reason),
+            compromised_since: Set(compromised_since),
+            revoked_by: Set(revoked_by.to_string()),
+            revoked_at: Set(now),
+        }
+        .insert(db)
+        .await?;
+        Ok(revocation)
+    }
+}
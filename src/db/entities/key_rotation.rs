@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub type ActiveKeyRotation = ActiveModel;
+
+/// Replacement of a signing key by its successor
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "key_rotations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub namespace: String,
+    pub old_key_id: i64,
+    pub new_key_id: i64,
+    pub rotated_by: String,
+    pub rotated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
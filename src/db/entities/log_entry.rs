@@ -0,0 +1,160 @@
+use std::fmt;
+
+use borderless_hash::Hash256;
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+    QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+
+use super::index;
+use crate::{error::Error, transparency};
+
+pub type ActiveLogEntry = ActiveModel;
+
+/// Entry of the transparency log
+///
+/// The leaf of the merkle tree is the hash of `leaf`, which is the json representation of the entry.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "transparency_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip)]
+    pub id: i64,
+    pub kind: String,
+    pub oci: String,
+    pub digest: String,
+    pub actor: String,
+    /// The exact bytes, that are hashed into the leaf
+    pub leaf: String,
+    /// Hex encoded leaf hash
+    pub leaf_hash: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Kind of change, that is recorded in the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogKind {
+    Publish,
+    Yank,
+    Unyank,
+    Deprecate,
+    Undeprecate,
+}
+
+impl fmt::Display for LogKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            LogKind::Publish => "publish",
+            LogKind::Yank => "yank",
+            LogKind::Unyank => "unyank",
+            LogKind::Deprecate => "deprecate",
+            LogKind::Undeprecate => "undeprecate",
+        };
+        f.write_str(kind)
+    }
+}
+
+/// Content of a leaf - the field order is part of the log format and must not change
+#[derive(Serialize)]
+struct Leaf<'a> {
+    kind: LogKind,
+    oci: &'a str,
+    digest: &'a str,
+    actor: &'a str,
+    timestamp: i64,
+}
+
+impl Entity {
+    /// Appends an entry to the log
+    pub async fn append(
+        db: &impl ConnectionTrait,
+        kind: LogKind,
+        oci: &str,
+        digest: &str,
+        actor: &str,
+    ) -> Result<Model, Error> {
+        let now = chrono::Utc::now();
+        let leaf = serde_json::to_string(&Leaf {
+            kind,
+            oci,
+            digest,
+            actor,
+            timestamp: now.timestamp(),
+        })
+        .expect("leaves are serializable");
+        let leaf_hash = transparency::leaf_hash(leaf.as_bytes());
+        let entry = ActiveLogEntry {
+            id: NotSet,
+            kind: Set(kind.to_string()),
+            oci: Set(oci.to_string()),
+            digest: Set(digest.to_string()),
+            actor: Set(actor.to_string()),
+            leaf: Set(leaf),
+            leaf_hash: Set(String::from(leaf_hash)),
+            created_at: Set(now),
+        };
+        Ok(entry.insert(db).await?)
+    }
+
+    /// Appends an entry for a change of the given index entry
+    pub async fn append_index(
+        db: &impl ConnectionTrait,
+        kind: LogKind,
+        entry: &index::Model,
+        actor: &str,
+    ) -> Result<Model, Error> {
+        let digest = index::Entity::digest_of(db, entry.pkg_id)
+            .await?
+            .ok_or_else(|| Error::NotPublished(entry.oci_string()))?;
+        Self::append(db, kind, &entry.oci_string(), &digest, actor).await
+    }
+
+    /// Returns the number of entries in the log
+    pub async fn tree_size(db: &impl ConnectionTrait) -> Result<u64, Error> {
+        Ok(Entity::find().count(db).await?)
+    }
+
+    /// Returns the leaf hashes of the first `size` entries
+    pub async fn leaf_hashes(db: &impl ConnectionTrait, size: u64) -> Result<Vec<Hash256>, Error> {
+        let hashes = Entity::find()
+            .select_only()
+            .column(Column::LeafHash)
+            .order_by_asc(Column::Id)
+            .limit(size)
+            .into_tuple::<String>()
+            .all(db)
+            .await?;
+        hashes
+            .into_iter()
+            .map(|h| {
+                hex::decode(&h)
+                    .ok()
+                    .and_then(|bytes| Hash256::try_from(bytes).ok())
+                    .ok_or_else(|| DbErr::Custom(format!("corrupt leaf hash in log: {h}")).into())
+            })
+            .collect()
+    }
+
+    /// Returns up to `limit` entries, starting at the given leaf index
+    pub async fn entries(
+        db: &impl ConnectionTrait,
+        start: u64,
+        limit: u64,
+    ) -> Result<Vec<Model>, Error> {
+        let entries = Entity::find()
+            .order_by_asc(Column::Id)
+            .offset(start)
+            .limit(limit)
+            .all(db)
+            .await?;
+        Ok(entries)
+    }
+}
@@ -15,7 +15,7 @@ pub type ActiveMeta = ActiveModel;
 #[sea_orm(table_name = "meta")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i64,
     pub description: Option<String>,
     pub documentation: Option<String>,
     pub license: Option<String>,
@@ -59,7 +59,7 @@ impl ActiveMeta {
     pub async fn from_model(
         txn: &DatabaseTransaction,
         meta: borderless_pkg::PkgMeta,
-    ) -> Result<u64, Error> {
+    ) -> Result<i64, Error> {
         let meta_model = ActiveMeta {
             id: NotSet,
             description: Set(meta.description),
@@ -2,9 +2,26 @@ pub mod author;
 pub mod capabilities;
 pub mod git_info;
 pub mod index;
+pub mod key_revocation;
+pub mod key_rotation;
+pub mod log_entry;
 pub mod meta;
+pub mod namespace_policy;
+pub mod namespace_role;
+pub mod oci_blob;
+pub mod oci_manifest;
+pub mod oci_referrer;
+pub mod oci_upload;
+pub mod oci_upload_chunk;
 pub mod package;
 pub mod package_author;
 pub mod registry;
+pub mod signature;
+pub mod signing_key;
 pub mod source;
+pub mod tag_pointer;
+pub mod tag_pointer_history;
+pub mod team;
+pub mod team_member;
+pub mod token;
 pub mod url_white_list;
@@ -0,0 +1,69 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+    QueryOrder, TryIntoModel,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Error, models::namespace_ancestors};
+
+pub type ActiveNamespacePolicy = ActiveModel;
+
+/// Publish policy of a namespace - the most specific policy applies to all namespaces below it
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "namespace_policies")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub namespace: String,
+    /// Rejects publishes without a valid signature
+    pub require_signature: bool,
+    pub updated_by: String,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Entity {
+    /// The policy, that applies to the namespace
+    pub async fn effective(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+    ) -> Result<Option<Model>, Error> {
+        // Longer namespaces are more specific
+        let policy = Entity::find()
+            .filter(Column::Namespace.is_in(namespace_ancestors(namespace)))
+            .order_by_desc(Expr::cust("LENGTH(namespace)"))
+            .one(db)
+            .await?;
+        Ok(policy)
+    }
+
+    /// Sets the policy of a namespace
+    pub async fn set(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        require_signature: bool,
+        updated_by: &str,
+    ) -> Result<Model, Error> {
+        let existing = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .one(db)
+            .await?;
+        let mut policy = match existing {
+            Some(existing) => existing.into(),
+            None => ActiveNamespacePolicy {
+                id: NotSet,
+                namespace: Set(namespace.to_string()),
+                ..Default::default()
+            },
+        };
+        policy.require_signature = Set(require_signature);
+        policy.updated_by = Set(updated_by.to_string());
+        policy.updated_at = Set(chrono::Utc::now());
+        Ok(policy.save(db).await?.try_into_model()?)
+    }
+}
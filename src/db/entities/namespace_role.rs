@@ -0,0 +1,237 @@
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Expr, LikeExpr},
+    ActiveValue::{NotSet, Set},
+    Condition,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{index::escape_like, team};
+use crate::{auth::Role, error::Error, models::namespace_ancestors};
+
+pub type ActiveNamespaceRole = ActiveModel;
+
+/// Role of a user or team in a namespace
+///
+/// Roles are inherited by all namespaces below, so the owner of `org` also owns `org/team`.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "namespace_roles")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub namespace: String,
+    /// Either `user` or `team`
+    pub grantee_kind: String,
+    pub grantee: String,
+    pub role: String,
+    pub granted_by: String,
+    pub granted_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// User or team, that a role is granted to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grantee {
+    User(String),
+    Team(String),
+}
+
+impl Grantee {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Grantee::User(_) => "user",
+            Grantee::Team(_) => "team",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Grantee::User(name) | Grantee::Team(name) => name,
+        }
+    }
+}
+
+impl Entity {
+    /// Direct grants of a namespace (without inherited ones)
+    pub async fn find_grants(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+    ) -> Result<Vec<Model>, Error> {
+        let grants = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .all(db)
+            .await?;
+        Ok(grants)
+    }
+
+    /// A namespace is claimed, if it - or any namespace above it - has an owner
+    pub async fn is_claimed(db: &impl ConnectionTrait, namespace: &str) -> Result<bool, Error> {
+        let owner = Entity::find()
+            .filter(Column::Namespace.is_in(namespace_ancestors(namespace)))
+            .filter(Column::Role.eq(Role::Owner.to_string()))
+            .one(db)
+            .await?;
+        Ok(owner.is_some())
+    }
+
+    /// Checks if a namespace below the given one has an owner other than the user
+    ///
+    /// Claiming the namespace would make its claimer the owner of these namespaces as well.
+    pub async fn is_owned_below(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        subject: &str,
+    ) -> Result<bool, Error> {
+        let pattern = format!("{}/%", escape_like(namespace));
+        let owner = Entity::find()
+            .filter(Expr::col(Column::Namespace).like(LikeExpr::new(pattern).escape('\\')))
+            .filter(Column::Role.eq(Role::Owner.to_string()))
+            .filter(
+                Condition::any()
+                    .add(Column::GranteeKind.ne("user"))
+                    .add(Column::Grantee.ne(subject)),
+            )
+            .one(db)
+            .await?;
+        Ok(owner.is_some())
+    }
+
+    /// Highest role of a user in a namespace - either granted directly or through one of its teams
+    pub async fn effective_role(
+        db: &impl ConnectionTrait,
+        subject: &str,
+        namespace: &str,
+    ) -> Result<Option<Role>, Error> {
+        let teams = team::Entity::names_of_member(db, subject).await?;
+        let grants = Entity::find()
+            .filter(Column::Namespace.is_in(namespace_ancestors(namespace)))
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(Column::GranteeKind.eq("user"))
+                            .add(Column::Grantee.eq(subject)),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(Column::GranteeKind.eq("team"))
+                            .add(Column::Grantee.is_in(teams)),
+                    ),
+            )
+            .all(db)
+            .await?;
+        Ok(grants.iter().filter_map(|g| g.role.parse().ok()).max())
+    }
+
+    /// Grants (or changes) the role of a user or team in a namespace
+    pub async fn grant(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        grantee: &Grantee,
+        role: Role,
+        granted_by: &str,
+    ) -> Result<Model, Error> {
+        if let Grantee::Team(name) = grantee {
+            team::Entity::find_by_name(db, name)
+                .await?
+                .ok_or_else(|| Error::UnknownTeam(name.clone()))?;
+        }
+        let grant = match Self::find_grant(db, namespace, grantee).await? {
+            Some(existing) => {
+                if role != Role::Owner {
+                    Self::ensure_other_owner(db, namespace, &existing).await?;
+                }
+                let mut grant: ActiveNamespaceRole = existing.into();
+                grant.role = Set(role.to_string());
+                grant.granted_by = Set(granted_by.to_string());
+                grant.granted_at = Set(chrono::Utc::now());
+                grant.update(db).await?
+            }
+            None => {
+                ActiveNamespaceRole {
+                    id: NotSet,
+                    namespace: Set(namespace.to_string()),
+                    grantee_kind: Set(grantee.kind().to_string()),
+                    grantee: Set(grantee.name().to_string()),
+                    role: Set(role.to_string()),
+                    granted_by: Set(granted_by.to_string()),
+                    granted_at: Set(chrono::Utc::now()),
+                }
+                .insert(db)
+                .await?
+            }
+        };
+        Ok(grant)
+    }
+
+    /// Removes the role of a user or team in a namespace
+    pub async fn revoke(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        grantee: &Grantee,
+    ) -> Result<Option<Model>, Error> {
+        let Some(existing) = Self::find_grant(db, namespace, grantee).await? else {
+            return Ok(None);
+        };
+        Self::ensure_other_owner(db, namespace, &existing).await?;
+        Entity::delete_by_id(existing.id).exec(db).await?;
+        Ok(Some(existing))
+    }
+
+    /// Makes the grantee the only owner of the namespace
+    pub async fn transfer(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        grantee: &Grantee,
+        granted_by: &str,
+    ) -> Result<Model, Error> {
+        let owner = Self::grant(db, namespace, grantee, Role::Owner, granted_by).await?;
+        Entity::delete_many()
+            .filter(Column::Namespace.eq(namespace))
+            .filter(Column::Role.eq(Role::Owner.to_string()))
+            .filter(Column::Id.ne(owner.id))
+            .exec(db)
+            .await?;
+        Ok(owner)
+    }
+
+    async fn find_grant(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        grantee: &Grantee,
+    ) -> Result<Option<Model>, Error> {
+        let grant = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .filter(Column::GranteeKind.eq(grantee.kind()))
+            .filter(Column::Grantee.eq(grantee.name()))
+            .one(db)
+            .await?;
+        Ok(grant)
+    }
+
+    /// Prevents, that a namespace loses its last owner
+    async fn ensure_other_owner(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        grant: &Model,
+    ) -> Result<(), Error> {
+        if grant.role != Role::Owner.to_string() {
+            return Ok(());
+        }
+        let other_owner = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .filter(Column::Role.eq(Role::Owner.to_string()))
+            .filter(Column::Id.ne(grant.id))
+            .one(db)
+            .await?;
+        if other_owner.is_none() {
+            return Err(Error::LastOwner(namespace.to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::OnConflict,
+    ActiveValue::{NotSet, Set},
+};
+
+use crate::{
+    blobs::{self, BlobStore},
+    error::Error,
+    oci,
+};
+
+pub type ActiveOciBlob = ActiveModel;
+
+/// Uploaded blob, that is not yet (or never) referenced by a manifest
+///
+/// The content lives in the blob store under [`oci::blob_key`].
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "oci_blobs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub digest: String,
+    /// Content of blobs, that were uploaded before the blob store existed
+    pub data: Option<Vec<u8>>,
+    /// Size of the (uncompressed) blob in the blob store - `None` until a blob is moved into the store
+    pub size: Option<i64>,
+    /// Encoding of the blob in the blob store
+    pub encoding: Option<String>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Entity {
+    /// Stores a blob - blobs are content addressed, so storing the same blob twice only refreshes it
+    ///
+    /// The garbage collection keeps blobs for a grace period after their (last) upload. The content is
+    /// written to the blob store before the row - callers hold the index lock, so the garbage collection
+    /// never sees the content without its row.
+    pub async fn store(
+        db: &impl ConnectionTrait,
+        blobs: &dyn BlobStore,
+        digest: &str,
+        data: Vec<u8>,
+    ) -> Result<(), Error> {
+        let size = data.len();
+        let encoding = blobs::store_wasm(blobs, &oci::blob_key(digest)?, data).await?;
+        let blob = ActiveOciBlob {
+            id: NotSet,
+            digest: Set(digest.to_string()),
+            data: Set(None),
+            size: Set(Some(size as i64)),
+            encoding: Set(Some(encoding.to_string())),
+            created_at: Set(chrono::Utc::now()),
+        };
+        Entity::insert(blob)
+            .on_conflict(
+                OnConflict::column(Column::Digest)
+                    .update_column(Column::CreatedAt)
+                    .to_owned(),
+            )
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Restarts the grace period of a blob, e.g. when it is mounted into another repository
+    pub async fn touch(db: &impl ConnectionTrait, digest: &str) -> Result<(), Error> {
+        Entity::update_many()
+            .col_expr(Column::CreatedAt, Expr::value(chrono::Utc::now()))
+            .filter(Column::Digest.eq(digest))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Looks up the blob with the given digest
+    pub async fn find_by_digest(
+        db: &impl ConnectionTrait,
+        digest: &str,
+    ) -> Result<Option<Model>, Error> {
+        let blob = Entity::find()
+            .filter(Column::Digest.eq(digest))
+            .one(db)
+            .await?;
+        Ok(blob)
+    }
+
+    /// Moves the content of blobs, that were uploaded before the blob store existed, into the store
+    ///
+    /// Blobs are moved one by one, so the migration can be interrupted and picked up on the next start.
+    /// Returns the number of moved blobs.
+    pub async fn move_legacy_blobs(
+        db: &impl ConnectionTrait,
+        blobs: &dyn BlobStore,
+    ) -> Result<u64, Error> {
+        let mut moved = 0;
+        while let Some(blob) = Entity::find()
+            .filter(Column::Data.is_not_null())
+            .one(db)
+            .await?
+        {
+            let data = blob.data.unwrap_or_default();
+            let size = data.len();
+            let encoding = blobs::store_wasm(blobs, &oci::blob_key(&blob.digest)?, data).await?;
+            ActiveOciBlob {
+                id: Set(blob.id),
+                data: Set(None),
+                size: Set(Some(size as i64)),
+                encoding: Set(Some(encoding.to_string())),
+                ..Default::default()
+            }
+            .update(db)
+            .await?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+}
@@ -0,0 +1,143 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+    JoinType, QuerySelect,
+};
+
+use crate::{error::Error, oci::OciArtifact};
+
+pub type ActiveOciManifest = ActiveModel;
+
+/// OCI manifest of a published package
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "oci_manifests")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub index_id: i64,
+    pub digest: String,
+    pub media_type: String,
+    pub content: Vec<u8>,
+    pub config_digest: String,
+    pub config: Vec<u8>,
+    pub layer_digest: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::index::Entity",
+        from = "Column::IndexId",
+        to = "super::index::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Index,
+}
+
+impl Related<super::index::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Index.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Blob, that is referenced by a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestBlob {
+    /// Content of a config blob
+    Config(Vec<u8>),
+    /// Digest, size and encoding of the source, whose wasm is the layer - the wasm itself lives in the blob store
+    Layer {
+        digest: String,
+        size: Option<i64>,
+        encoding: Option<String>,
+    },
+}
+
+impl Entity {
+    /// Stores the manifest of a registry index entry
+    pub async fn store(
+        db: &impl ConnectionTrait,
+        index_id: i64,
+        artifact: OciArtifact,
+    ) -> Result<Model, Error> {
+        let manifest = ActiveOciManifest {
+            id: NotSet,
+            index_id: Set(index_id),
+            digest: Set(artifact.digest),
+            media_type: Set(artifact.media_type),
+            content: Set(artifact.manifest),
+            config_digest: Set(artifact.config_digest),
+            config: Set(artifact.config),
+            layer_digest: Set(artifact.layer_digest),
+            created_at: Set(chrono::Utc::now()),
+        };
+        Ok(manifest.insert(db).await?)
+    }
+
+    /// Returns the manifest of a registry index entry
+    pub async fn find_for_index(
+        db: &impl ConnectionTrait,
+        index_id: i64,
+    ) -> Result<Option<Model>, Error> {
+        let manifest = Entity::find()
+            .filter(Column::IndexId.eq(index_id))
+            .one(db)
+            .await?;
+        Ok(manifest)
+    }
+
+    /// Returns the manifest with the given digest in a repository, together with its index entry
+    pub async fn find_by_digest(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        repository: &str,
+        digest: &str,
+    ) -> Result<Option<(Model, super::index::Model)>, Error> {
+        let found = Entity::find()
+            .find_also_related(super::index::Entity)
+            .filter(Column::Digest.eq(digest))
+            .filter(super::index::Column::Namespace.eq(namespace))
+            .filter(super::index::Column::Repository.eq(repository))
+            .one(db)
+            .await?;
+        Ok(found.and_then(|(manifest, entry)| Some((manifest, entry?))))
+    }
+
+    /// Looks up a config or layer blob, that is referenced by a manifest
+    pub async fn find_blob(
+        db: &impl ConnectionTrait,
+        digest: &str,
+    ) -> Result<Option<ManifestBlob>, Error> {
+        let config = Entity::find()
+            .select_only()
+            .column(Column::Config)
+            .filter(Column::ConfigDigest.eq(digest))
+            .into_tuple::<Vec<u8>>()
+            .one(db)
+            .await?;
+        if let Some(config) = config {
+            return Ok(Some(ManifestBlob::Config(config)));
+        }
+        let source = Entity::find()
+            .select_only()
+            .column(super::source::Column::Digest)
+            .column(super::source::Column::Size)
+            .column(super::source::Column::Encoding)
+            .join(JoinType::InnerJoin, Relation::Index.def())
+            .join(JoinType::InnerJoin, super::index::Relation::Package.def())
+            .join(JoinType::InnerJoin, super::package::Relation::Sources.def())
+            .filter(Column::LayerDigest.eq(digest))
+            .into_tuple::<(String, Option<i64>, Option<String>)>()
+            .one(db)
+            .await?;
+        Ok(source.map(|(digest, size, encoding)| ManifestBlob::Layer {
+            digest,
+            size,
+            encoding,
+        }))
+    }
+}
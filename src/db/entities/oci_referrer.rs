@@ -0,0 +1,133 @@
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::OnConflict,
+    ActiveValue::{NotSet, Set},
+    QueryOrder,
+};
+
+use crate::{
+    error::Error,
+    oci::{self, Descriptor, ImageManifest},
+};
+
+pub type ActiveOciReferrer = ActiveModel;
+
+/// Artifact, whose manifest refers to another manifest via its `subject`
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "oci_referrers")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub namespace: String,
+    pub repository: String,
+    pub digest: String,
+    pub media_type: String,
+    pub artifact_type: String,
+    pub content: Vec<u8>,
+    pub subject_digest: String,
+    /// Json encoded annotations of the manifest
+    pub annotations: Option<String>,
+    pub pushed_by: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Descriptor of the referrer, as it is listed in the referrers index
+    pub fn descriptor(&self) -> Descriptor {
+        Descriptor {
+            media_type: self.media_type.clone(),
+            digest: self.digest.clone(),
+            size: self.content.len() as u64,
+            artifact_type: Some(self.artifact_type.clone()),
+            annotations: self
+                .annotations
+                .as_deref()
+                .and_then(|a| serde_json::from_str(a).ok()),
+        }
+    }
+}
+
+impl Entity {
+    /// Stores an artifact, that refers to the subject of its manifest
+    ///
+    /// Manifests are content addressed, so storing the same manifest twice is a no-op.
+    pub async fn store(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        repository: &str,
+        manifest: &ImageManifest,
+        content: Vec<u8>,
+        pushed_by: &str,
+    ) -> Result<String, Error> {
+        let subject = manifest
+            .subject
+            .as_ref()
+            .ok_or_else(|| Error::InvalidManifest("manifest has no subject".into()))?;
+        let digest = oci::digest(&content);
+        let referrer = ActiveOciReferrer {
+            id: NotSet,
+            namespace: Set(namespace.to_string()),
+            repository: Set(repository.to_string()),
+            digest: Set(digest.clone()),
+            media_type: Set(oci::MANIFEST_MEDIA_TYPE.to_string()),
+            artifact_type: Set(manifest.artifact_type().to_string()),
+            content: Set(content),
+            subject_digest: Set(subject.digest.clone()),
+            annotations: Set(manifest
+                .annotations
+                .as_ref()
+                .map(|a| serde_json::to_string(a).expect("annotations are serializable"))),
+            pushed_by: Set(pushed_by.to_string()),
+            created_at: Set(chrono::Utc::now()),
+        };
+        Entity::insert(referrer)
+            .on_conflict(
+                OnConflict::columns([Column::Namespace, Column::Repository, Column::Digest])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec(db)
+            .await?;
+        Ok(digest)
+    }
+
+    /// Returns the referrer with the given digest in a repository
+    pub async fn find_by_digest(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        repository: &str,
+        digest: &str,
+    ) -> Result<Option<Model>, Error> {
+        let referrer = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .filter(Column::Repository.eq(repository))
+            .filter(Column::Digest.eq(digest))
+            .one(db)
+            .await?;
+        Ok(referrer)
+    }
+
+    /// Returns all referrers of a manifest, optionally restricted to an artifact type
+    pub async fn find_for_subject(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        repository: &str,
+        subject: &str,
+        artifact_type: Option<&str>,
+    ) -> Result<Vec<Model>, Error> {
+        let mut select = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .filter(Column::Repository.eq(repository))
+            .filter(Column::SubjectDigest.eq(subject));
+        if let Some(artifact_type) = artifact_type {
+            select = select.filter(Column::ArtifactType.eq(artifact_type));
+        }
+        Ok(select.order_by_asc(Column::Id).all(db).await?)
+    }
+}
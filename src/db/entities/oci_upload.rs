@@ -0,0 +1,135 @@
+use rand::{rngs::OsRng, RngCore};
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+    Condition, QueryOrder, QuerySelect,
+};
+
+use super::oci_upload_chunk::{self, ActiveOciUploadChunk};
+use crate::error::Error;
+
+pub type ActiveOciUpload = ActiveModel;
+
+/// Blob upload session
+///
+/// The uploaded data is stored in chunks (see [`oci_upload_chunk`]).
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "oci_uploads")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    /// Repository (`<namespace>/<repository>`), the upload was started in
+    pub repository: String,
+    pub created_at: DateTimeUtc,
+    /// Number of bytes, that were uploaded so far
+    pub size: i64,
+    /// Time of the last chunk
+    pub updated_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::oci_upload_chunk::Entity")]
+    Chunks,
+}
+
+impl Related<super::oci_upload_chunk::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Chunks.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Entity {
+    /// Starts a new upload session in the given repository
+    pub async fn start(db: &impl ConnectionTrait, repository: &str) -> Result<Model, Error> {
+        let mut id = [0u8; 16];
+        OsRng.fill_bytes(&mut id);
+        let now = chrono::Utc::now();
+        let upload = ActiveOciUpload {
+            id: Set(hex::encode(id)),
+            repository: Set(repository.to_string()),
+            created_at: Set(now),
+            size: Set(0),
+            updated_at: Set(Some(now)),
+        };
+        Ok(upload.insert(db).await?)
+    }
+
+    /// Returns the upload session with the given id in a repository
+    pub async fn find_session(
+        db: &impl ConnectionTrait,
+        repository: &str,
+        id: &str,
+    ) -> Result<Model, Error> {
+        Entity::find_by_id(id)
+            .filter(Column::Repository.eq(repository))
+            .one(db)
+            .await?
+            .ok_or_else(|| Error::UnknownUpload(id.to_string()))
+    }
+
+    /// Appends a chunk at the given offset, which must be the current size of the upload
+    pub async fn append(
+        db: &impl ConnectionTrait,
+        upload: Model,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> Result<Model, Error> {
+        if offset != upload.size as u64 {
+            return Err(Error::InvalidRange {
+                expected: upload.size as u64,
+            });
+        }
+        if data.is_empty() {
+            return Ok(upload);
+        }
+        let size = upload.size + data.len() as i64;
+        ActiveOciUploadChunk {
+            id: NotSet,
+            upload_id: Set(upload.id.clone()),
+            offset: Set(upload.size),
+            data: Set(data),
+        }
+        .insert(db)
+        .await?;
+        let mut upload: ActiveOciUpload = upload.into();
+        upload.size = Set(size);
+        upload.updated_at = Set(Some(chrono::Utc::now()));
+        Ok(upload.update(db).await?)
+    }
+
+    /// Returns the uploaded data
+    pub async fn assemble(db: &impl ConnectionTrait, upload: &Model) -> Result<Vec<u8>, Error> {
+        let chunks = oci_upload_chunk::Entity::find()
+            .select_only()
+            .column(oci_upload_chunk::Column::Data)
+            .filter(oci_upload_chunk::Column::UploadId.eq(&upload.id))
+            .order_by_asc(oci_upload_chunk::Column::Offset)
+            .into_tuple::<Vec<u8>>()
+            .all(db)
+            .await?;
+        let mut data = Vec::with_capacity(upload.size as usize);
+        for chunk in chunks {
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data)
+    }
+
+    /// Deletes all uploads, that were inactive since the given time, and returns their number
+    pub async fn expire(
+        db: &impl ConnectionTrait,
+        inactive_since: DateTimeUtc,
+    ) -> Result<u64, Error> {
+        let inactive = Condition::any()
+            .add(Column::UpdatedAt.lt(inactive_since))
+            .add(
+                Condition::all()
+                    .add(Column::UpdatedAt.is_null())
+                    .add(Column::CreatedAt.lt(inactive_since)),
+            );
+        let result = Entity::delete_many().filter(inactive).exec(db).await?;
+        Ok(result.rows_affected)
+    }
+}
@@ -0,0 +1,35 @@
+use sea_orm::entity::prelude::*;
+
+pub type ActiveOciUploadChunk = ActiveModel;
+
+/// Chunk of a blob upload
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "oci_upload_chunks")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub upload_id: String,
+    /// Position of the chunk in the blob
+    pub offset: i64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::oci_upload::Entity",
+        from = "Column::UploadId",
+        to = "super::oci_upload::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Upload,
+}
+
+impl Related<super::oci_upload::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Upload.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -8,7 +8,7 @@ use super::capabilities::ActiveCapabilities;
 use super::meta::ActiveMeta;
 use super::source::ActiveSource;
 
-use crate::error::Error;
+use crate::{blobs::BlobStore, error::Error};
 
 pub type ActivePackage = ActiveModel;
 
@@ -16,14 +16,14 @@ pub type ActivePackage = ActiveModel;
 #[sea_orm(table_name = "packages")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i64,
     pub name: String,
     pub app_name: Option<String>,
     pub app_module: Option<String>,
     pub pkg_type: String,
-    pub meta_id: u64,
-    pub source_id: u64,
-    pub capabilities_id: Option<u64>,
+    pub meta_id: i64,
+    pub source_id: i64,
+    pub capabilities_id: Option<i64>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -75,10 +75,11 @@ impl ActiveModelBehavior for ActiveModel {}
 impl ActivePackage {
     pub async fn from_model(
         txn: &DatabaseTransaction,
+        blobs: &dyn BlobStore,
         model: borderless_pkg::WasmPkg,
     ) -> Result<Model, Error> {
         let meta_id = ActiveMeta::from_model(txn, model.meta).await?;
-        let source_id = ActiveSource::from_model(txn, model.source).await?;
+        let source_id = ActiveSource::from_model(txn, blobs, model.source).await?;
 
         let capabilities_id = if let Some(capa) = model.capabilities {
             let id = ActiveCapabilities::from_model(txn, capa).await?;
@@ -87,16 +88,23 @@ impl ActivePackage {
             None
         };
 
+        let pkg_type = match model.pkg_type {
+            borderless_pkg::PkgType::Contract => "contract",
+            borderless_pkg::PkgType::Agent => "agent",
+        };
+
+        let now = chrono::Utc::now().fixed_offset();
         let package = ActivePackage {
             id: NotSet,
             name: Set(model.name),
             app_name: Set(model.app_name),
             app_module: Set(model.app_module),
-            pkg_type: Set(model.pkg_type.to_string()),
+            pkg_type: Set(pkg_type.to_string()),
             meta_id: Set(meta_id),
             source_id: Set(source_id),
             capabilities_id: Set(capabilities_id),
-            ..Default::default()
+            created_at: Set(now),
+            updated_at: Set(now),
         };
 
         let pkg_result = ActivePackage::insert(package, txn).await?;
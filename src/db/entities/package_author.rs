@@ -11,9 +11,9 @@ pub type ActivePackageAuthors = ActiveModel;
 #[sea_orm(table_name = "package_authors")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
-    pub meta_id: u64,
+    pub meta_id: i64,
     #[sea_orm(primary_key, auto_increment = false)]
-    pub author_id: u64,
+    pub author_id: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -54,8 +54,8 @@ impl ActiveModelBehavior for ActiveModel {}
 impl ActivePackageAuthors {
     pub async fn from_ids(
         txn: &DatabaseTransaction,
-        meta_id: u64,
-        author_id: u64,
+        meta_id: i64,
+        author_id: i64,
     ) -> Result<(), Error> {
         let meta_pkg = ActivePackageAuthors {
             meta_id: Set(meta_id),
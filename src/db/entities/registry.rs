@@ -12,7 +12,7 @@ pub type ActiveRegistry = ActiveModel;
 #[sea_orm(table_name = "registries")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i64,
     pub registry_type: Option<String>,
     pub hostname: String,
     pub namespace: String,
@@ -27,7 +27,7 @@ impl ActiveRegistry {
     pub async fn from_model(
         txn: &DatabaseTransaction,
         registry: borderless_pkg::Registry,
-    ) -> Result<u64, Error> {
+    ) -> Result<i64, Error> {
         let registry = ActiveRegistry {
             id: NotSet,
             registry_type: Set(registry.registry_type),
@@ -0,0 +1,144 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+    JoinType, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{key_revocation, signing_key};
+use crate::error::Error;
+
+pub type ActiveSignature = ActiveModel;
+
+/// Detached signature of a package source
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "signatures")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub source_id: i64,
+    pub key_id: i64,
+    /// Base64 encoded signature
+    pub signature: String,
+    /// The signed payload (canonical manifest)
+    pub payload: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::source::Entity",
+        from = "Column::SourceId",
+        to = "super::source::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Source,
+    #[sea_orm(
+        belongs_to = "super::signing_key::Entity",
+        from = "Column::KeyId",
+        to = "super::signing_key::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Restrict"
+    )]
+    SigningKey,
+}
+
+impl Related<super::source::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Source.def()
+    }
+}
+
+impl Related<super::signing_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SigningKey.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// A signature together with the key, that verifies it - everything a node needs to verify it offline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureInfo {
+    pub namespace: String,
+    pub fingerprint: String,
+    pub public_key: String,
+    pub signature: String,
+    pub payload: String,
+    pub created_at: DateTimeUtc,
+    /// The key was valid, when the signature was created, and was not compromised since
+    pub trusted: bool,
+    pub key_revoked_at: Option<DateTimeUtc>,
+    pub key_compromised_since: Option<DateTimeUtc>,
+}
+
+impl Entity {
+    /// Stores a verified signature - storing the same signature twice is a no-op
+    pub async fn record(
+        db: &impl ConnectionTrait,
+        source_id: i64,
+        key_id: i64,
+        signature: &str,
+        payload: &str,
+    ) -> Result<(), Error> {
+        let existing = Entity::find()
+            .filter(Column::SourceId.eq(source_id))
+            .filter(Column::KeyId.eq(key_id))
+            .one(db)
+            .await?;
+        if existing.is_some() {
+            return Ok(());
+        }
+        ActiveSignature {
+            id: NotSet,
+            source_id: Set(source_id),
+            key_id: Set(key_id),
+            signature: Set(signature.to_string()),
+            payload: Set(payload.to_string()),
+            created_at: Set(chrono::Utc::now()),
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    /// All signatures of the source with the given (full hex) digest
+    pub async fn find_by_digest(
+        db: &impl ConnectionTrait,
+        digest: &str,
+    ) -> Result<Vec<SignatureInfo>, Error> {
+        let rows = Entity::find()
+            .join(JoinType::InnerJoin, Relation::Source.def())
+            .filter(super::source::Column::Digest.eq(digest))
+            .find_also_related(signing_key::Entity)
+            .all(db)
+            .await?;
+        let key_ids: Vec<_> = rows.iter().map(|(s, _)| s.key_id).collect();
+        let revocations = key_revocation::Entity::find()
+            .filter(key_revocation::Column::KeyId.is_in(key_ids))
+            .all(db)
+            .await?;
+
+        let signatures = rows
+            .into_iter()
+            .filter_map(|(signature, key)| {
+                let key = key?;
+                let revocation = revocations.iter().find(|r| r.key_id == key.id);
+                Some(SignatureInfo {
+                    trusted: key.trusted_at(signature.created_at, revocation),
+                    key_revoked_at: revocation.map(|r| r.revoked_at),
+                    key_compromised_since: revocation.and_then(|r| r.compromised_since),
+                    namespace: key.namespace,
+                    fingerprint: key.fingerprint,
+                    public_key: key.public_key,
+                    signature: signature.signature,
+                    payload: signature.payload,
+                    created_at: signature.created_at,
+                })
+            })
+            .collect();
+        Ok(signatures)
+    }
+}
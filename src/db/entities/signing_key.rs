@@ -0,0 +1,247 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{key_revocation, key_rotation};
+use crate::{error::Error, models::namespace_ancestors};
+
+pub type ActiveSigningKey = ActiveModel;
+
+/// Ed25519 public key, that may sign packages of a namespace (and the namespaces below it)
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "signing_keys")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub namespace: String,
+    /// Base64 encoded public key
+    pub public_key: String,
+    /// Hex encoded sha3-256 hash of the public key
+    pub fingerprint: String,
+    pub registered_by: String,
+    pub created_at: DateTimeUtc,
+    /// Start of the validity window - `None` means since the key was registered
+    pub valid_from: Option<DateTimeUtc>,
+    /// End of the validity window - `None` means open ended
+    pub valid_until: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::signature::Entity")]
+    Signatures,
+    #[sea_orm(has_one = "super::key_revocation::Entity")]
+    Revocation,
+}
+
+impl Related<super::signature::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Signatures.def()
+    }
+}
+
+impl Related<super::key_revocation::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Revocation.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Validity window of a key
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct KeyValidity {
+    #[serde(default)]
+    pub valid_from: Option<DateTimeUtc>,
+    #[serde(default)]
+    pub valid_until: Option<DateTimeUtc>,
+}
+
+impl Model {
+    /// Checks, if a signature created at `signed_at` is trustworthy
+    ///
+    /// The key must have been valid at that time, and the signature must predate a revocation (or compromise).
+    pub fn trusted_at(
+        &self,
+        signed_at: DateTimeUtc,
+        revocation: Option<&key_revocation::Model>,
+    ) -> bool {
+        signed_at >= self.valid_from.unwrap_or(self.created_at)
+            && self.valid_until.is_none_or(|until| signed_at < until)
+            && revocation.is_none_or(|r| signed_at < r.untrusted_since())
+    }
+}
+
+impl Entity {
+    /// Keys registered directly for the namespace, together with their revocation
+    pub async fn find_for_namespace(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+    ) -> Result<Vec<(Model, Option<key_revocation::Model>)>, Error> {
+        let keys = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .find_also_related(key_revocation::Entity)
+            .all(db)
+            .await?;
+        Ok(keys)
+    }
+
+    pub async fn find_by_fingerprint(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        fingerprint: &str,
+    ) -> Result<Option<Model>, Error> {
+        let key = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .filter(Column::Fingerprint.eq(fingerprint))
+            .one(db)
+            .await?;
+        Ok(key)
+    }
+
+    /// Keys, that may currently sign packages of the namespace - including keys of all namespaces above it
+    pub async fn find_trusted(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+    ) -> Result<Vec<Model>, Error> {
+        let now = chrono::Utc::now();
+        let keys = Entity::find()
+            .filter(Column::Namespace.is_in(namespace_ancestors(namespace)))
+            .find_also_related(key_revocation::Entity)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter(|(key, revocation)| key.trusted_at(now, revocation.as_ref()))
+            .map(|(key, _)| key)
+            .collect();
+        Ok(keys)
+    }
+
+    /// Registers a key for a namespace - registering a known key returns the existing entry
+    pub async fn register(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        public_key: &str,
+        fingerprint: &str,
+        validity: KeyValidity,
+        registered_by: &str,
+    ) -> Result<(Model, bool), Error> {
+        if let Some(existing) = Self::find_by_fingerprint(db, namespace, fingerprint).await? {
+            return Ok((existing, false));
+        }
+        let now = chrono::Utc::now();
+        let key = ActiveSigningKey {
+            id: NotSet,
+            namespace: Set(namespace.to_string()),
+            public_key: Set(public_key.to_string()),
+            fingerprint: Set(fingerprint.to_string()),
+            registered_by: Set(registered_by.to_string()),
+            created_at: Set(now),
+            valid_from: Set(Some(validity.valid_from.unwrap_or(now))),
+            valid_until: Set(validity.valid_until),
+        }
+        .insert(db)
+        .await?;
+        Ok((key, true))
+    }
+
+    /// Replaces a key by a new one
+    ///
+    /// The old key stays valid for `overlap`, so packages signed shortly before the rotation are still accepted.
+    pub async fn rotate(
+        db: &impl ConnectionTrait,
+        old: Model,
+        public_key: &str,
+        fingerprint: &str,
+        overlap: chrono::Duration,
+        rotated_by: &str,
+    ) -> Result<(Model, Model, key_rotation::Model), Error> {
+        let now = chrono::Utc::now();
+        let namespace = old.namespace.clone();
+        let (new, _) = Self::register(
+            db,
+            &namespace,
+            public_key,
+            fingerprint,
+            KeyValidity::default(),
+            rotated_by,
+        )
+        .await?;
+
+        let end = now + overlap;
+        let old = if old.valid_until.is_none_or(|until| until > end) {
+            let mut old: ActiveSigningKey = old.into();
+            old.valid_until = Set(Some(end));
+            old.update(db).await?
+        } else {
+            old
+        };
+
+        let rotation = key_rotation::ActiveKeyRotation {
+            id: NotSet,
+            namespace: Set(namespace),
+            old_key_id: Set(old.id),
+            new_key_id: Set(new.id),
+            rotated_by: Set(rotated_by.to_string()),
+            rotated_at: Set(now),
+        }
+        .insert(db)
+        .await?;
+        Ok((old, new, rotation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn key(valid_until: Option<DateTimeUtc>) -> Model {
+        let created_at = Utc::now() - Duration::days(10);
+        Model {
+            id: 1,
+            namespace: "org".to_string(),
+            public_key: String::new(),
+            fingerprint: String::new(),
+            registered_by: "alice".to_string(),
+            created_at,
+            valid_from: Some(created_at),
+            valid_until,
+        }
+    }
+
+    fn revocation(compromised_since: Option<DateTimeUtc>) -> key_revocation::Model {
+        key_revocation::Model {
+            id: 1,
+            key_id: 1,
+            reason: "leaked".to_string(),
+            compromised_since,
+            revoked_by: "alice".to_string(),
+            revoked_at: Utc::now() - Duration::days(1),
+        }
+    }
+
+    #[test]
+    fn trust_follows_validity_and_revocation() {
+        let now = Utc::now();
+        let key_ = key(None);
+        assert!(key_.trusted_at(now, None));
+        assert!(!key_.trusted_at(now - Duration::days(11), None));
+
+        let expired = key(Some(now - Duration::days(5)));
+        assert!(expired.trusted_at(now - Duration::days(6), None));
+        assert!(!expired.trusted_at(now - Duration::days(4), None));
+
+        // Signatures before the revocation stay trusted ..
+        let revoked = revocation(None);
+        assert!(key_.trusted_at(now - Duration::days(2), Some(&revoked)));
+        assert!(!key_.trusted_at(now, Some(&revoked)));
+
+        // .. unless the key was compromised before
+        let compromised = revocation(Some(now - Duration::days(3)));
+        assert!(key_.trusted_at(now - Duration::days(4), Some(&compromised)));
+        assert!(!key_.trusted_at(now - Duration::days(2), Some(&compromised)));
+    }
+}
@@ -1,6 +1,11 @@
+use borderless_hash::Hash256;
 use sea_orm::{entity::prelude::*, ActiveValue::NotSet, DatabaseTransaction, Set};
 
-use crate::{db::entities::git_info::ActiveGitInfo, error::Error};
+use crate::{
+    blobs::{self, BlobStore, Encoding},
+    db::entities::git_info::ActiveGitInfo,
+    error::Error,
+};
 
 use super::registry::ActiveRegistry;
 
@@ -10,12 +15,18 @@ pub type ActiveSource = ActiveModel;
 #[sea_orm(table_name = "sources")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i64,
     pub version: String,
     pub digest: String,
+    pub source_type: String,
+    /// Wasm of sources, that were written before the blob store existed
     pub wasm_blob: Option<Vec<u8>>,
-    pub registry_id: Option<u64>,
-    pub git_info_id: Option<u64>,
+    pub registry_id: Option<i64>,
+    pub git_info_id: Option<i64>,
+    /// Size of the (uncompressed) wasm in the blob store - `None` until the wasm of a registry source is pulled
+    pub size: Option<i64>,
+    /// Encoding of the wasm in the blob store - `None` for blobs, that were stored uncompressed
+    pub encoding: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -53,14 +64,113 @@ impl Related<super::git_info::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+impl Entity {
+    /// Looks up a source by its (full) content digest
+    pub async fn find_by_digest(
+        db: &impl ConnectionTrait,
+        digest: &Hash256,
+    ) -> Result<Option<Model>, Error> {
+        let source = Entity::find()
+            .filter(Column::Digest.eq(String::from(*digest)))
+            .one(db)
+            .await?;
+        Ok(source)
+    }
+
+    /// Records size and encoding of a source, whose wasm was pulled into the blob store
+    pub async fn set_blob(
+        db: &impl ConnectionTrait,
+        id: i64,
+        size: usize,
+        encoding: Encoding,
+    ) -> Result<(), Error> {
+        ActiveSource {
+            id: Set(id),
+            size: Set(Some(size as i64)),
+            encoding: Set(Some(encoding.to_string())),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Moves the wasm of sources, that were written before the blob store existed, into the store
+    ///
+    /// Sources are moved one by one, so the migration can be interrupted and picked up on the next start.
+    /// Returns the number of moved blobs.
+    pub async fn move_legacy_blobs(
+        db: &impl ConnectionTrait,
+        blobs: &dyn BlobStore,
+    ) -> Result<u64, Error> {
+        let mut moved = 0;
+        while let Some(source) = Entity::find()
+            .filter(Column::WasmBlob.is_not_null())
+            .one(db)
+            .await?
+        {
+            let wasm = source.wasm_blob.unwrap_or_default();
+            let digest = blobs::parse_digest(&source.digest)?;
+            let size = wasm.len();
+            let encoding = blobs::store_wasm(blobs, &digest, wasm).await?;
+            ActiveSource {
+                id: Set(source.id),
+                wasm_blob: Set(None),
+                size: Set(Some(size as i64)),
+                encoding: Set(Some(encoding.to_string())),
+                ..Default::default()
+            }
+            .update(db)
+            .await?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+}
+
 impl ActiveSource {
     pub async fn from_model(
         txn: &DatabaseTransaction,
+        blobs: &dyn BlobStore,
         source: borderless_pkg::Source,
-    ) -> Result<u64, Error> {
-        let (wasm, git_info, registry) = match source.code {
-            borderless_pkg::SourceType::Wasm { wasm, git_info } => (Some(wasm), git_info, None),
-            borderless_pkg::SourceType::Registry { registry } => (None, None, Some(registry)),
+    ) -> Result<i64, Error> {
+        let (source_type, wasm, git_info, registry) = match source.code {
+            borderless_pkg::SourceType::Wasm { wasm, git_info } => {
+                // Never trust the digest declared by the client
+                let computed = Hash256::digest(&wasm);
+                if computed != source.digest {
+                    return Err(Error::DigestMismatch {
+                        declared: String::from(source.digest),
+                        computed: String::from(computed),
+                    });
+                }
+                ("wasm", Some(wasm), git_info, None)
+            }
+            borderless_pkg::SourceType::Registry { registry } => {
+                ("registry", None, None, Some(registry))
+            }
+        };
+
+        // Sources are content-addressed, so identical code is only stored once
+        if let Some(existing) = Entity::find_by_digest(txn, &source.digest).await? {
+            // Sources, that reference another registry, have no blob yet - the wasm of this publish fills the gap
+            if let (Some(wasm), None) = (wasm, existing.size) {
+                let size = wasm.len();
+                let encoding = blobs::store_wasm(blobs, &source.digest, wasm).await?;
+                Entity::set_blob(txn, existing.id, size, encoding).await?;
+            }
+            return Ok(existing.id);
+        }
+
+        // The blob is written before the transaction commits. If the publish fails afterwards,
+        // the blob is left without a source and removed by the garbage collection.
+        let (size, encoding) = match wasm {
+            Some(wasm) => {
+                let size = wasm.len();
+                let encoding = blobs::store_wasm(blobs, &source.digest, wasm).await?;
+                (Some(size as i64), Some(encoding.to_string()))
+            }
+            None => (None, None),
         };
 
         let git_id = if let Some(git) = git_info {
@@ -80,10 +190,14 @@ impl ActiveSource {
         let src = ActiveSource {
             id: NotSet,
             version: Set(source.version.to_string()),
-            digest: Set(source.digest.to_string()),
-            wasm_blob: Set(wasm),
+            // NOTE: Display of Hash256 is truncated, so we have to use the full base16 string here
+            digest: Set(String::from(source.digest)),
+            source_type: Set(source_type.to_string()),
+            wasm_blob: Set(None),
             git_info_id: Set(git_id),
             registry_id: Set(registry_id),
+            size: Set(size),
+            encoding: Set(encoding),
         };
 
         let src_result = ActiveSource::insert(src, txn).await?;
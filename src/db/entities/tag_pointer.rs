@@ -0,0 +1,160 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+};
+use semver::Version;
+
+use super::{index, tag_pointer_history};
+use crate::{
+    error::Error,
+    models::{OciIdentifier, Tag, LATEST, NIGHTLY},
+};
+
+pub type ActiveTagPointer = ActiveModel;
+
+/// Current target of a floating tag
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "tag_pointers")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub namespace: String,
+    pub repository: String,
+    pub keyword: String,
+    pub index_id: i64,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::index::Entity",
+        from = "Column::IndexId",
+        to = "super::index::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Index,
+}
+
+impl Related<super::index::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Index.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Entity {
+    /// Returns the index entry, that a floating tag currently points to
+    pub async fn target(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        repository: &str,
+        keyword: &str,
+    ) -> Result<Option<index::Model>, Error> {
+        let target = Entity::find()
+            .filter(Column::Namespace.eq(namespace))
+            .filter(Column::Repository.eq(repository))
+            .filter(Column::Keyword.eq(keyword))
+            .find_also_related(index::Entity)
+            .one(db)
+            .await?;
+        Ok(target.and_then(|(_, entry)| entry))
+    }
+
+    /// Resolves a floating tag to the concrete version it points to
+    ///
+    /// Identifiers with any other tag are returned unchanged.
+    pub async fn resolve(
+        db: &impl ConnectionTrait,
+        oid: OciIdentifier,
+    ) -> Result<OciIdentifier, Error> {
+        let Some(keyword) = oid.tag.floating_keyword() else {
+            return Ok(oid);
+        };
+        let target = Self::target(db, &oid.namespace, &oid.repository, keyword)
+            .await?
+            .ok_or_else(|| Error::NotPublished(oid.to_string()))?;
+        Ok(OciIdentifier {
+            tag: Tag::parse(&target.tag),
+            registry: Some(target.registry)
+                .filter(|r| !r.is_empty())
+                .and_then(|r| r.parse().ok()),
+            ..oid
+        })
+    }
+
+    /// Moves the floating tags of a repository to the newest eligible versions
+    ///
+    /// [`LATEST`] points to the highest stable version, [`NIGHTLY`] to the highest prerelease.
+    /// Yanked versions are never eligible. Every move is recorded in the pointer history.
+    pub async fn refresh(
+        db: &impl ConnectionTrait,
+        namespace: &str,
+        repository: &str,
+    ) -> Result<(), Error> {
+        let tags = index::Entity::find_tags(db, namespace, repository).await?;
+        let versions: Vec<_> = tags
+            .into_iter()
+            .filter(|t| !t.yank)
+            .filter_map(|t| Version::parse(&t.tag).ok().map(|v| (v, t)))
+            .collect();
+
+        for keyword in [LATEST, NIGHTLY] {
+            let target = versions
+                .iter()
+                .filter(|(v, _)| v.pre.is_empty() == (keyword == LATEST))
+                .max_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(_, t)| t);
+
+            let current = Entity::find()
+                .filter(Column::Namespace.eq(namespace))
+                .filter(Column::Repository.eq(repository))
+                .filter(Column::Keyword.eq(keyword))
+                .one(db)
+                .await?;
+
+            if current.as_ref().map(|c| c.index_id) == target.map(|t| t.id) {
+                continue;
+            }
+
+            let now = chrono::Utc::now();
+            match (current, target) {
+                (Some(current), Some(target)) => {
+                    let mut pointer: ActiveTagPointer = current.into();
+                    pointer.index_id = Set(target.id);
+                    pointer.updated_at = Set(now);
+                    pointer.update(db).await?;
+                }
+                (None, Some(target)) => {
+                    let pointer = ActiveTagPointer {
+                        id: NotSet,
+                        namespace: Set(namespace.to_string()),
+                        repository: Set(repository.to_string()),
+                        keyword: Set(keyword.to_string()),
+                        index_id: Set(target.id),
+                        updated_at: Set(now),
+                    };
+                    pointer.insert(db).await?;
+                }
+                (Some(current), None) => {
+                    current.delete(db).await?;
+                }
+                (None, None) => unreachable!("pointer without change is skipped"),
+            }
+
+            let history = tag_pointer_history::ActiveModel {
+                id: NotSet,
+                namespace: Set(namespace.to_string()),
+                repository: Set(repository.to_string()),
+                keyword: Set(keyword.to_string()),
+                tag: Set(target.map(|t| t.tag.clone())),
+                digest: Set(target.map(|t| t.digest.clone())),
+                moved_at: Set(now),
+            };
+            history.insert(db).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+};
+use serde::{Deserialize, Serialize};
+
+use super::team_member;
+use crate::error::Error;
+
+pub type ActiveTeam = ActiveModel;
+
+/// A named group of users, that can be granted namespace roles
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "teams")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub created_by: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::team_member::Entity")]
+    Members,
+}
+
+impl Related<super::team_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Members.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Entity {
+    pub async fn find_by_name(
+        db: &impl ConnectionTrait,
+        name: &str,
+    ) -> Result<Option<Model>, Error> {
+        let team = Entity::find().filter(Column::Name.eq(name)).one(db).await?;
+        Ok(team)
+    }
+
+    /// Creates a team, with its creator as first member
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        name: &str,
+        created_by: &str,
+    ) -> Result<Model, Error> {
+        let team = ActiveTeam {
+            id: NotSet,
+            name: Set(name.to_string()),
+            created_by: Set(created_by.to_string()),
+            created_at: Set(chrono::Utc::now()),
+        }
+        .insert(db)
+        .await?;
+        team_member::Entity::add(db, team.id, created_by).await?;
+        Ok(team)
+    }
+
+    /// Names of all teams, that the subject is a member of
+    pub async fn names_of_member(
+        db: &impl ConnectionTrait,
+        subject: &str,
+    ) -> Result<Vec<String>, Error> {
+        let teams = Entity::find()
+            .inner_join(team_member::Entity)
+            .filter(team_member::Column::Subject.eq(subject))
+            .all(db)
+            .await?;
+        Ok(teams.into_iter().map(|t| t.name).collect())
+    }
+}
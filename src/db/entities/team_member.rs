@@ -0,0 +1,95 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+pub type ActiveTeamMember = ActiveModel;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "team_members")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub team_id: i64,
+    pub subject: String,
+    pub added_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::team::Entity",
+        from = "Column::TeamId",
+        to = "super::team::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Team,
+}
+
+impl Related<super::team::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Team.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Entity {
+    pub async fn find_members(
+        db: &impl ConnectionTrait,
+        team_id: i64,
+    ) -> Result<Vec<Model>, Error> {
+        let members = Entity::find()
+            .filter(Column::TeamId.eq(team_id))
+            .all(db)
+            .await?;
+        Ok(members)
+    }
+
+    pub async fn is_member(
+        db: &impl ConnectionTrait,
+        team_id: i64,
+        subject: &str,
+    ) -> Result<bool, Error> {
+        let member = Entity::find()
+            .filter(Column::TeamId.eq(team_id))
+            .filter(Column::Subject.eq(subject))
+            .one(db)
+            .await?;
+        Ok(member.is_some())
+    }
+
+    /// Adds a member to a team - adding an existing member is a no-op
+    pub async fn add(db: &impl ConnectionTrait, team_id: i64, subject: &str) -> Result<(), Error> {
+        if Self::is_member(db, team_id, subject).await? {
+            return Ok(());
+        }
+        ActiveTeamMember {
+            id: NotSet,
+            team_id: Set(team_id),
+            subject: Set(subject.to_string()),
+            added_at: Set(chrono::Utc::now()),
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes a member from a team, returns `false` if the subject was no member
+    pub async fn remove(
+        db: &impl ConnectionTrait,
+        team_id: i64,
+        subject: &str,
+    ) -> Result<bool, Error> {
+        let result = Entity::delete_many()
+            .filter(Column::TeamId.eq(team_id))
+            .filter(Column::Subject.eq(subject))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+}
@@ -0,0 +1,118 @@
+use sea_orm::{
+    entity::prelude::*,
+    ActiveValue::{NotSet, Set},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::Scope, error::Error};
+
+pub type ActiveToken = ActiveModel;
+
+/// API token - the secret itself is never stored, only its hash
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "tokens")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Human readable name of the token, e.g. `ci-release`
+    pub name: String,
+    /// User or service, the token was issued for
+    pub subject: String,
+    #[serde(skip)]
+    pub secret_hash: String,
+    /// Comma separated list of scopes
+    pub scopes: String,
+    /// Comma separated list of namespaces - `None` grants access to all namespaces
+    pub namespaces: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub expires_at: Option<DateTimeUtc>,
+    pub revoked_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Parameters of a new token
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewToken {
+    pub name: String,
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+    /// Namespaces (including their subtrees), the token is restricted to
+    #[serde(default)]
+    pub namespaces: Option<Vec<String>>,
+    #[serde(default)]
+    pub expires_at: Option<DateTimeUtc>,
+}
+
+impl Model {
+    /// Parsed scopes of the token
+    pub fn scope_list(&self) -> Vec<Scope> {
+        self.scopes
+            .split(',')
+            .filter_map(|s| s.parse().ok())
+            .collect()
+    }
+
+    /// Parsed namespace restriction of the token
+    pub fn namespace_list(&self) -> Option<Vec<String>> {
+        self.namespaces
+            .as_ref()
+            .map(|ns| ns.split(',').map(str::to_string).collect())
+    }
+
+    /// A token is valid, if it is neither revoked nor expired
+    pub fn is_valid(&self, now: DateTimeUtc) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|exp| exp > now)
+    }
+}
+
+impl Entity {
+    /// Stores a new token with the given secret
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        token: NewToken,
+        secret: &str,
+    ) -> Result<Model, Error> {
+        let scopes: Vec<_> = token.scopes.iter().map(Scope::to_string).collect();
+        let token = ActiveToken {
+            id: NotSet,
+            name: Set(token.name),
+            subject: Set(token.subject),
+            secret_hash: Set(crate::auth::hash_secret(secret)),
+            scopes: Set(scopes.join(",")),
+            namespaces: Set(token.namespaces.map(|ns| ns.join(","))),
+            created_at: Set(chrono::Utc::now()),
+            expires_at: Set(token.expires_at),
+            revoked_at: Set(None),
+        };
+        Ok(token.insert(db).await?)
+    }
+
+    /// Looks up a token by its secret - regardless of whether it is still valid
+    pub async fn find_by_secret(
+        db: &impl ConnectionTrait,
+        secret: &str,
+    ) -> Result<Option<Model>, Error> {
+        let token = Entity::find()
+            .filter(Column::SecretHash.eq(crate::auth::hash_secret(secret)))
+            .one(db)
+            .await?;
+        Ok(token)
+    }
+
+    /// Revokes a token - revoking a token twice keeps the original revocation time
+    pub async fn revoke(db: &impl ConnectionTrait, id: i64) -> Result<Option<Model>, Error> {
+        let Some(token) = Entity::find_by_id(id).one(db).await? else {
+            return Ok(None);
+        };
+        if token.revoked_at.is_some() {
+            return Ok(Some(token));
+        }
+        let mut token: ActiveToken = token.into();
+        token.revoked_at = Set(Some(chrono::Utc::now()));
+        Ok(Some(token.update(db).await?))
+    }
+}
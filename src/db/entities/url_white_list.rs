@@ -12,8 +12,8 @@ pub type ActiveUrlWhitelist = ActiveModel;
 #[sea_orm(table_name = "url_whitelist")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
-    pub capability_id: u64,
+    pub id: i64,
+    pub capability_id: i64,
     pub url: String,
 }
 
@@ -40,9 +40,9 @@ impl ActiveModelBehavior for ActiveModel {}
 impl ActiveUrlWhitelist {
     pub async fn from_id_and_url(
         txn: &DatabaseTransaction,
-        capability_id: u64,
+        capability_id: i64,
         url: String,
-    ) -> Result<u64, Error> {
+    ) -> Result<i64, Error> {
         let url_whitelist = ActiveUrlWhitelist {
             id: NotSet,
             capability_id: Set(capability_id),
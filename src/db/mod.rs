@@ -1,38 +1,91 @@
 pub mod entities;
 
-use sea_orm::{ConnectOptions, Database, DatabaseConnection, DbErr};
+use std::path::Path;
+
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbErr, TransactionTrait,
+};
 use sea_orm_migration::prelude::*;
 
 use crate::migrator::Migrator;
 
 use tracing::{debug, error, info, instrument};
 
+/// Path that selects a transient in-memory database
+pub const IN_MEMORY: &str = ":memory:";
+
+/// Configuration of the database and its connection pool
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    /// Path to the SQLite database file (or [`IN_MEMORY`])
+    pub path: String,
+    /// Maximum number of pooled connections
+    pub max_connections: u32,
+    /// Minimum number of idle connections kept in the pool
+    pub min_connections: u32,
+}
+
+impl DbConfig {
+    /// Configuration for a transient in-memory database
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self {
+            path: IN_MEMORY.to_string(),
+            max_connections: 1,
+            min_connections: 1,
+        }
+    }
+
+    fn is_in_memory(&self) -> bool {
+        self.path == IN_MEMORY
+    }
+
+    fn connect_options(&self) -> ConnectOptions {
+        let mut opt = if self.is_in_memory() {
+            // Every connection to an in-memory database would open a new database,
+            // so we keep exactly one connection alive for the lifetime of the pool.
+            let mut opt = ConnectOptions::new("sqlite::memory:");
+            opt.max_connections(1).min_connections(1);
+            opt
+        } else {
+            // mode=rwc creates the database file, if it does not exist.
+            // The path is percent-encoded, so '?', '#' or '%' in it are not parsed as url syntax.
+            let mut opt = ConnectOptions::new(format!(
+                "sqlite://{}?mode=rwc",
+                urlencoding::encode(&self.path)
+            ));
+            opt.max_connections(self.max_connections.max(1))
+                .min_connections(self.min_connections.min(self.max_connections));
+            opt
+        };
+
+        // Turn this on for detailed database loging
+        opt.sqlx_logging(false);
+        opt
+    }
+}
+
 #[instrument(err)]
-pub async fn setup_database(db_url: &str) -> Result<DatabaseConnection, DbErr> {
-    let mut opt = ConnectOptions::new("sqlite::memory:");
-
-    // Turn this on for detailed database loging
-    opt.sqlx_logging(false);
-    let db = Database::connect(opt).await?;
-
-    let migrations = Migrator::migrations();
-    for (index, _migration) in migrations.iter().enumerate() {
-        let step = (index + 1) as u32;
-        match Migrator::up(&db, Some(step)).await {
-            Ok(_) => {}
-            Err(e) => {
-                error!("❌ FAILED at migration step {}", step);
-                debug!("Error: {}", e);
-
-                if let Some(failed_migration) = migrations.get(index) {
-                    error!("Failed migration name: {}", failed_migration.name());
-                }
-
-                return Err(e.into());
-            }
+pub async fn setup_database(config: &DbConfig) -> Result<DatabaseConnection, DbErr> {
+    if !config.is_in_memory() {
+        if let Some(parent) = Path::new(&config.path).parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| DbErr::Custom(format!("failed to create database directory: {e}")))?;
         }
     }
 
+    let db = Database::connect(config.connect_options()).await?;
+
+    // NOTE: sqlx enables foreign keys for every pooled connection by default,
+    // but we make sure, that this is actually the case.
+    db.execute_unprepared("PRAGMA foreign_keys = ON").await?;
+    if !config.is_in_memory() {
+        // The journal mode is persisted in the database file
+        db.execute_unprepared("PRAGMA journal_mode = WAL").await?;
+    }
+
+    apply_migrations(&db).await?;
+
     info!("Check database schema!");
     let schema_manager = SchemaManager::new(&db);
     assert!(schema_manager.has_table("authors").await?);
@@ -44,6 +97,186 @@ pub async fn setup_database(db_url: &str) -> Result<DatabaseConnection, DbErr> {
     assert!(schema_manager.has_table("packages").await?);
     assert!(schema_manager.has_table("meta").await?);
     assert!(schema_manager.has_table("package_authors").await?);
+    assert!(schema_manager.has_table("registry_index").await?);
+    assert!(schema_manager.has_table("tag_pointers").await?);
+    assert!(schema_manager.has_table("tokens").await?);
+    assert!(schema_manager.has_table("teams").await?);
+    assert!(schema_manager.has_table("team_members").await?);
+    assert!(schema_manager.has_table("namespace_roles").await?);
+    assert!(schema_manager.has_table("signing_keys").await?);
+    assert!(schema_manager.has_table("signatures").await?);
+    assert!(schema_manager.has_table("namespace_policies").await?);
+    assert!(schema_manager.has_table("key_revocations").await?);
+    assert!(schema_manager.has_table("key_rotations").await?);
+    assert!(schema_manager.has_table("transparency_log").await?);
+    assert!(schema_manager.has_table("oci_manifests").await?);
+    assert!(schema_manager.has_table("oci_blobs").await?);
+    assert!(schema_manager.has_table("oci_uploads").await?);
+    assert!(schema_manager.has_table("oci_upload_chunks").await?);
+    assert!(schema_manager.has_table("oci_referrers").await?);
 
     Ok(db)
 }
+
+/// Applies all pending migrations atomically
+///
+/// sea-orm-migration runs SQLite migrations outside of a transaction, statement by statement on any
+/// connection of the pool. Table rebuilds - like the one of `package_authors` - break on a pooled file
+/// database, and a migration that fails halfway would leave a schema behind, that neither the old nor
+/// the new code can rely on. Therefore all pending migrations are applied on a single connection and
+/// in one transaction.
+async fn apply_migrations(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let pending = Migrator::get_pending_migrations(db).await?;
+    if pending.is_empty() {
+        info!("Database schema is up to date");
+        return Ok(());
+    }
+    info!("Apply {} pending migrations", pending.len());
+
+    let txn = db.begin().await?;
+    for migration in pending {
+        if let Err(e) = Migrator::up(&txn, Some(1)).await {
+            error!("❌ FAILED at migration {}", migration.name());
+            debug!("Error: {}", e);
+            txn.rollback().await?;
+            return Err(e);
+        }
+    }
+    txn.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{EntityTrait, PaginatorTrait, Statement};
+
+    fn file_config(dir: &tempfile::TempDir) -> DbConfig {
+        DbConfig {
+            path: dir
+                .path()
+                .join("nested/registry.db")
+                .to_string_lossy()
+                .to_string(),
+            max_connections: 4,
+            min_connections: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn file_database_survives_restart() -> Result<(), DbErr> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = file_config(&dir);
+
+        let db = setup_database(&config).await?;
+        let meta = entities::meta::ActiveModel {
+            description: sea_orm::Set(Some("persisted".to_string())),
+            ..Default::default()
+        };
+        entities::meta::Entity::insert(meta).exec(&db).await?;
+        db.close().await?;
+
+        // Re-opening the database must not re-apply any migration
+        let db = setup_database(&config).await?;
+        assert!(Migrator::get_pending_migrations(&db).await?.is_empty());
+        assert_eq!(entities::meta::Entity::find().count(&db).await?, 1);
+
+        let row = db
+            .query_one(Statement::from_string(
+                db.get_database_backend(),
+                "PRAGMA journal_mode",
+            ))
+            .await?
+            .unwrap();
+        let mode: String = row.try_get_by_index(0)?;
+        assert_eq!(mode, "wal");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_upgrades_are_rolled_back() -> Result<(), DbErr> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = file_config(&dir);
+        std::fs::create_dir_all(dir.path().join("nested")).unwrap();
+
+        // A leftover table makes a late migration fail, after earlier ones already ran
+        let db = Database::connect(config.connect_options()).await?;
+        Migrator::up(&db, Some(10)).await?;
+        db.execute_unprepared("CREATE TABLE package_authors_rebuilt (id integer)")
+            .await?;
+        db.close().await?;
+        assert!(setup_database(&config).await.is_err());
+
+        let db = Database::connect(config.connect_options()).await?;
+        let pending = Migrator::get_pending_migrations(&db).await?;
+        assert_eq!(
+            pending.len(),
+            Migrator::migrations().len() - 10,
+            "no migration may be applied partially"
+        );
+        assert!(!SchemaManager::new(&db).has_table("tag_pointers").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn paths_with_url_syntax() -> Result<(), DbErr> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("odd?name#with%20chars.db");
+        let config = DbConfig {
+            path: path.to_string_lossy().to_string(),
+            ..file_config(&dir)
+        };
+
+        let db = setup_database(&config).await?;
+        db.close().await?;
+        assert!(path.exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn foreign_keys_are_enforced() -> Result<(), DbErr> {
+        let db = setup_database(&DbConfig::in_memory()).await?;
+        let index = entities::index::ActiveModel {
+            pkg_id: sea_orm::Set(4711),
+            registry: sea_orm::Set(String::new()),
+            namespace: sea_orm::Set("ns".to_string()),
+            repository: sea_orm::Set("repo".to_string()),
+            tag: sea_orm::Set("1.0.0".to_string()),
+            yank: sea_orm::Set(false),
+            deprecated: sea_orm::Set(false),
+            created_at: sea_orm::Set(chrono::Utc::now()),
+            ..Default::default()
+        };
+        assert!(entities::index::Entity::insert(index)
+            .exec(&db)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn baseline_databases_are_upgraded() -> Result<(), DbErr> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = file_config(&dir);
+        std::fs::create_dir_all(dir.path().join("nested")).unwrap();
+
+        // A database, that only knows the baseline schema, with a package author linked by package
+        let db = Database::connect(config.connect_options()).await?;
+        Migrator::up(&db, Some(10)).await?;
+        db.execute_unprepared(
+            "INSERT INTO authors (id, name) VALUES (1, 'Jane Doe');
+             INSERT INTO meta (id) VALUES (7);
+             INSERT INTO sources (id, version, digest, source_type) VALUES (1, '0.1.0', 'ab', 'wasm');
+             INSERT INTO packages (id, name, pkg_type, source_id, meta_id, created_at, updated_at)
+                 VALUES (3, 'counter', 'contract', 1, 7, '2025-06-05', '2025-06-05');
+             INSERT INTO package_authors (package_id, author_id) VALUES (3, 1);",
+        )
+        .await?;
+        db.close().await?;
+
+        let db = setup_database(&config).await?;
+        let links = entities::package_author::Entity::find().all(&db).await?;
+        assert_eq!(links.len(), 1);
+        assert_eq!((links[0].meta_id, links[0].author_id), (7, 1));
+        Ok(())
+    }
+}
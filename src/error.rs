@@ -1,6 +1,10 @@
 use std::string::FromUtf8Error;
 
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use borderless_hash::Hash256;
 use serde_json::json;
 use thiserror::Error;
@@ -13,6 +17,8 @@ pub enum Error {
     Bincode(#[from] bincode::Error),
     #[error("Storage error - {0}")]
     Storage(#[from] borderless_kv_store::Error),
+    #[error("I/O error - {0}")]
+    Io(#[from] std::io::Error),
     #[error("Duplicated Key - {0}")]
     Dublicated(Hash256),
     #[error("No entry in storage for key - {0}")]
@@ -25,34 +31,187 @@ pub enum Error {
     UrlEncoding,
     #[error("Invalid path!")]
     InvalidPath,
+    #[error("Invalid digest - expected 64 base16 characters")]
+    InvalidDigest,
+    #[error("No package published as {0}")]
+    NotPublished(String),
+    #[error("Package {oci} was yanked - {reason}")]
+    Yanked { oci: String, reason: String },
+    #[error("Unauthorized - {0}")]
+    Unauthorized(&'static str),
+    #[error("Forbidden - token lacks the '{scope}' scope{}", namespace.as_ref().map(|ns| format!(" for namespace {ns}")).unwrap_or_default())]
+    Forbidden {
+        scope: String,
+        namespace: Option<String>,
+    },
+    #[error("Forbidden - requires role '{required}' in namespace {namespace}")]
+    MissingRole { required: String, namespace: String },
+    #[error("No role granted to {grantee} in namespace {namespace}")]
+    NoRole { grantee: String, namespace: String },
+    #[error("Namespace {0} must keep at least one owner")]
+    LastOwner(String),
+    #[error("Namespace {0} cannot be claimed - a namespace below it is owned by someone else")]
+    ClaimConflict(String),
+    #[error("No team named {0}")]
+    UnknownTeam(String),
+    #[error("Invalid public key - expected a base64 encoded ed25519 key")]
+    InvalidKey,
+    #[error("No signing key with fingerprint {0}")]
+    UnknownKey(String),
+    #[error("Invalid validity window")]
+    InvalidValidity,
+    #[error("Invalid signature - no key of the namespace verifies the package")]
+    InvalidSignature,
+    #[error("Namespace {0} requires signed packages")]
+    SignatureRequired(String),
+    #[error("No token with id {0}")]
+    UnknownToken(i64),
+    #[error("Invalid pagination cursor")]
+    InvalidCursor,
+    #[error("Invalid proof request - {0}")]
+    InvalidProofRequest(String),
+    #[error("Invalid manifest - {0}")]
+    InvalidManifest(String),
+    #[error("No manifest {0}")]
+    UnknownManifest(String),
+    #[error("No blob with digest {0}")]
+    UnknownBlob(String),
+    #[error("No blob upload with id {0}")]
+    UnknownUpload(String),
+    #[error("Invalid range - the upload continues at offset {expected}")]
+    InvalidRange { expected: u64 },
+    #[error("Tag '{0}' is a floating tag and moves automatically")]
+    FloatingTag(String),
+    #[error("Invalid version requirement - {0}")]
+    InvalidVersionReq(#[from] semver::Error),
+    #[error("No version of {repository} matches {req}")]
+    NoMatchingVersion { repository: String, req: String },
+    #[error("Invalid successor - {0}")]
+    InvalidSuccessor(String),
+    #[error("Conflict - {oci} is already published with digest {existing}")]
+    Conflict {
+        oci: String,
+        existing: String,
+        attempted: String,
+    },
+    #[error("Digest mismatch - declared {declared}, but wasm hashes to {computed}")]
+    DigestMismatch { declared: String, computed: String },
+    #[error("Upstream registry error - {0}")]
+    Upstream(String),
     #[error("Oci path error - {0} ")]
     Oci(#[from] models::Error),
     #[error("UTF-8 error - {0}")]
     UTF8(#[from] FromUtf8Error),
 }
 
+impl Error {
+    /// Http status code of the error
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Bincode(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Dublicated(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::NoPkg(_) => StatusCode::NOT_FOUND,
+            Error::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::InvalidSource => StatusCode::NO_CONTENT,
+            Error::UrlEncoding => StatusCode::BAD_REQUEST,
+            Error::InvalidPath => StatusCode::BAD_REQUEST,
+            Error::InvalidDigest => StatusCode::BAD_REQUEST,
+            Error::DigestMismatch { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Conflict { .. } => StatusCode::CONFLICT,
+            Error::NotPublished(_) => StatusCode::NOT_FOUND,
+            Error::Yanked { .. } => StatusCode::GONE,
+            Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Error::Forbidden { .. } => StatusCode::FORBIDDEN,
+            Error::MissingRole { .. } => StatusCode::FORBIDDEN,
+            Error::NoRole { .. } => StatusCode::NOT_FOUND,
+            Error::LastOwner(_) => StatusCode::CONFLICT,
+            Error::ClaimConflict(_) => StatusCode::CONFLICT,
+            Error::UnknownTeam(_) => StatusCode::NOT_FOUND,
+            Error::InvalidKey => StatusCode::BAD_REQUEST,
+            Error::UnknownKey(_) => StatusCode::NOT_FOUND,
+            Error::InvalidValidity => StatusCode::BAD_REQUEST,
+            Error::InvalidSignature => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::SignatureRequired(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::UnknownToken(_) => StatusCode::NOT_FOUND,
+            Error::InvalidCursor => StatusCode::BAD_REQUEST,
+            Error::InvalidProofRequest(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidManifest(_) => StatusCode::BAD_REQUEST,
+            Error::UnknownManifest(_) => StatusCode::NOT_FOUND,
+            Error::UnknownBlob(_) => StatusCode::NOT_FOUND,
+            Error::UnknownUpload(_) => StatusCode::NOT_FOUND,
+            Error::InvalidRange { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
+            Error::FloatingTag(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidVersionReq(_) => StatusCode::BAD_REQUEST,
+            Error::NoMatchingVersion { .. } => StatusCode::NOT_FOUND,
+            Error::InvalidSuccessor(_) => StatusCode::BAD_REQUEST,
+            Error::Upstream(_) => StatusCode::BAD_GATEWAY,
+            Error::Oci(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::UTF8(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            Error::Conflict {
+                oci,
+                existing,
+                attempted,
+            } => Some(json!({
+                "oci": oci,
+                "existing_digest": existing,
+                "attempted_digest": attempted,
+            })),
+            Error::Yanked { oci, reason } => Some(json!({
+                "oci": oci,
+                "reason": reason,
+            })),
+            Error::Forbidden { scope, namespace } => Some(json!({
+                "scope": scope,
+                "namespace": namespace,
+            })),
+            Error::MissingRole {
+                required,
+                namespace,
+            } => Some(json!({
+                "required_role": required,
+                "namespace": namespace,
+            })),
+            Error::InvalidRange { expected } => Some(json!({
+                "expected_offset": expected,
+            })),
+            Error::DigestMismatch { declared, computed } => Some(json!({
+                "declared_digest": declared,
+                "computed_digest": computed,
+            })),
+            _ => None,
+        }
+    }
+}
+
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
-        let (status, error_message) = match &self {
-            Error::Bincode(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Error::Storage(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Error::Dublicated(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Error::NoPkg(_) => (StatusCode::NOT_FOUND, self.to_string()),
-            Error::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Error::InvalidSource => (StatusCode::NO_CONTENT, self.to_string()),
-            Error::UrlEncoding => (StatusCode::BAD_REQUEST, self.to_string()),
-            Error::InvalidPath => (StatusCode::BAD_GATEWAY, self.to_string()),
-            Error::Oci(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Error::UTF8(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-        };
-
-        let body = Json(json!({
+        let status = self.status();
+        let error_message = self.to_string();
+
+        let mut body = json!({
             "error": {
                 "message": error_message,
                 "status": status.as_u16()
             }
-        }));
+        });
+
+        // Some errors carry structured details, that clients can act upon
+        if let Some(details) = self.details() {
+            body["error"]["details"] = details;
+        }
+        let body = Json(body);
 
+        // Tell clients how to authenticate
+        if let Error::Unauthorized(_) = self {
+            return (status, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response();
+        }
         (status, body).into_response()
     }
 }
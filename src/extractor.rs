@@ -1,13 +1,30 @@
 use crate::{error::Error, models::OciIdentifier};
 use axum::{
-    extract::{FromRequestParts, MatchedPath, Path},
+    extract::{FromRequestParts, Path},
     http::request::Parts,
 };
+use borderless_hash::Hash256;
 use std::str::FromStr;
 use tracing::info;
 
 pub struct OciId(pub OciIdentifier);
 
+/// Extracts a base16 encoded [`Hash256`] from the request path
+pub struct Digest(pub Hash256);
+
+/// Extracts a `namespace/repository` path (without tag) from the request path
+///
+/// Like in the [`OciIdentifier`], the namespace can contain multiple segments.
+#[derive(Debug, Clone)]
+pub struct RepoPath {
+    pub namespace: String,
+    pub repository: String,
+}
+
+/// Extracts a (possibly multi-segment) namespace from the request path
+#[derive(Debug, Clone)]
+pub struct NamespacePath(pub String);
+
 // First, let's implement the Axum extractor for OciIdentifier
 impl<S> FromRequestParts<S> for OciId
 where
@@ -28,13 +45,75 @@ where
         info!("Decoded Path: {0}", decoded_path);
 
         // Parse the OCI identifier
-        let id = OciIdentifier::from_str(&decoded_path)?;
+        let id = OciIdentifier::from_str(&decoded_path).map_err(|_| Error::InvalidPath)?;
 
         info!("Oci Parameter: {:?}", id);
         Ok(OciId(id))
     }
 }
 
+impl<S> FromRequestParts<S> for Digest
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(digest): Path<String> = Path::from_request_parts(&mut parts.clone(), state)
+            .await
+            .map_err(|_| Error::InvalidPath)?;
+
+        let bytes = hex::decode(&digest).map_err(|_| Error::InvalidDigest)?;
+        let hash = Hash256::try_from(bytes).map_err(|_| Error::InvalidDigest)?;
+        Ok(Digest(hash))
+    }
+}
+
+impl<S> FromRequestParts<S> for RepoPath
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(path): Path<String> = Path::from_request_parts(&mut parts.clone(), state)
+            .await
+            .map_err(|_| Error::InvalidPath)?;
+        let decoded_path = urlencoding::decode(&path)?;
+
+        let (namespace, repository) = decoded_path
+            .trim_matches('/')
+            .rsplit_once('/')
+            .ok_or(Error::InvalidPath)?;
+        if namespace.is_empty() || repository.is_empty() {
+            return Err(Error::InvalidPath);
+        }
+        Ok(RepoPath {
+            namespace: namespace.to_string(),
+            repository: repository.to_string(),
+        })
+    }
+}
+
+impl<S> FromRequestParts<S> for NamespacePath
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(path): Path<String> = Path::from_request_parts(&mut parts.clone(), state)
+            .await
+            .map_err(|_| Error::InvalidPath)?;
+        let decoded_path = urlencoding::decode(&path)?;
+        let namespace = decoded_path.trim_matches('/');
+        if namespace.is_empty() {
+            return Err(Error::InvalidPath);
+        }
+        Ok(NamespacePath(namespace.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,7 +130,7 @@ mod tests {
     // Handler function that uses the OCI extractor
     async fn get_package(oci: OciId) -> Result<Json<serde_json::Value>, StatusCode> {
         // Your business logic here
-        println!("Requested package: {}", oci.0.to_string());
+        println!("Requested package: {}", oci.0);
         println!("Registry: {:?}", oci.0.registry);
         println!("Namespace: {}", oci.0.namespace);
         println!("Repository: {}", oci.0.repository);
@@ -158,4 +237,31 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn malformed_paths_are_bad_requests() {
+        async fn repo(repo: RepoPath) -> String {
+            repo.repository
+        }
+        async fn namespace(NamespacePath(ns): NamespacePath) -> String {
+            ns
+        }
+        let app = create_router()
+            .route("/repo/{*path}", get(repo))
+            .route("/ns/{*path}", get(namespace));
+
+        for uri in [
+            "/api/v0/nginx:latest:1",
+            "/repo/counter",
+            "/repo/%2F",
+            "/ns/%2F",
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
 }
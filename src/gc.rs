@@ -0,0 +1,570 @@
+//! Garbage collection of rows and blobs, that no registry index entry references any more
+//!
+//! Every publish inserts fresh `meta`, `authors`, `capabilities`, `git_info` and `registries` rows.
+//! A row is live, if it is reachable from a `registry_index` entry - or from a package, that is younger
+//! than the grace period. Everything else is deleted in batches. Each batch holds the index lock only
+//! for one select and one delete, so publishes never see a row disappear under them and are never
+//! blocked for long.
+//!
+//! The index lock only exists within one process. The `gc` subcommand must therefore not run
+//! against the database of a running server - the server collects garbage itself (`--gc-interval-hours`).
+use std::{collections::HashSet, fmt, time::Duration};
+
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Query, SelectStatement},
+    ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QuerySelect,
+};
+use tracing::{info, warn};
+
+use crate::{
+    db::entities::{
+        author, capabilities, git_info, index, meta, oci_blob, oci_referrer, package,
+        package_author, registry, source,
+    },
+    error::Error,
+    oci::{self, ImageManifest},
+    AppState,
+};
+
+/// Collects orphaned rows and blobs
+pub struct Gc {
+    state: AppState,
+    grace: chrono::Duration,
+    batch_size: u64,
+}
+
+/// Number of orphans per table - or that would be deleted in a dry run
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GcReport {
+    pub packages: u64,
+    pub meta: u64,
+    pub authors: u64,
+    pub capabilities: u64,
+    pub sources: u64,
+    pub git_info: u64,
+    pub registries: u64,
+    /// Uploaded OCI blobs, that no referrer references - their content is removed from the blob store with them
+    pub oci_blobs: u64,
+    /// Blobs in the blob store, that neither a source nor an uploaded OCI blob references
+    pub blobs: u64,
+}
+
+impl GcReport {
+    pub fn total(&self) -> u64 {
+        self.packages
+            + self.meta
+            + self.authors
+            + self.capabilities
+            + self.sources
+            + self.git_info
+            + self.registries
+            + self.oci_blobs
+            + self.blobs
+    }
+}
+
+impl fmt::Display for GcReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "packages      {}", self.packages)?;
+        writeln!(f, "meta          {}", self.meta)?;
+        writeln!(f, "authors       {}", self.authors)?;
+        writeln!(f, "capabilities  {}", self.capabilities)?;
+        writeln!(f, "sources       {}", self.sources)?;
+        writeln!(f, "git_info      {}", self.git_info)?;
+        writeln!(f, "registries    {}", self.registries)?;
+        writeln!(f, "oci_blobs     {}", self.oci_blobs)?;
+        write!(f, "blobs         {}", self.blobs)
+    }
+}
+
+impl Gc {
+    pub fn new(state: AppState, grace: chrono::Duration, batch_size: u64) -> Self {
+        Self {
+            state,
+            grace,
+            batch_size: batch_size.max(1),
+        }
+    }
+
+    /// Deletes all orphans - or only counts them, if `dry_run` is set
+    ///
+    /// Liveness is always derived from the index, so a dry run reports exactly what a real run deletes.
+    pub async fn run(&self, dry_run: bool) -> Result<GcReport, Error> {
+        let cutoff = Utc::now() - self.grace;
+        let live_meta = live_packages(package::Column::MetaId, cutoff);
+        let live_sources = live_packages(package::Column::SourceId, cutoff);
+        let live_authors = Query::select()
+            .column(package_author::Column::AuthorId)
+            .from(package_author::Entity)
+            .and_where(package_author::Column::MetaId.in_subquery(live_meta.clone()))
+            .to_owned();
+
+        // Packages go first - the rows below are only orphaned relative to the live packages
+        let packages = self
+            .sweep::<package::Entity>(
+                package::Column::Id,
+                live_package_condition(cutoff).not(),
+                dry_run,
+            )
+            .await?;
+        let sources = self
+            .sweep::<source::Entity>(
+                source::Column::Id,
+                Condition::all().add(source::Column::Id.not_in_subquery(live_sources.clone())),
+                dry_run,
+            )
+            .await?;
+        let meta = self
+            .sweep::<meta::Entity>(
+                meta::Column::Id,
+                Condition::all().add(meta::Column::Id.not_in_subquery(live_meta)),
+                dry_run,
+            )
+            .await?;
+        let authors = self
+            .sweep::<author::Entity>(
+                author::Column::Id,
+                Condition::all().add(author::Column::Id.not_in_subquery(live_authors)),
+                dry_run,
+            )
+            .await?;
+        let capabilities = self
+            .sweep::<capabilities::Entity>(
+                capabilities::Column::Id,
+                Condition::all().add(
+                    capabilities::Column::Id
+                        .not_in_subquery(live_packages(package::Column::CapabilitiesId, cutoff)),
+                ),
+                dry_run,
+            )
+            .await?;
+        let git_info = self
+            .sweep::<git_info::Entity>(
+                git_info::Column::Id,
+                Condition::all().add(git_info::Column::Id.not_in_subquery(referenced_by_sources(
+                    source::Column::GitInfoId,
+                    live_sources.clone(),
+                ))),
+                dry_run,
+            )
+            .await?;
+        let registries = self
+            .sweep::<registry::Entity>(
+                registry::Column::Id,
+                Condition::all().add(registry::Column::Id.not_in_subquery(referenced_by_sources(
+                    source::Column::RegistryId,
+                    live_sources.clone(),
+                ))),
+                dry_run,
+            )
+            .await?;
+        let oci_blobs = self.sweep_oci_blobs(cutoff, dry_run).await?;
+        let blobs = self.sweep_blobs(live_sources, dry_run).await?;
+        Ok(GcReport {
+            packages,
+            meta,
+            authors,
+            capabilities,
+            sources,
+            git_info,
+            registries,
+            oci_blobs,
+            blobs,
+        })
+    }
+
+    /// Runs the collection in the given interval - the first run happens one interval after the start
+    pub async fn run_periodically(self, period: Duration) {
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            match self.run(false).await {
+                Ok(report) if report.total() > 0 => {
+                    info!("Garbage collection finished: {report:?}")
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Garbage collection failed: {e}"),
+            }
+        }
+    }
+
+    /// Deletes the rows of an entity, that match the orphan condition, in batches
+    async fn sweep<E>(&self, id: E::Column, orphans: Condition, dry_run: bool) -> Result<u64, Error>
+    where
+        E: EntityTrait,
+        E::Model: Sync,
+    {
+        let db = &self.state.db;
+        if dry_run {
+            return Ok(E::find().filter(orphans).count(db).await?);
+        }
+        let mut deleted = 0;
+        loop {
+            let _guard = self.state.index_lock.lock().await;
+            let ids: Vec<i64> = E::find()
+                .select_only()
+                .column(id)
+                .filter(orphans.clone())
+                .limit(self.batch_size)
+                .into_tuple()
+                .all(db)
+                .await?;
+            if ids.is_empty() {
+                return Ok(deleted);
+            }
+            let result = E::delete_many().filter(id.is_in(ids)).exec(db).await?;
+            deleted += result.rows_affected;
+        }
+    }
+
+    /// Deletes uploaded OCI blobs, that are older than the grace period and that no referrer references
+    ///
+    /// Blobs of packages are copied into the manifest and the blob store on push, so only referrers
+    /// keep uploaded blobs alive. Referrers are pushed under the index lock, so the referenced digests
+    /// are collected again for every batch.
+    async fn sweep_oci_blobs(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<u64, Error> {
+        let db = &self.state.db;
+        let orphans = |referenced: HashSet<String>| {
+            oci_blob::Entity::find()
+                .filter(oci_blob::Column::CreatedAt.lt(cutoff))
+                .filter(oci_blob::Column::Digest.is_not_in(referenced))
+        };
+        if dry_run {
+            return Ok(orphans(self.referenced_oci_blobs().await?)
+                .count(db)
+                .await?);
+        }
+        let mut deleted = 0;
+        loop {
+            let _guard = self.state.index_lock.lock().await;
+            let batch: Vec<(i64, String)> = orphans(self.referenced_oci_blobs().await?)
+                .select_only()
+                .column(oci_blob::Column::Id)
+                .column(oci_blob::Column::Digest)
+                .limit(self.batch_size)
+                .into_tuple()
+                .all(db)
+                .await?;
+            if batch.is_empty() {
+                return Ok(deleted);
+            }
+            // The content goes first - if deleting it fails, the row is still there for the next run
+            let mut ids = Vec::with_capacity(batch.len());
+            for (id, digest) in batch {
+                self.state.blobs.delete(&oci::blob_key(&digest)?).await?;
+                ids.push(id);
+            }
+            let result = oci_blob::Entity::delete_many()
+                .filter(oci_blob::Column::Id.is_in(ids))
+                .exec(db)
+                .await?;
+            deleted += result.rows_affected;
+        }
+    }
+
+    /// Digests of all blobs, that a referrer references
+    async fn referenced_oci_blobs(&self) -> Result<HashSet<String>, Error> {
+        let mut referenced = HashSet::new();
+        for referrer in oci_referrer::Entity::find().all(&self.state.db).await? {
+            let manifest = ImageManifest::parse(&referrer.content, Some(&referrer.media_type))?;
+            referenced.insert(manifest.config.digest);
+            referenced.extend(manifest.layers.into_iter().map(|layer| layer.digest));
+        }
+        Ok(referenced)
+    }
+
+    /// Deletes blobs from the blob store, whose digest no live source and no uploaded OCI blob has
+    async fn sweep_blobs(
+        &self,
+        live_sources: SelectStatement,
+        dry_run: bool,
+    ) -> Result<u64, Error> {
+        let db = &self.state.db;
+        let stored = self.state.blobs.list().await?;
+        let mut orphaned = 0;
+        for batch in stored.chunks(self.batch_size as usize) {
+            let _guard = self.state.index_lock.lock().await;
+            let digests: Vec<String> = batch.iter().map(|d| String::from(*d)).collect();
+            let uploads: Vec<String> = digests.iter().map(|d| format!("sha256:{d}")).collect();
+            let mut live: HashSet<String> = source::Entity::find()
+                .select_only()
+                .column(source::Column::Digest)
+                .filter(source::Column::Digest.is_in(digests))
+                .filter(source::Column::Id.in_subquery(live_sources.clone()))
+                .into_tuple()
+                .all(db)
+                .await?
+                .into_iter()
+                .collect();
+            // Uploaded OCI blobs are stored under their sha256 digest (see `oci::blob_key`)
+            let uploaded: Vec<String> = oci_blob::Entity::find()
+                .select_only()
+                .column(oci_blob::Column::Digest)
+                .filter(oci_blob::Column::Digest.is_in(uploads))
+                .into_tuple()
+                .all(db)
+                .await?;
+            live.extend(
+                uploaded
+                    .iter()
+                    .filter_map(|d| d.strip_prefix("sha256:"))
+                    .map(str::to_string),
+            );
+            for digest in batch {
+                if live.contains(&String::from(*digest)) {
+                    continue;
+                }
+                if !dry_run {
+                    self.state.blobs.delete(digest).await?;
+                }
+                orphaned += 1;
+            }
+        }
+        Ok(orphaned)
+    }
+}
+
+/// Condition, that matches packages, which are in the index or younger than the cutoff
+fn live_package_condition(cutoff: DateTime<Utc>) -> Condition {
+    Condition::any()
+        .add(
+            package::Column::Id.in_subquery(
+                Query::select()
+                    .column(index::Column::PkgId)
+                    .from(index::Entity)
+                    .to_owned(),
+            ),
+        )
+        .add(package::Column::CreatedAt.gte(cutoff.fixed_offset()))
+}
+
+/// Selects a (non-null) column of all live packages
+fn live_packages(column: package::Column, cutoff: DateTime<Utc>) -> SelectStatement {
+    Query::select()
+        .column(column)
+        .from(package::Entity)
+        .cond_where(live_package_condition(cutoff))
+        .and_where(column.is_not_null())
+        .to_owned()
+}
+
+/// Selects a (non-null) column of the sources, whose ids are selected by `sources`
+fn referenced_by_sources(column: source::Column, sources: SelectStatement) -> SelectStatement {
+    Query::select()
+        .column(column)
+        .from(source::Entity)
+        .and_where(source::Column::Id.in_subquery(sources))
+        .and_where(column.is_not_null())
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{oci, test_util::*};
+    use axum::http::StatusCode;
+    use borderless_hash::Hash256;
+    use borderless_pkg::{git_info::GitInfo, Author, Capabilities};
+    use sea_orm::sea_query::Expr;
+
+    /// Removes the index entry of a published version, which orphans everything below it
+    async fn unpublish(state: &AppState, repository: &str) {
+        index::Entity::delete_many()
+            .filter(index::Column::Repository.eq(repository))
+            .exec(&state.db)
+            .await
+            .unwrap();
+    }
+
+    /// Stores an uploaded OCI blob, whose grace period is over
+    async fn store_stale_upload(state: &AppState, blob: &[u8]) {
+        let digest = oci::digest(blob);
+        oci_blob::Entity::store(&state.db, state.blobs.as_ref(), &digest, blob.to_vec())
+            .await
+            .unwrap();
+        oci_blob::Entity::update_many()
+            .col_expr(
+                oci_blob::Column::CreatedAt,
+                Expr::value(Utc::now() - chrono::Duration::days(2)),
+            )
+            .filter(oci_blob::Column::Digest.eq(digest))
+            .exec(&state.db)
+            .await
+            .unwrap();
+    }
+
+    /// Checks, that an uploaded OCI blob was kept (or collected) - together with its content
+    async fn assert_upload(state: &AppState, blob: &[u8], kept: bool) {
+        let digest = oci::digest(blob);
+        let found = oci_blob::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap();
+        assert_eq!(found.is_some(), kept);
+        let key = oci::blob_key(&digest).unwrap();
+        assert_eq!(state.blobs.contains(&key).await.unwrap(), kept);
+    }
+
+    #[tokio::test]
+    async fn collects_orphans() {
+        let (app, state) = test_app_with_state().await;
+        let kept = b"\0asm-kept".to_vec();
+        let gone = b"\0asm-gone".to_vec();
+        publish(&app, "borderless/kept:1.0.0", &test_pkg("kept", &kept)).await;
+        let mut pkg = test_pkg("gone", &gone);
+        pkg.capabilities = Some(Capabilities {
+            network: true,
+            websocket: false,
+            url_whitelist: vec!["https://borderless.dev".to_string()],
+        });
+        pkg.meta.authors = vec![Author::new("Jane Doe", Some("jane@borderless.dev"))];
+        if let borderless_pkg::SourceType::Wasm { git_info, .. } = &mut pkg.source.code {
+            *git_info = Some(GitInfo {
+                tag: Some("v1.0.0".to_string()),
+                commits_past_tag: None,
+                commit_hash_short: "abc1234".to_string(),
+                dirty: false,
+            });
+        }
+        assert_eq!(
+            publish(&app, "borderless/gone:1.0.0", &pkg).await,
+            StatusCode::CREATED
+        );
+        let remote = b"\0asm-remote".to_vec();
+        let pkg = upstream_pkg("remote", &remote, "https://registry.borderless.dev");
+        publish(&app, "borderless/remote:1.0.0", &pkg).await;
+        unpublish(&state, "gone").await;
+        unpublish(&state, "remote").await;
+
+        let gc = Gc::new(state.clone(), chrono::Duration::zero(), 1);
+        let expected = GcReport {
+            packages: 2,
+            meta: 2,
+            authors: 1,
+            capabilities: 1,
+            sources: 2,
+            git_info: 1,
+            registries: 1,
+            oci_blobs: 0,
+            blobs: 1,
+        };
+        assert_eq!(gc.run(true).await.unwrap(), expected);
+        // A dry run leaves everything in place
+        assert_eq!(gc.run(true).await.unwrap(), expected);
+        assert!(state.blobs.contains(&Hash256::digest(&gone)).await.unwrap());
+
+        assert_eq!(gc.run(false).await.unwrap(), expected);
+        assert_eq!(gc.run(true).await.unwrap(), GcReport::default());
+        assert!(!state.blobs.contains(&Hash256::digest(&gone)).await.unwrap());
+        assert!(
+            source::Entity::find_by_digest(&state.db, &Hash256::digest(&gone))
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Live versions are untouched
+        let uri = format!("/api/v0/blobs/{}", String::from(Hash256::digest(&kept)));
+        assert_eq!(get(&app, &uri).await.status(), StatusCode::OK);
+        assert_eq!(package::Entity::find().count(&state.db).await.unwrap(), 1);
+        assert_eq!(meta::Entity::find().count(&state.db).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn grace_period_keeps_recent_orphans() {
+        let (app, state) = test_app_with_state().await;
+        let wasm = b"\0asm-counter".to_vec();
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", &wasm),
+        )
+        .await;
+        unpublish(&state, "counter").await;
+        let upload = oci::digest(b"upload");
+        oci_blob::Entity::store(&state.db, state.blobs.as_ref(), &upload, b"upload".to_vec())
+            .await
+            .unwrap();
+
+        let gc = Gc::new(state.clone(), chrono::Duration::hours(1), 100);
+        assert_eq!(gc.run(false).await.unwrap(), GcReport::default());
+        assert!(state.blobs.contains(&Hash256::digest(&wasm)).await.unwrap());
+        assert_upload(&state, b"upload", true).await;
+    }
+
+    #[tokio::test]
+    async fn reuploads_restart_the_grace_period() {
+        let (_, state) = test_app_with_state().await;
+        for blob in [
+            b"reuploaded".to_vec(),
+            b"mounted".to_vec(),
+            b"stale".to_vec(),
+        ] {
+            store_stale_upload(&state, &blob).await;
+        }
+        oci_blob::Entity::store(
+            &state.db,
+            state.blobs.as_ref(),
+            &oci::digest(b"reuploaded"),
+            b"reuploaded".to_vec(),
+        )
+        .await
+        .unwrap();
+        oci_blob::Entity::touch(&state.db, &oci::digest(b"mounted"))
+            .await
+            .unwrap();
+
+        let gc = Gc::new(state.clone(), chrono::Duration::hours(24), 100);
+        assert_eq!(gc.run(false).await.unwrap().oci_blobs, 1);
+        for (blob, kept) in [
+            (&b"reuploaded"[..], true),
+            (&b"mounted"[..], true),
+            (&b"stale"[..], false),
+        ] {
+            assert_upload(&state, blob, kept).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn referrers_keep_their_blobs() {
+        let (_, state) = test_app_with_state().await;
+        let config = b"{}".to_vec();
+        let sbom = b"sbom".to_vec();
+        let stale = b"stale".to_vec();
+        for blob in [&config, &sbom, &stale] {
+            store_stale_upload(&state, blob).await;
+        }
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": oci::MANIFEST_MEDIA_TYPE,
+            "artifactType": "application/spdx+json",
+            "config": {
+                "mediaType": "application/vnd.oci.empty.v1+json",
+                "digest": oci::digest(&config),
+                "size": config.len(),
+            },
+            "layers": [{
+                "mediaType": "application/spdx+json",
+                "digest": oci::digest(&sbom),
+                "size": sbom.len(),
+            }],
+            "subject": {
+                "mediaType": oci::MANIFEST_MEDIA_TYPE,
+                "digest": oci::digest(b"subject"),
+                "size": 7,
+            },
+        });
+        let content = serde_json::to_vec(&manifest).unwrap();
+        let parsed = ImageManifest::parse(&content, None).unwrap();
+        oci_referrer::Entity::store(&state.db, "borderless", "counter", &parsed, content, "test")
+            .await
+            .unwrap();
+
+        let gc = Gc::new(state.clone(), chrono::Duration::hours(24), 100);
+        let report = gc.run(false).await.unwrap();
+        assert_eq!(report.oci_blobs, 1);
+        for (blob, kept) in [(&config, true), (&sbom, true), (&stale, false)] {
+            assert_upload(&state, blob, kept).await;
+        }
+    }
+}
@@ -1,39 +1,215 @@
+mod api;
+mod auth;
+mod blobs;
 mod db;
 mod error;
 mod extractor;
+mod gc;
 mod migrator;
+mod mirror;
 mod models;
+mod oci;
+mod signing;
+#[cfg(test)]
+mod test_util;
+mod transparency;
+mod upstream;
 
 use crate::error::Error;
 use anyhow::Result;
+use api::deprecate::deprecation_headers;
+use auth::{Principal, Role, Scope};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    routing::put,
+    body::Body,
+    extract::{DefaultBodyLimit, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{AppendHeaders, IntoResponse, Response},
+    routing::{delete, get, post, put},
     Json, Router,
 };
+use base64::prelude::*;
+use blobs::{BlobStore, Encoding};
 use borderless_pkg::WasmPkg;
-use clap::Parser;
-use db::entities::{index::ActiveIndex, package::ActivePackage};
-use extractor::OciId;
+use clap::{Parser, Subcommand};
+use db::entities::{
+    index::{self, ActiveIndex, PackageSummary, SearchQuery},
+    log_entry::{self, LogKind},
+    namespace_role::{self, Grantee},
+    oci_blob, oci_manifest,
+    package::ActivePackage,
+    signature, source, tag_pointer,
+    token::{self, NewToken},
+};
+use ed25519_dalek::SigningKey;
+use extractor::{Digest, OciId};
+use models::OciIdentifier;
+use oci::OciArtifact;
 use sea_orm::{
     entity::prelude::*,
     ActiveValue::{NotSet, Set},
 };
-use sea_orm::{DatabaseConnection, TransactionTrait};
-use tracing::{info, instrument};
+use sea_orm::{DatabaseConnection, SqlErr, TransactionTrait};
+use signing::SIGNATURE_HEADER;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+use tracing::{info, instrument, warn};
+use upstream::Upstream;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// Path to the database directory (global)
+    /// Path to the SQLite database file (created if missing, use `:memory:` for a transient database)
     #[arg(short, long)]
     db: String,
+
+    /// Maximum number of pooled database connections
+    #[arg(long, default_value_t = 8)]
+    max_connections: u32,
+
+    /// Minimum number of idle database connections
+    #[arg(long, default_value_t = 1)]
+    min_connections: u32,
+
+    /// File with the ed25519 key, that signs the tree heads of the transparency log (created if missing)
+    ///
+    /// Without a key file, an ephemeral key is generated on every start.
+    #[arg(long)]
+    log_key: Option<PathBuf>,
+
+    /// Hours of inactivity, after which unfinished blob uploads are discarded
+    #[arg(long, default_value_t = 24)]
+    upload_ttl_hours: i64,
+
+    /// Largest body in bytes of a single OCI upload request (a chunk or a monolithic upload)
+    #[arg(long, default_value_t = api::oci::DEFAULT_MAX_UPLOAD_SIZE)]
+    max_upload_size: usize,
+
+    /// Directory of the blob store, that holds the wasm of all packages (created if missing)
+    #[arg(long, default_value = "blobs")]
+    blob_dir: PathBuf,
+
+    /// Storage backend of the blob store
+    #[arg(long, value_enum, default_value_t = blobs::Backend::Kv)]
+    blob_backend: blobs::Backend,
+
+    /// Seconds to wait for other registries, when pulling packages that reference them
+    #[arg(long, default_value_t = 30)]
+    upstream_timeout_secs: u64,
+
+    /// Hosts (optionally with a port), that packages may be pulled from over https
+    ///
+    /// Without any host, packages that reference another registry cannot be downloaded.
+    #[arg(long, value_delimiter = ',')]
+    upstream_hosts: Vec<String>,
+
+    /// Largest blob in bytes, that is accepted from other registries
+    #[arg(long, default_value_t = upstream::DEFAULT_MAX_BLOB_SIZE)]
+    upstream_max_blob_size: usize,
+
+    /// Hours between two garbage collections of orphaned rows and blobs (0 disables them)
+    #[arg(long, default_value_t = 24)]
+    gc_interval_hours: u64,
+
+    /// Hours, that orphaned packages and uploaded blobs are kept, before they are collected
+    #[arg(long, default_value_t = 24)]
+    gc_grace_hours: i64,
+
+    /// Number of rows or blobs, that the garbage collection deletes at once
+    #[arg(long, default_value_t = 500)]
+    gc_batch_size: u64,
+
+    /// Runs a maintenance command instead of the server
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Creates an API token and prints its secret
+    CreateToken {
+        /// Name of the token
+        #[arg(long)]
+        name: String,
+        /// User or service, the token is issued for
+        #[arg(long)]
+        subject: String,
+        /// Granted scopes (publish, yank, admin)
+        #[arg(long, value_delimiter = ',', required = true)]
+        scopes: Vec<Scope>,
+        /// Restricts the token to these namespaces (and their subtrees)
+        #[arg(long, value_delimiter = ',')]
+        namespaces: Option<Vec<String>>,
+        /// Lifetime of the token in days - tokens without lifetime never expire
+        #[arg(long)]
+        expires_in_days: Option<i64>,
+    },
+    /// Copies namespaces from another borderless registry into this one
+    Mirror {
+        /// Base url of the registry to mirror
+        #[arg(long)]
+        from: String,
+        /// Namespaces to mirror, including the namespaces below them
+        #[arg(long, value_delimiter = ',', required = true)]
+        namespaces: Vec<String>,
+        /// Keeps running and repeats the mirror run in this interval
+        #[arg(long)]
+        interval_secs: Option<u64>,
+    },
+    /// Deletes rows and blobs, that no registry index entry references any more
+    ///
+    /// Must not run while a server uses the same database and blob store, because it cannot
+    /// coordinate with the publishes of another process. A running server collects garbage itself.
+    Gc {
+        /// Only reports what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub db: DatabaseConnection,
+    /// Serializes writes to the registry index
+    pub index_lock: Arc<Mutex<()>>,
+    /// Signs the tree heads of the transparency log
+    pub log_key: Arc<SigningKey>,
+    /// Fetches packages, whose code lives in another registry (refuses every pull by default)
+    pub upstream: Upstream,
+    /// Content-addressed storage of the wasm blobs
+    pub blobs: Arc<dyn BlobStore>,
+    /// Largest body of a single request to the OCI api
+    pub max_upload_size: usize,
+}
+
+impl AppState {
+    pub fn new(db: DatabaseConnection, blobs: Arc<dyn BlobStore>) -> Self {
+        Self {
+            db,
+            blobs,
+            index_lock: Arc::new(Mutex::new(())),
+            log_key: Arc::new(transparency::generate_key()),
+            upstream: Upstream::default(),
+            max_upload_size: api::oci::DEFAULT_MAX_UPLOAD_SIZE,
+        }
+    }
+
+    /// Uses the given key to sign tree heads
+    pub fn with_log_key(mut self, key: SigningKey) -> Self {
+        self.log_key = Arc::new(key);
+        self
+    }
+
+    /// Uses the given client to fetch packages from other registries
+    pub fn with_upstream(mut self, upstream: Upstream) -> Self {
+        self.upstream = upstream;
+        self
+    }
+
+    /// Accepts OCI upload requests up to the given size
+    pub fn with_max_upload_size(mut self, size: usize) -> Self {
+        self.max_upload_size = size;
+        self
+    }
 }
 
 #[tokio::main]
@@ -45,13 +221,97 @@ async fn main() -> Result<()> {
     info!("Start Registry Server!");
 
     let args = Cli::parse();
-    let db = db::setup_database(&args.db).await?;
+    let db = db::setup_database(&db::DbConfig {
+        path: args.db,
+        max_connections: args.max_connections,
+        min_connections: args.min_connections,
+    })
+    .await?;
+    let blobs = blobs::open(args.blob_backend, &args.blob_dir)?;
+    let moved = source::Entity::move_legacy_blobs(&db, blobs.as_ref()).await?;
+    if moved > 0 {
+        info!("Moved {moved} wasm blob(s) from the database into the blob store");
+    }
+    let moved = oci_blob::Entity::move_legacy_blobs(&db, blobs.as_ref()).await?;
+    if moved > 0 {
+        info!("Moved {moved} uploaded OCI blob(s) from the database into the blob store");
+    }
 
-    let app = AppState { db };
+    let timeout = Duration::from_secs(args.upstream_timeout_secs);
+    let upstream = Upstream::new(timeout, args.upstream_hosts, args.upstream_max_blob_size);
+    let gc_grace = chrono::Duration::hours(args.gc_grace_hours);
+    match args.command {
+        Some(Command::CreateToken {
+            name,
+            subject,
+            scopes,
+            namespaces,
+            expires_in_days,
+        }) => {
+            let secret = auth::generate_secret();
+            let new_token = NewToken {
+                name,
+                subject,
+                scopes,
+                namespaces,
+                expires_at: expires_in_days
+                    .map(|days| chrono::Utc::now() + chrono::Duration::days(days)),
+            };
+            let token = token::Entity::create(&db, new_token, &secret).await?;
+            info!("Created token {} for {}", token.id, token.subject);
+            println!("{secret}");
+            return Ok(());
+        }
+        Some(Command::Mirror {
+            from,
+            namespaces,
+            interval_secs,
+        }) => {
+            let state = AppState::new(db, blobs).with_upstream(upstream);
+            let mirror = mirror::Mirror::new(state, &from, timeout);
+            match interval_secs {
+                Some(secs) => {
+                    mirror
+                        .run_periodically(namespaces, Duration::from_secs(secs))
+                        .await
+                }
+                None => {
+                    let report = mirror.run(&namespaces).await?;
+                    info!("Mirrored {from}: {report:?}");
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Gc { dry_run }) => {
+            let state = AppState::new(db, blobs).with_upstream(upstream);
+            let gc = gc::Gc::new(state, gc_grace, args.gc_batch_size);
+            let report = gc.run(dry_run).await?;
+            if dry_run {
+                println!("Would delete:\n{report}");
+            } else {
+                println!("Deleted:\n{report}");
+            }
+            return Ok(());
+        }
+        None => {}
+    }
 
-    let app = Router::new()
-        .route("/api/v0/publish/{*oci}", put(publish))
-        .with_state(app);
+    let mut state = AppState::new(db, blobs)
+        .with_upstream(upstream)
+        .with_max_upload_size(args.max_upload_size);
+    match args.log_key {
+        Some(path) => state = state.with_log_key(transparency::load_or_create_key(&path)?),
+        None => warn!("No log key configured - tree heads are signed with an ephemeral key"),
+    }
+    tokio::spawn(api::oci::expire_uploads(
+        state.db.clone(),
+        chrono::Duration::hours(args.upload_ttl_hours),
+    ));
+    if args.gc_interval_hours > 0 {
+        let gc = gc::Gc::new(state.clone(), gc_grace, args.gc_batch_size);
+        tokio::spawn(gc.run_periodically(Duration::from_secs(args.gc_interval_hours * 3600)));
+    }
+    let app = router(state);
 
     info!("Start API Service");
     let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
@@ -59,51 +319,1057 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Creates the API router
+pub fn router(state: AppState) -> Router {
+    // Chunks and monolithic uploads are larger than the default limit of axum
+    let upload_limit = DefaultBodyLimit::max(state.max_upload_size);
+    Router::new()
+        .route("/api/v0/publish/{*oci}", put(publish))
+        .route("/api/v0/search", get(search))
+        .route("/api/v0/blobs/{digest}", get(download))
+        .route("/api/v0/packages/{*oci}", get(package_info))
+        .route(
+            "/api/v0/yank/{*oci}",
+            put(api::yank::yank).delete(api::yank::unyank),
+        )
+        .route("/api/v0/resolve/{*path}", get(api::resolve::resolve))
+        .route("/api/v0/tags/{*path}", get(api::tags::list_tags))
+        .route("/api/v0/namespaces", get(api::catalog::list_namespaces))
+        .route(
+            "/api/v0/namespaces/{*path}",
+            get(api::catalog::list_namespace_repositories),
+        )
+        .route("/api/v0/catalog", get(api::catalog::catalog))
+        .route(
+            "/api/v0/tokens",
+            get(api::tokens::list_tokens).post(api::tokens::create_token),
+        )
+        .route("/api/v0/tokens/{id}", delete(api::tokens::revoke_token))
+        .route(
+            "/api/v0/roles/{*namespace}",
+            get(api::access::list_roles)
+                .put(api::access::grant_role)
+                .delete(api::access::revoke_role),
+        )
+        .route(
+            "/api/v0/transfer/{*namespace}",
+            post(api::access::transfer_ownership),
+        )
+        .route(
+            "/api/v0/keys/{*namespace}",
+            get(api::signing::list_keys)
+                .put(api::signing::register_key)
+                .delete(api::signing::revoke_key),
+        )
+        .route(
+            "/api/v0/rotate/{*namespace}",
+            post(api::signing::rotate_key),
+        )
+        .route(
+            "/api/v0/policy/{*namespace}",
+            get(api::signing::get_policy).put(api::signing::set_policy),
+        )
+        .route("/api/v0/signatures/{digest}", get(api::signing::signatures))
+        .route("/api/v0/log/head", get(api::log::tree_head))
+        .route("/api/v0/log/entries", get(api::log::entries))
+        .route(
+            "/api/v0/log/proof/inclusion",
+            get(api::log::inclusion_proof),
+        )
+        .route(
+            "/api/v0/log/proof/consistency",
+            get(api::log::consistency_proof),
+        )
+        .route(
+            "/api/v0/teams/{team}",
+            get(api::access::team_members).put(api::access::create_team),
+        )
+        .route(
+            "/api/v0/teams/{team}/members/{subject}",
+            put(api::access::add_member).delete(api::access::remove_member),
+        )
+        .route(
+            "/api/v0/deprecate/{*oci}",
+            put(api::deprecate::deprecate).delete(api::deprecate::undeprecate),
+        )
+        .route("/v2", get(api::oci::version))
+        .route("/v2/", get(api::oci::version))
+        .route("/v2/_catalog", get(api::oci::catalog))
+        .route(
+            "/v2/{*path}",
+            get(api::oci::get)
+                .put(api::oci::put)
+                .post(api::oci::post)
+                .patch(api::oci::patch)
+                .delete(api::oci::delete)
+                .layer(upload_limit),
+        )
+        .with_state(state)
+}
+
 // PUT publish wasm package in registry
-#[instrument]
+#[instrument(skip(principal))]
 pub async fn publish(
     State(state): State<AppState>,
+    principal: Principal,
     OciId(oid): OciId,
+    headers: HeaderMap,
     Json(pkg): Json<WasmPkg>,
 ) -> Result<StatusCode, Error> {
     info!("Trigger route!");
     info!("Hey oci {:?}", oid);
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .map(|v| v.to_str().map_err(|_| Error::InvalidSignature))
+        .transpose()?;
+    let artifact = OciArtifact::from_package(&pkg);
+    publish_package(&state, &principal, &oid, signature, pkg, artifact).await
+}
+
+/// Publishes a package under the given identifier - shared by the registry api and the OCI api
+///
+/// The OCI manifest of the package is stored next to its index entry.
+pub async fn publish_package(
+    state: &AppState,
+    principal: &Principal,
+    oid: &OciIdentifier,
+    signature: Option<&str>,
+    pkg: WasmPkg,
+    artifact: Option<OciArtifact>,
+) -> Result<StatusCode, Error> {
+    principal.authorize(Scope::Publish, &oid.namespace)?;
+    let digest = String::from(pkg.source.digest);
+
+    // Floating tags are moved by the registry and cannot be published directly
+    if let Some(keyword) = oid.tag.floating_keyword() {
+        return Err(Error::FloatingTag(keyword.to_string()));
+    }
+
+    // Publishes are serialized, so concurrent publishes of the same tag resolve deterministically
+    let _guard = state.index_lock.lock().await;
+
+    // The first publish into an unclaimed namespace claims it, later publishes require a role
+    let claimed = namespace_role::Entity::is_claimed(&state.db, &oid.namespace).await?;
+    if claimed {
+        principal
+            .authorize_in(&state.db, Scope::Publish, Role::Publisher, &oid.namespace)
+            .await?;
+    } else if namespace_role::Entity::is_owned_below(&state.db, &oid.namespace, &principal.subject)
+        .await?
+    {
+        // Roles are inherited, so the claim would take over the namespaces below
+        return Err(Error::ClaimConflict(oid.namespace.clone()));
+    }
+
+    // Published versions are immutable - this is checked before the signature,
+    // so a conflicting publish is reported as such, whatever its signature is
+    if let Some(existing) = index::Entity::find_digest(&state.db, oid).await? {
+        return republish_status(oid, existing, digest);
+    }
+
+    let verified = signing::verify_publish(&state.db, &oid.namespace, &pkg, signature).await?;
+
     let txn = state.db.begin().await?;
     // add pkg to database
-    let pkg_model = ActivePackage::from_model(&txn, pkg).await?;
+    let pkg_model = ActivePackage::from_model(&txn, state.blobs.as_ref(), pkg).await?;
+    if let Some(verified) = verified {
+        signature::Entity::record(
+            &txn,
+            pkg_model.source_id,
+            verified.key_id,
+            &verified.signature,
+            &verified.payload,
+        )
+        .await?;
+    }
 
     // and registry index
-    let index_oci = oid.clone();
     let idx_entry = ActiveIndex {
         id: NotSet,
         pkg_id: Set(pkg_model.id),
-        registry: Set(oid.registry.unwrap().to_string()),
-        namespace: Set(oid.namespace),
-        repository: Set(oid.repository),
+        registry: Set(oid.registry_string()),
+        namespace: Set(oid.namespace.clone()),
+        repository: Set(oid.repository.clone()),
         tag: Set(oid.tag.to_string()),
         yank: Set(false),
         deprecated: Set(false),
-        created_at: Set(DateTimeUtc::default()),
+        created_at: Set(chrono::Utc::now()),
+        published_by: Set(Some(principal.subject.clone())),
+        ..Default::default()
     };
 
-    ActiveIndex::insert(idx_entry, &txn).await?;
+    let entry = match ActiveIndex::insert(idx_entry, &txn).await {
+        Ok(entry) => entry,
+        // Another registry process may have published the same identifier in the meantime
+        Err(e) => {
+            if let Some(SqlErr::UniqueConstraintViolation(_)) = e.sql_err() {
+                txn.rollback().await?;
+                let existing = index::Entity::find_digest(&state.db, oid)
+                    .await?
+                    .ok_or(Error::Database(e))?;
+                return republish_status(oid, existing, digest);
+            }
+            return Err(e.into());
+        }
+    };
+    if let Some(artifact) = artifact {
+        oci_manifest::Entity::store(&txn, entry.id, artifact).await?;
+    }
+    if !claimed {
+        let owner = Grantee::User(principal.subject.clone());
+        namespace_role::Entity::grant(
+            &txn,
+            &oid.namespace,
+            &owner,
+            Role::Owner,
+            &principal.subject,
+        )
+        .await?;
+        info!(
+            "Namespace {} claimed by {}",
+            oid.namespace, principal.subject
+        );
+    }
+    tag_pointer::Entity::refresh(&txn, &oid.namespace, &oid.repository).await?;
+    log_entry::Entity::append(
+        &txn,
+        LogKind::Publish,
+        &oid.to_string(),
+        &digest,
+        &principal.subject,
+    )
+    .await?;
     txn.commit().await?;
 
-    info!("Added Package with oci identifier: {:?}", index_oci);
+    info!("Added Package with oci identifier: {:?}", oid);
     Ok(StatusCode::CREATED)
 }
 
+/// Status of a publish for an identifier, that already exists in the registry
+///
+/// Re-publishing the exact same package is a no-op, while changing the content of a version is a conflict.
+fn republish_status(
+    oid: &OciIdentifier,
+    existing: String,
+    digest: String,
+) -> Result<StatusCode, Error> {
+    if existing == digest {
+        info!("Package {} is already published", oid);
+        Ok(StatusCode::OK)
+    } else {
+        Err(Error::Conflict {
+            oci: oid.to_string(),
+            existing,
+            attempted: digest,
+        })
+    }
+}
+
 // GET search a package
 #[instrument]
-pub async fn search(State(state): State<AppState>, Path(path): Path<String>) -> Result<(), Error> {
-    todo!()
+pub async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<PackageSummary>>, Error> {
+    let results = index::Entity::search(&state.db, &query).await?;
+    Ok(Json(results))
+}
+
+// GET resolve a package by its oci identifier
+#[instrument]
+pub async fn package_info(
+    State(state): State<AppState>,
+    OciId(oid): OciId,
+) -> Result<Response, Error> {
+    let oid = tag_pointer::Entity::resolve(&state.db, oid).await?;
+    let entry = index::Entity::find_by_oci(&oid)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| Error::NotPublished(oid.to_string()))?;
+
+    // Yanked versions can only be fetched by their digest
+    if entry.yank {
+        return Err(Error::Yanked {
+            oci: oid.to_string(),
+            reason: entry.yank_reason.unwrap_or_default(),
+        });
+    }
+
+    let summary = index::Entity::find_summary(&state.db, &oid)
+        .await?
+        .ok_or_else(|| Error::NotPublished(oid.to_string()))?;
+    let headers = deprecation_headers(
+        summary.deprecated_at,
+        summary.deprecation_successor.as_deref(),
+    );
+    Ok((AppendHeaders(headers), Json(summary)).into_response())
 }
 
 // GET download a pkg by hash
 #[instrument]
 pub async fn download(
     State(state): State<AppState>,
-    Path(path): Path<String>,
-) -> Result<(), Error> {
-    todo!()
+    Digest(digest): Digest,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let source = source::Entity::find_by_digest(&state.db, &digest)
+        .await?
+        .ok_or(Error::NoPkg(digest))?;
+
+    // Packages that reference another registry are pulled on their first download
+    let (stored, encoding, size) = match state.blobs.stream(&digest).await {
+        Ok(body) => (
+            body,
+            Encoding::from_column(source.encoding.as_deref())?,
+            source.size,
+        ),
+        Err(Error::NoPkg(_)) if source.registry_id.is_some() => {
+            let wasm = state
+                .upstream
+                .pull(&state.db, state.blobs.as_ref(), &source, &digest)
+                .await?;
+            let size = wasm.len() as i64;
+            (Body::from(wasm), Encoding::Identity, Some(size))
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Clients, that understand zstd, get compressed blobs exactly as they are stored
+    let passthrough = encoding == Encoding::Zstd && accepts_zstd(&headers);
+    let etag = if passthrough {
+        format!("\"{}+zstd\"", source.digest)
+    } else {
+        format!("\"{}\"", source.digest)
+    };
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|t| t.trim() == etag || t.trim() == "*"));
+    if not_modified {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::VARY, header::ACCEPT_ENCODING.to_string()),
+            ],
+        )
+            .into_response());
+    }
+
+    let content_digest = format!("sha3-256=:{}:", BASE64_STANDARD.encode(digest));
+    let headers = [
+        (header::CONTENT_TYPE, "application/wasm".to_string()),
+        (header::ETAG, etag),
+        (HeaderName::from_static("content-digest"), content_digest),
+        (header::VARY, header::ACCEPT_ENCODING.to_string()),
+    ];
+
+    // The blob is deprecated, once every version that references it is deprecated
+    let entries = index::Entity::find_by_digest(&state.db, &source.digest).await?;
+    let deprecation = match entries.iter().max_by_key(|e| e.deprecated_at) {
+        Some(latest) if entries.iter().all(|e| e.deprecated) => deprecation_headers(
+            latest.deprecated_at,
+            latest.deprecation_successor.as_deref(),
+        ),
+        _ => Vec::new(),
+    };
+    let signatures = signature::Entity::find_by_digest(&state.db, &source.digest)
+        .await?
+        .into_iter()
+        .filter(|s| s.trusted)
+        .map(|s| {
+            (
+                HeaderName::from_static(SIGNATURE_HEADER),
+                format!("{}:{}", s.fingerprint, s.signature),
+            )
+        })
+        .collect::<Vec<_>>();
+    // Streamed bodies have no length of their own - the size is only known for the uncompressed wasm
+    let (body, representation) = if passthrough {
+        let coding = (header::CONTENT_ENCODING, encoding.to_string());
+        (stored, Some(coding))
+    } else {
+        let length = size.map(|size| (header::CONTENT_LENGTH, size.to_string()));
+        (blobs::decompress_body(encoding, stored), length)
+    };
+    match size {
+        Some(size) => info!(
+            "Serve wasm blob {} ({size} bytes, {encoding})",
+            source.digest
+        ),
+        None => info!("Serve wasm blob {} ({encoding})", source.digest),
+    }
+    Ok((
+        headers,
+        AppendHeaders(representation),
+        AppendHeaders(deprecation),
+        AppendHeaders(signatures),
+        body,
+    )
+        .into_response())
+}
+
+/// Checks, if the client accepts zstd as content coding (and did not opt out with `q=0`)
+fn accepts_zstd(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|coding| {
+            let mut params = coding.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default();
+            let q = params
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            name.eq_ignore_ascii_case("zstd") && q > 0.0
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::entities::{source, tag_pointer_history};
+    use crate::test_util::*;
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Method, Request, StatusCode},
+        Router,
+    };
+    use borderless_hash::Hash256;
+    use sea_orm::{ActiveModelTrait, EntityTrait, QueryOrder, Set};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn search_by_namespace_and_repository_prefix() {
+        let app = test_app().await;
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        assert_eq!(
+            publish(&app, "borderless/counter:1.0.0", &pkg).await,
+            StatusCode::CREATED
+        );
+        let pkg = test_pkg("flipper", b"\0asm-flipper");
+        assert_eq!(
+            publish(&app, "borderless/flipper:0.2.0", &pkg).await,
+            StatusCode::CREATED
+        );
+        let pkg = test_pkg("counter", b"\0asm-other-counter");
+        assert_eq!(
+            publish(&app, "other/counter:1.0.0", &pkg).await,
+            StatusCode::CREATED
+        );
+
+        let (status, body) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/search?namespace=borderless&repository=cou",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let hits = body.as_array().unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0]["repository"], "counter");
+        assert_eq!(hits[0]["tag"], "1.0.0");
+        assert_eq!(hits[0]["pkg_type"], "contract");
+        assert_eq!(hits[0]["description"], "The counter contract");
+
+        let (_, body) =
+            send_json(&app, Method::GET, "/api/v0/search?repository=counter", None).await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+
+        let (_, body) = send_json(&app, Method::GET, "/api/v0/search?tag=0.2.0", None).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["repository"], "flipper");
+    }
+
+    #[tokio::test]
+    async fn search_repository_prefix_escapes_wildcards() {
+        let app = test_app().await;
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        publish(&app, "borderless/counter:1.0.0", &pkg).await;
+
+        let (status, body) =
+            send_json(&app, Method::GET, "/api/v0/search?repository=%25", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn republish_is_idempotent() {
+        let app = test_app().await;
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        assert_eq!(
+            publish(&app, "borderless/counter:1.0.0", &pkg).await,
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            publish(&app, "borderless/counter:1.0.0", &pkg).await,
+            StatusCode::OK
+        );
+
+        let (_, body) = send_json(&app, Method::GET, "/api/v0/search", None).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn published_versions_are_immutable() {
+        let app = test_app().await;
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        publish(&app, "borderless/counter:1.0.0", &pkg).await;
+
+        let changed = test_pkg("counter", b"\0asm-counter-v2");
+        let uri = "/api/v0/publish/borderless/counter:1.0.0";
+        let body = serde_json::to_value(&changed).unwrap();
+        let (status, body) = send_json(&app, Method::PUT, uri, Some(body)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let details = &body["error"]["details"];
+        assert_eq!(details["oci"], "borderless/counter:1.0.0");
+        assert_eq!(
+            details["existing_digest"],
+            String::from(pkg.source.digest).as_str()
+        );
+        assert_eq!(
+            details["attempted_digest"],
+            String::from(changed.source.digest).as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn repositories_share_versions_and_code() {
+        let app = test_app().await;
+        let mut pkg = test_pkg("counter", b"\0asm-counter");
+        pkg.meta.authors = vec![borderless_pkg::Author {
+            name: "Jane Doe".to_string(),
+            email: Some("jane@example.com".to_string()),
+        }];
+        assert_eq!(
+            publish(&app, "borderless/counter:1.0.0", &pkg).await,
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            publish(&app, "borderless/counter-fork:1.0.0", &pkg).await,
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            publish(&app, "borderless/counter:1.0.1", &pkg).await,
+            StatusCode::CREATED
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_publishes_resolve_deterministically() {
+        let app = test_app().await;
+        let a = test_pkg("counter", b"\0asm-counter-a");
+        let b = test_pkg("counter", b"\0asm-counter-b");
+        let (first, second) = tokio::join!(
+            publish(&app, "borderless/counter:1.0.0", &a),
+            publish(&app, "borderless/counter:1.0.0", &b),
+        );
+        let mut statuses = [first, second];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::CREATED, StatusCode::CONFLICT]);
+    }
+
+    #[tokio::test]
+    async fn floating_tags_follow_the_newest_eligible_version() {
+        let (app, state) = test_app_with_state().await;
+        for version in ["1.0.0", "1.1.0", "0.9.0", "2.0.0-rc.1"] {
+            let pkg = test_pkg("counter", format!("\0asm-{version}").as_bytes());
+            let status = publish(&app, &format!("borderless/counter:{version}"), &pkg).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+
+        let (_, latest) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/packages/borderless/counter:latest",
+            None,
+        )
+        .await;
+        assert_eq!(latest["tag"], "1.1.0");
+        let (_, nightly) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/packages/borderless/counter:nightly",
+            None,
+        )
+        .await;
+        assert_eq!(nightly["tag"], "2.0.0-rc.1");
+
+        // Yanking the target moves the pointer back
+        let body = serde_json::json!({ "reason": "broken" });
+        send_json(
+            &app,
+            Method::PUT,
+            "/api/v0/yank/borderless/counter:1.1.0",
+            Some(body),
+        )
+        .await;
+        let (_, latest) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/packages/borderless/counter:latest",
+            None,
+        )
+        .await;
+        assert_eq!(latest["tag"], "1.0.0");
+
+        let history: Vec<_> = tag_pointer_history::Entity::find()
+            .order_by_asc(tag_pointer_history::Column::Id)
+            .all(&state.db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|h| (h.keyword, h.tag.unwrap()))
+            .collect();
+        let expected = [
+            ("latest", "1.0.0"),
+            ("latest", "1.1.0"),
+            ("nightly", "2.0.0-rc.1"),
+            ("latest", "1.0.0"),
+        ];
+        assert_eq!(
+            history,
+            expected.map(|(k, t)| (k.to_string(), t.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn floating_tags_cannot_be_published() {
+        let app = test_app().await;
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        assert_eq!(
+            publish(&app, "borderless/counter:latest", &pkg).await,
+            StatusCode::BAD_REQUEST
+        );
+        let (status, _) = send_json(
+            &app,
+            Method::GET,
+            "/api/v0/packages/borderless/counter:latest",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn publish_rejects_digest_mismatch() {
+        let app = test_app().await;
+        let mut pkg = test_pkg("counter", b"\0asm-counter");
+        pkg.source.digest = Hash256::digest(b"something else");
+
+        let uri = "/api/v0/publish/borderless/counter:1.0.0";
+        let body = serde_json::to_value(&pkg).unwrap();
+        let (status, body) = send_json(&app, Method::PUT, uri, Some(body)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["status"], 422);
+
+        // Nothing must have been stored
+        let (_, body) = send_json(&app, Method::GET, "/api/v0/search", None).await;
+        assert!(body.as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn download_by_digest() {
+        let app = test_app().await;
+        let wasm = b"\0asm-counter".to_vec();
+        let pkg = test_pkg("counter", &wasm);
+        publish(&app, "borderless/counter:1.0.0", &pkg).await;
+
+        let digest = String::from(Hash256::digest(&wasm));
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v0/blobs/{digest}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers().clone();
+        assert_eq!(headers[header::CONTENT_TYPE], "application/wasm");
+        assert_eq!(headers[header::ETAG], format!("\"{digest}\""));
+        assert!(headers["content-digest"]
+            .to_str()
+            .unwrap()
+            .starts_with("sha3-256=:"));
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+
+        // Conditional requests are answered without a body
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/v0/blobs/{digest}"))
+                    .header(header::IF_NONE_MATCH, format!("\"{digest}\""))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn download_streams_from_fs_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let blobs = crate::blobs::open(crate::blobs::Backend::Fs, dir.path()).unwrap();
+        let (app, _) = test_app_with_blobs(blobs).await;
+        let wasm = b"\0asm-counter".to_vec();
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", &wasm),
+        )
+        .await;
+
+        let hex = String::from(Hash256::digest(&wasm));
+        assert!(dir
+            .path()
+            .join(&hex[0..2])
+            .join(&hex[2..4])
+            .join(&hex)
+            .is_file());
+        let response = get(&app, &format!("/api/v0/blobs/{hex}")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_LENGTH],
+            wasm.len().to_string()
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+    }
+
+    #[tokio::test]
+    async fn download_negotiates_zstd() {
+        let (app, state) = test_app_with_state().await;
+        let wasm = b"\0asm-counter".repeat(64);
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", &wasm),
+        )
+        .await;
+
+        // The digest keeps referring to the uncompressed wasm
+        let digest = Hash256::digest(&wasm);
+        let source = source::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.encoding.as_deref(), Some("zstd"));
+        assert_eq!(source.size, Some(wasm.len() as i64));
+        let stored = state.blobs.get(&digest).await.unwrap();
+        assert!(stored.len() < wasm.len());
+
+        let uri = format!("/api/v0/blobs/{}", String::from(digest));
+        let response = get(&app, &uri).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers().clone();
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(headers[header::CONTENT_LENGTH], wasm.len().to_string());
+        assert_eq!(headers[header::VARY], "accept-encoding");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+
+        let request = |accept: &str| {
+            Request::get(&uri)
+                .header(header::ACCEPT_ENCODING, accept)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(request("gzip, zstd")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers().clone();
+        assert_eq!(headers[header::CONTENT_ENCODING], "zstd");
+        assert_ne!(headers[header::ETAG], format!("\"{}\"", source.digest));
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), stored);
+
+        // Clients can opt out of zstd
+        let response = app.clone().oneshot(request("zstd;q=0")).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+    }
+
+    #[tokio::test]
+    async fn download_unknown_or_invalid_digest() {
+        let app = test_app().await;
+        let unknown = String::from(Hash256::digest(b"unknown"));
+        let (status, body) =
+            send_json(&app, Method::GET, &format!("/api/v0/blobs/{unknown}"), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["status"], 404);
+
+        let (status, _) = send_json(&app, Method::GET, "/api/v0/blobs/not-a-digest", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn download_pulls_through_upstream() {
+        let wasm = b"\0asm-counter".to_vec();
+        let upstream = test_app().await;
+        publish(
+            &upstream,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", &wasm),
+        )
+        .await;
+        let (url, server) = serve(upstream).await;
+
+        let (app, state) = test_app_with_state().await;
+        let pkg = upstream_pkg("counter", &wasm, &url);
+        assert_eq!(
+            publish(&app, "mirror/counter:1.0.0", &pkg).await,
+            StatusCode::CREATED
+        );
+        let digest = Hash256::digest(&wasm);
+        let source = source::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.size, None);
+        assert!(!state.blobs.contains(&digest).await.unwrap());
+
+        let uri = format!("/api/v0/blobs/{}", String::from(digest));
+        let response = get(&app, &uri).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+
+        assert!(state.blobs.contains(&digest).await.unwrap());
+        let source = source::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.size, Some(wasm.len() as i64));
+
+        // Later downloads are served from the cache, even without the upstream
+        server.abort();
+        let _ = server.await;
+        let response = get(&app, &uri).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+    }
+
+    #[tokio::test]
+    async fn download_rejects_tampered_upstream() {
+        // Stand-in registry, that serves different bytes than requested
+        let upstream = Router::new().route(
+            "/api/v0/blobs/{digest}",
+            axum::routing::get(|| async { b"\0asm-tampered".to_vec() }),
+        );
+        let (url, _server) = serve(upstream).await;
+
+        let (app, state) = test_app_with_state().await;
+        let wasm = b"\0asm-counter".to_vec();
+        publish(
+            &app,
+            "mirror/counter:1.0.0",
+            &upstream_pkg("counter", &wasm, &url),
+        )
+        .await;
+
+        let digest = Hash256::digest(&wasm);
+        let uri = format!("/api/v0/blobs/{}", String::from(digest));
+        let (status, body) = send_json(&app, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(body["error"]["status"], 502);
+        assert!(!state.blobs.contains(&digest).await.unwrap());
+
+        // Unreachable upstreams are reported the same way
+        let wasm = b"\0asm-other".to_vec();
+        let pkg = upstream_pkg("other", &wasm, "http://127.0.0.1:1");
+        publish(&app, "mirror/other:1.0.0", &pkg).await;
+        let uri = format!("/api/v0/blobs/{}", String::from(Hash256::digest(&wasm)));
+        let (status, _) = send_json(&app, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn download_limits_upstreams() {
+        // Stand-in registry, that streams an endless blob and counts its requests
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let upstream = Router::new().route(
+            "/api/v0/blobs/{digest}",
+            axum::routing::get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let chunks =
+                    std::iter::repeat_with(|| Ok::<_, std::io::Error>(vec![0u8; 64 * 1024]));
+                Body::from_stream(futures_util::stream::iter(chunks))
+            }),
+        );
+        let (url, _server) = serve(upstream).await;
+
+        let (app, _) = test_app_with_state().await;
+        let wasm = b"\0asm-counter".to_vec();
+        publish(
+            &app,
+            "mirror/counter:1.0.0",
+            &upstream_pkg("counter", &wasm, &url),
+        )
+        .await;
+        let uri = format!("/api/v0/blobs/{}", String::from(Hash256::digest(&wasm)));
+        let (status, body) = send_json(&app, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("exceeds"));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Hosts, that the operator did not allow, are never contacted
+        let wasm = b"\0asm-other".to_vec();
+        let foreign = url.replace("127.0.0.1", "localhost");
+        publish(
+            &app,
+            "mirror/other:1.0.0",
+            &upstream_pkg("other", &wasm, &foreign),
+        )
+        .await;
+        let uri = format!("/api/v0/blobs/{}", String::from(Hash256::digest(&wasm)));
+        let (status, body) = send_json(&app, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("not an allowed upstream"));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn wasm_publish_fills_registry_source() {
+        let (app, state) = test_app_with_state().await;
+        // Compressible, so the blob is stored with zstd
+        let wasm = b"\0asm-counter".repeat(64);
+        let pkg = upstream_pkg("counter", &wasm, "http://127.0.0.1:1");
+        assert_eq!(
+            publish(&app, "mirror/counter:1.0.0", &pkg).await,
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            publish(
+                &app,
+                "borderless/counter:1.0.0",
+                &test_pkg("counter", &wasm)
+            )
+            .await,
+            StatusCode::CREATED
+        );
+
+        let digest = Hash256::digest(&wasm);
+        let source = source::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.size, Some(wasm.len() as i64));
+        assert_eq!(source.encoding.as_deref(), Some("zstd"));
+
+        let response = get(&app, &format!("/api/v0/blobs/{}", String::from(digest))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+    }
+
+    #[tokio::test]
+    async fn reused_sources_keep_their_encoding() {
+        let (app, state) = test_app_with_state().await;
+        let wasm = b"\0asm-counter".repeat(64);
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", &wasm),
+        )
+        .await;
+        let digest = Hash256::digest(&wasm);
+        let source = source::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Blobs, that were stored before compression existed, have no encoding
+        state.blobs.delete(&digest).await.unwrap();
+        state.blobs.put(&digest, wasm.clone()).await.unwrap();
+        source::ActiveSource {
+            id: Set(source.id),
+            encoding: Set(None),
+            ..Default::default()
+        }
+        .update(&state.db)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            publish(&app, "borderless/copy:1.0.0", &test_pkg("copy", &wasm)).await,
+            StatusCode::CREATED
+        );
+        let source = source::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.encoding, None);
+        let response = get(&app, &format!("/api/v0/blobs/{}", String::from(digest))).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+    }
+
+    #[tokio::test]
+    async fn legacy_blobs_are_moved_into_the_store() {
+        let (app, state) = test_app_with_state().await;
+        let wasm = b"\0asm-counter".to_vec();
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", &wasm),
+        )
+        .await;
+        let digest = Hash256::digest(&wasm);
+        let source = source::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(source.wasm_blob.is_none());
+        assert_eq!(source.size, Some(wasm.len() as i64));
+
+        // Rows written before the blob store existed carry the wasm themselves
+        source::ActiveSource {
+            id: Set(source.id),
+            wasm_blob: Set(Some(wasm.clone())),
+            size: Set(None),
+            ..Default::default()
+        }
+        .update(&state.db)
+        .await
+        .unwrap();
+        state.blobs.delete(&digest).await.unwrap();
+
+        let moved = source::Entity::move_legacy_blobs(&state.db, state.blobs.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(moved, 1);
+        let source = source::Entity::find_by_digest(&state.db, &digest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(source.wasm_blob.is_none());
+        assert_eq!(source.size, Some(wasm.len() as i64));
+        let response = get(&app, &format!("/api/v0/blobs/{}", String::from(digest))).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.to_vec(), wasm);
+
+        // Moving is a one-shot operation
+        let moved = source::Entity::move_legacy_blobs(&state.db, state.blobs.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(moved, 0);
+    }
 }
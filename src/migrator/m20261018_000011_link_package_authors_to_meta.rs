@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+
+use super::{
+    m20250605_000001_create_authors_table::Authors,
+    m20250605_000008_create_package_authors_tables::PackageAuthors,
+    m20250605_000009_ceate_meta_table::Meta,
+};
+
+/// Links the authors of a package to its meta data instead of the package itself
+///
+/// Authors are part of [`borderless_pkg::PkgMeta`], so they are written together with the meta row.
+/// SQLite cannot change primary or foreign keys in place, so the table is rebuilt and existing
+/// links are carried over through `packages.meta_id`.
+#[derive(DeriveMigrationName)]
+pub struct LinkPackageAuthorsToMeta;
+
+#[async_trait::async_trait]
+impl MigrationTrait for LinkPackageAuthorsToMeta {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let rebuilt = Alias::new("package_authors_rebuilt");
+        manager
+            .create_table(
+                Table::create()
+                    .table(rebuilt.clone())
+                    .col(ColumnDef::new(MetaAuthors::MetaId).integer().not_null())
+                    .col(
+                        ColumnDef::new(PackageAuthors::AuthorId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .name("pk_package_authors")
+                            .col(MetaAuthors::MetaId)
+                            .col(PackageAuthors::AuthorId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_package_authors_meta")
+                            .from(rebuilt.clone(), MetaAuthors::MetaId)
+                            .to(Meta::Table, Meta::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_package_authors_author")
+                            .from(rebuilt.clone(), PackageAuthors::AuthorId)
+                            .to(Authors::Table, Authors::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT OR IGNORE INTO package_authors_rebuilt (meta_id, author_id) \
+                 SELECT packages.meta_id, package_authors.author_id FROM package_authors \
+                 JOIN packages ON packages.id = package_authors.package_id",
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(PackageAuthors::Table).to_owned())
+            .await?;
+        manager
+            .rename_table(
+                Table::rename()
+                    .table(rebuilt, PackageAuthors::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Authors can be shared by several packages through their meta data, which the old table cannot express
+        Err(DbErr::Migration(
+            "package authors cannot be linked back to packages".to_string(),
+        ))
+    }
+}
+
+#[derive(Iden)]
+pub enum MetaAuthors {
+    MetaId,
+}
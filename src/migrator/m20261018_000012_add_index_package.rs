@@ -0,0 +1,29 @@
+use sea_orm_migration::prelude::*;
+
+/// Links every registry index entry to the package, that it publishes
+///
+/// SQLite can only add a foreign key together with a new column, and only if the column may be `NULL`.
+/// Index entries could not be written without a package before, so no existing entry lacks one.
+#[derive(DeriveMigrationName)]
+pub struct AddIndexPackage;
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddIndexPackage {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "ALTER TABLE registry_index ADD COLUMN pkg_id integer \
+                 REFERENCES packages (id) ON DELETE CASCADE",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite refuses to drop columns, that are part of a foreign key
+        Err(DbErr::Migration(
+            "registry_index.pkg_id cannot be dropped".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000010_create_index_table::RegistryIndex;
+
+/// Adds the repository to the unique identity of a package,
+/// so that multiple repositories in the same namespace can share a version.
+#[derive(DeriveMigrationName)]
+pub struct UniqueIndexWithRepository;
+
+#[async_trait::async_trait]
+impl MigrationTrait for UniqueIndexWithRepository {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_unique_package_identity")
+                    .table(RegistryIndex::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_package_identity")
+                    .table(RegistryIndex::Table)
+                    .col(RegistryIndex::Registry)
+                    .col(RegistryIndex::Namespace)
+                    .col(RegistryIndex::Repository)
+                    .col(RegistryIndex::Tag)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_unique_package_identity")
+                    .table(RegistryIndex::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_package_identity")
+                    .table(RegistryIndex::Table)
+                    .col(RegistryIndex::Registry)
+                    .col(RegistryIndex::Namespace)
+                    .col(RegistryIndex::Tag)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000010_create_index_table::RegistryIndex;
+
+/// Records why, when and by whom a package version was yanked
+#[derive(DeriveMigrationName)]
+pub struct AddYankDetails;
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddYankDetails {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // NOTE: SQLite only supports one column per ALTER TABLE statement
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RegistryIndex::Table)
+                    .add_column(ColumnDef::new(YankDetails::YankReason).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RegistryIndex::Table)
+                    .add_column(ColumnDef::new(YankDetails::YankedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RegistryIndex::Table)
+                    .add_column(ColumnDef::new(YankDetails::YankedBy).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            YankDetails::YankReason,
+            YankDetails::YankedAt,
+            YankDetails::YankedBy,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RegistryIndex::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum YankDetails {
+    YankReason,
+    YankedAt,
+    YankedBy,
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000010_create_index_table::RegistryIndex;
+
+/// Adds a human readable message and an optional successor to deprecated versions
+#[derive(DeriveMigrationName)]
+pub struct AddDeprecationDetails;
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddDeprecationDetails {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // NOTE: SQLite only supports one column per ALTER TABLE statement
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RegistryIndex::Table)
+                    .add_column(
+                        ColumnDef::new(DeprecationDetails::DeprecationMessage)
+                            .text()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RegistryIndex::Table)
+                    .add_column(
+                        ColumnDef::new(DeprecationDetails::DeprecationSuccessor)
+                            .string()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RegistryIndex::Table)
+                    .add_column(
+                        ColumnDef::new(DeprecationDetails::DeprecatedAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            DeprecationDetails::DeprecationMessage,
+            DeprecationDetails::DeprecationSuccessor,
+            DeprecationDetails::DeprecatedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RegistryIndex::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum DeprecationDetails {
+    DeprecationMessage,
+    DeprecationSuccessor,
+    DeprecatedAt,
+}
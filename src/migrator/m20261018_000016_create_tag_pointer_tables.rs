@@ -0,0 +1,139 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000010_create_index_table::RegistryIndex;
+
+/// Floating keyword tags (`latest`, `nightly`) and the history of their targets
+#[derive(DeriveMigrationName)]
+pub struct CreateTagPointerTables;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateTagPointerTables {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TagPointers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TagPointers::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TagPointers::Namespace).string().not_null())
+                    .col(ColumnDef::new(TagPointers::Repository).string().not_null())
+                    .col(ColumnDef::new(TagPointers::Keyword).string().not_null())
+                    .col(ColumnDef::new(TagPointers::IndexId).integer().not_null())
+                    .col(
+                        ColumnDef::new(TagPointers::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tag_pointers_index")
+                            .from(TagPointers::Table, TagPointers::IndexId)
+                            .to(RegistryIndex::Table, RegistryIndex::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_tag_pointer")
+                    .table(TagPointers::Table)
+                    .col(TagPointers::Namespace)
+                    .col(TagPointers::Repository)
+                    .col(TagPointers::Keyword)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TagPointerHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TagPointerHistory::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TagPointerHistory::Namespace)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TagPointerHistory::Repository)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TagPointerHistory::Keyword)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TagPointerHistory::Tag).string().null())
+                    .col(ColumnDef::new(TagPointerHistory::Digest).string().null())
+                    .col(
+                        ColumnDef::new(TagPointerHistory::MovedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tag_pointer_history")
+                    .table(TagPointerHistory::Table)
+                    .col(TagPointerHistory::Namespace)
+                    .col(TagPointerHistory::Repository)
+                    .col(TagPointerHistory::Keyword)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TagPointerHistory::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TagPointers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum TagPointers {
+    Table,
+    Id,
+    Namespace,
+    Repository,
+    Keyword,
+    IndexId,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+pub enum TagPointerHistory {
+    Table,
+    Id,
+    Namespace,
+    Repository,
+    Keyword,
+    Tag,
+    Digest,
+    MovedAt,
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+/// API tokens - only the hash of the secret is stored
+#[derive(DeriveMigrationName)]
+pub struct CreateTokensTable;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateTokensTable {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tokens::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Tokens::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Tokens::Name).string().not_null())
+                    .col(ColumnDef::new(Tokens::Subject).string().not_null())
+                    .col(
+                        ColumnDef::new(Tokens::SecretHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Tokens::Scopes).string().not_null())
+                    .col(ColumnDef::new(Tokens::Namespaces).text().null())
+                    .col(ColumnDef::new(Tokens::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(Tokens::ExpiresAt).timestamp().null())
+                    .col(ColumnDef::new(Tokens::RevokedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Tokens::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Tokens {
+    Table,
+    Id,
+    Name,
+    Subject,
+    SecretHash,
+    Scopes,
+    Namespaces,
+    CreatedAt,
+    ExpiresAt,
+    RevokedAt,
+}
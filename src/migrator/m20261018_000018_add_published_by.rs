@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000010_create_index_table::RegistryIndex;
+
+/// Records the principal, that published a package version
+#[derive(DeriveMigrationName)]
+pub struct AddPublishedBy;
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddPublishedBy {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RegistryIndex::Table)
+                    .add_column(ColumnDef::new(PublishedBy::PublishedBy).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RegistryIndex::Table)
+                    .drop_column(PublishedBy::PublishedBy)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum PublishedBy {
+    PublishedBy,
+}
@@ -0,0 +1,160 @@
+use sea_orm_migration::prelude::*;
+
+/// Namespace roles of users and teams, and the members of teams
+#[derive(DeriveMigrationName)]
+pub struct CreateNamespaceAccessTables;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateNamespaceAccessTables {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Teams::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Teams::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Teams::Name).string().not_null().unique_key())
+                    .col(ColumnDef::new(Teams::CreatedBy).string().not_null())
+                    .col(ColumnDef::new(Teams::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamMembers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TeamMembers::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TeamMembers::TeamId).integer().not_null())
+                    .col(ColumnDef::new(TeamMembers::Subject).string().not_null())
+                    .col(ColumnDef::new(TeamMembers::AddedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_team_members_team")
+                            .from(TeamMembers::Table, TeamMembers::TeamId)
+                            .to(Teams::Table, Teams::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_team_member")
+                    .table(TeamMembers::Table)
+                    .col(TeamMembers::TeamId)
+                    .col(TeamMembers::Subject)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(NamespaceRoles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(NamespaceRoles::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(NamespaceRoles::Namespace)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NamespaceRoles::GranteeKind)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(NamespaceRoles::Grantee).string().not_null())
+                    .col(ColumnDef::new(NamespaceRoles::Role).string().not_null())
+                    .col(
+                        ColumnDef::new(NamespaceRoles::GrantedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NamespaceRoles::GrantedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_namespace_role")
+                    .table(NamespaceRoles::Table)
+                    .col(NamespaceRoles::Namespace)
+                    .col(NamespaceRoles::GranteeKind)
+                    .col(NamespaceRoles::Grantee)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(NamespaceRoles::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(TeamMembers::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Teams::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Teams {
+    Table,
+    Id,
+    Name,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+pub enum TeamMembers {
+    Table,
+    Id,
+    TeamId,
+    Subject,
+    AddedAt,
+}
+
+#[derive(Iden)]
+pub enum NamespaceRoles {
+    Table,
+    Id,
+    Namespace,
+    GranteeKind,
+    Grantee,
+    Role,
+    GrantedBy,
+    GrantedAt,
+}
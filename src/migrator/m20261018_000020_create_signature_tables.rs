@@ -0,0 +1,181 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000006_create_sources_table::Sources;
+
+/// Signing keys of namespaces, package signatures and per-namespace signature policies
+#[derive(DeriveMigrationName)]
+pub struct CreateSignatureTables;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateSignatureTables {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SigningKeys::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SigningKeys::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SigningKeys::Namespace).string().not_null())
+                    .col(ColumnDef::new(SigningKeys::PublicKey).string().not_null())
+                    .col(ColumnDef::new(SigningKeys::Fingerprint).string().not_null())
+                    .col(
+                        ColumnDef::new(SigningKeys::RegisteredBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SigningKeys::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_signing_key")
+                    .table(SigningKeys::Table)
+                    .col(SigningKeys::Namespace)
+                    .col(SigningKeys::Fingerprint)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Signatures::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Signatures::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Signatures::SourceId).integer().not_null())
+                    .col(ColumnDef::new(Signatures::KeyId).integer().not_null())
+                    .col(ColumnDef::new(Signatures::Signature).string().not_null())
+                    .col(ColumnDef::new(Signatures::Payload).text().not_null())
+                    .col(ColumnDef::new(Signatures::CreatedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_signatures_source")
+                            .from(Signatures::Table, Signatures::SourceId)
+                            .to(Sources::Table, Sources::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_signatures_key")
+                            .from(Signatures::Table, Signatures::KeyId)
+                            .to(SigningKeys::Table, SigningKeys::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_signature")
+                    .table(Signatures::Table)
+                    .col(Signatures::SourceId)
+                    .col(Signatures::KeyId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(NamespacePolicies::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(NamespacePolicies::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(NamespacePolicies::Namespace)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(NamespacePolicies::RequireSignature)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NamespacePolicies::UpdatedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NamespacePolicies::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(NamespacePolicies::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Signatures::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(SigningKeys::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum SigningKeys {
+    Table,
+    Id,
+    Namespace,
+    PublicKey,
+    Fingerprint,
+    RegisteredBy,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+pub enum Signatures {
+    Table,
+    Id,
+    SourceId,
+    KeyId,
+    Signature,
+    Payload,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+pub enum NamespacePolicies {
+    Table,
+    Id,
+    Namespace,
+    RequireSignature,
+    UpdatedBy,
+    UpdatedAt,
+}
@@ -0,0 +1,163 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20261018_000020_create_signature_tables::SigningKeys;
+
+/// Validity windows of signing keys, their revocations and rotations
+#[derive(DeriveMigrationName)]
+pub struct CreateKeyLifecycleTables;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateKeyLifecycleTables {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // NOTE: SQLite only supports one column per ALTER TABLE statement
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SigningKeys::Table)
+                    .add_column(ColumnDef::new(KeyValidity::ValidFrom).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SigningKeys::Table)
+                    .add_column(ColumnDef::new(KeyValidity::ValidUntil).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(KeyRevocations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KeyRevocations::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(KeyRevocations::KeyId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(KeyRevocations::Reason).text().not_null())
+                    .col(
+                        ColumnDef::new(KeyRevocations::CompromisedSince)
+                            .timestamp()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(KeyRevocations::RevokedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(KeyRevocations::RevokedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_key_revocations_key")
+                            .from(KeyRevocations::Table, KeyRevocations::KeyId)
+                            .to(SigningKeys::Table, SigningKeys::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(KeyRotations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KeyRotations::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(KeyRotations::Namespace).string().not_null())
+                    .col(ColumnDef::new(KeyRotations::OldKeyId).integer().not_null())
+                    .col(ColumnDef::new(KeyRotations::NewKeyId).integer().not_null())
+                    .col(ColumnDef::new(KeyRotations::RotatedBy).string().not_null())
+                    .col(
+                        ColumnDef::new(KeyRotations::RotatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_key_rotations_old_key")
+                            .from(KeyRotations::Table, KeyRotations::OldKeyId)
+                            .to(SigningKeys::Table, SigningKeys::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_key_rotations_new_key")
+                            .from(KeyRotations::Table, KeyRotations::NewKeyId)
+                            .to(SigningKeys::Table, SigningKeys::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(KeyRotations::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(KeyRevocations::Table).to_owned())
+            .await?;
+        for column in [KeyValidity::ValidFrom, KeyValidity::ValidUntil] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(SigningKeys::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum KeyValidity {
+    ValidFrom,
+    ValidUntil,
+}
+
+#[derive(Iden)]
+pub enum KeyRevocations {
+    Table,
+    Id,
+    KeyId,
+    Reason,
+    CompromisedSince,
+    RevokedBy,
+    RevokedAt,
+}
+
+#[derive(Iden)]
+pub enum KeyRotations {
+    Table,
+    Id,
+    Namespace,
+    OldKeyId,
+    NewKeyId,
+    RotatedBy,
+    RotatedAt,
+}
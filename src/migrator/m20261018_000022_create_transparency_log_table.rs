@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+/// Append-only transparency log - entries are never updated or deleted
+#[derive(DeriveMigrationName)]
+pub struct CreateTransparencyLogTable;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateTransparencyLogTable {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TransparencyLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TransparencyLog::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TransparencyLog::Kind).string().not_null())
+                    .col(ColumnDef::new(TransparencyLog::Oci).string().not_null())
+                    .col(ColumnDef::new(TransparencyLog::Digest).string().not_null())
+                    .col(ColumnDef::new(TransparencyLog::Actor).string().not_null())
+                    .col(ColumnDef::new(TransparencyLog::Leaf).text().not_null())
+                    .col(
+                        ColumnDef::new(TransparencyLog::LeafHash)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TransparencyLog::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_transparency_log_oci")
+                    .table(TransparencyLog::Table)
+                    .col(TransparencyLog::Oci)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TransparencyLog::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum TransparencyLog {
+    Table,
+    Id,
+    Kind,
+    Oci,
+    Digest,
+    Actor,
+    Leaf,
+    LeafHash,
+    CreatedAt,
+}
@@ -0,0 +1,162 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000010_create_index_table::RegistryIndex;
+
+/// OCI manifests of published packages, blobs that wait for their manifest and pending blob uploads
+#[derive(DeriveMigrationName)]
+pub struct CreateOciTables;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateOciTables {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OciManifests::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OciManifests::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OciManifests::IndexId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(OciManifests::Digest).string().not_null())
+                    .col(ColumnDef::new(OciManifests::MediaType).string().not_null())
+                    .col(ColumnDef::new(OciManifests::Content).binary().not_null())
+                    .col(
+                        ColumnDef::new(OciManifests::ConfigDigest)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OciManifests::Config).binary().not_null())
+                    .col(
+                        ColumnDef::new(OciManifests::LayerDigest)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OciManifests::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_oci_manifests_index")
+                            .from(OciManifests::Table, OciManifests::IndexId)
+                            .to(RegistryIndex::Table, RegistryIndex::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for (name, column) in [
+            ("idx_oci_manifests_digest", OciManifests::Digest),
+            ("idx_oci_manifests_config", OciManifests::ConfigDigest),
+            ("idx_oci_manifests_layer", OciManifests::LayerDigest),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(OciManifests::Table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OciBlobs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OciBlobs::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OciBlobs::Digest)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(OciBlobs::Data).binary().not_null())
+                    .col(ColumnDef::new(OciBlobs::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OciUploads::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OciUploads::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OciUploads::Repository).string().not_null())
+                    .col(ColumnDef::new(OciUploads::Data).binary().not_null())
+                    .col(ColumnDef::new(OciUploads::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OciUploads::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(OciBlobs::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(OciManifests::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum OciManifests {
+    Table,
+    Id,
+    IndexId,
+    Digest,
+    MediaType,
+    Content,
+    ConfigDigest,
+    Config,
+    LayerDigest,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+pub enum OciBlobs {
+    Table,
+    Id,
+    Digest,
+    Data,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+pub enum OciUploads {
+    Table,
+    Id,
+    Repository,
+    Data,
+    CreatedAt,
+}
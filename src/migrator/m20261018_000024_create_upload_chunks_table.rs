@@ -0,0 +1,143 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20261018_000023_create_oci_tables::OciUploads;
+
+/// Stores blob uploads in chunks, so appending a chunk does not rewrite the whole upload
+///
+/// `size` is the number of bytes uploaded so far, while `updated_at` tracks the last activity of an upload,
+/// so abandoned uploads can be expired.
+#[derive(DeriveMigrationName)]
+pub struct CreateUploadChunksTable;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateUploadChunksTable {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OciUploads::Table)
+                    .drop_column(OciUploads::Data)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OciUploads::Table)
+                    .add_column(
+                        ColumnDef::new(UploadProgress::Size)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OciUploads::Table)
+                    .add_column(ColumnDef::new(UploadProgress::UpdatedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OciUploadChunks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OciUploadChunks::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OciUploadChunks::UploadId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OciUploadChunks::Offset)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OciUploadChunks::Data).binary().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_oci_upload_chunks_upload")
+                            .from(OciUploadChunks::Table, OciUploadChunks::UploadId)
+                            .to(OciUploads::Table, OciUploads::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_upload_chunk")
+                    .table(OciUploadChunks::Table)
+                    .col(OciUploadChunks::UploadId)
+                    .col(OciUploadChunks::Offset)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OciUploadChunks::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OciUploads::Table)
+                    .drop_column(UploadProgress::UpdatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OciUploads::Table)
+                    .drop_column(UploadProgress::Size)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OciUploads::Table)
+                    .add_column(
+                        ColumnDef::new(OciUploads::Data)
+                            .binary()
+                            .not_null()
+                            .default(Vec::<u8>::new()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum UploadProgress {
+    Size,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+pub enum OciUploadChunks {
+    Table,
+    Id,
+    UploadId,
+    Offset,
+    Data,
+}
@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+
+/// Artifacts (signatures, SBOMs, attestations, ..), whose manifest refers to a package via its `subject`
+#[derive(DeriveMigrationName)]
+pub struct CreateOciReferrersTable;
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateOciReferrersTable {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OciReferrers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OciReferrers::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OciReferrers::Namespace).string().not_null())
+                    .col(ColumnDef::new(OciReferrers::Repository).string().not_null())
+                    .col(ColumnDef::new(OciReferrers::Digest).string().not_null())
+                    .col(ColumnDef::new(OciReferrers::MediaType).string().not_null())
+                    .col(
+                        ColumnDef::new(OciReferrers::ArtifactType)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OciReferrers::Content).binary().not_null())
+                    .col(
+                        ColumnDef::new(OciReferrers::SubjectDigest)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OciReferrers::Annotations).text().null())
+                    .col(ColumnDef::new(OciReferrers::PushedBy).string().not_null())
+                    .col(
+                        ColumnDef::new(OciReferrers::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unique_oci_referrer")
+                    .table(OciReferrers::Table)
+                    .col(OciReferrers::Namespace)
+                    .col(OciReferrers::Repository)
+                    .col(OciReferrers::Digest)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_oci_referrers_subject")
+                    .table(OciReferrers::Table)
+                    .col(OciReferrers::SubjectDigest)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OciReferrers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum OciReferrers {
+    Table,
+    Id,
+    Namespace,
+    Repository,
+    Digest,
+    MediaType,
+    ArtifactType,
+    Content,
+    SubjectDigest,
+    Annotations,
+    PushedBy,
+    CreatedAt,
+}
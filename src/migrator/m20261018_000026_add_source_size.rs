@@ -0,0 +1,39 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000006_create_sources_table::Sources;
+
+/// Records the size of the wasm of a source, whose content now lives in the blob store
+///
+/// `wasm_blob` is kept for sources written before the blob store existed - they are moved out at startup.
+#[derive(DeriveMigrationName)]
+pub struct AddSourceSize;
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddSourceSize {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sources::Table)
+                    .add_column(ColumnDef::new(SourceSize::Size).big_integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sources::Table)
+                    .drop_column(SourceSize::Size)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum SourceSize {
+    Size,
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20261018_000023_create_oci_tables::OciBlobs;
+
+/// Keeps only the size of uploaded OCI blobs, whose content now lives in the blob store
+///
+/// `data` is kept for blobs uploaded before - they are moved out at startup. SQLite cannot drop
+/// the `NOT NULL` constraint in place, so the table is rebuilt.
+#[derive(DeriveMigrationName)]
+pub struct MoveOciBlobsToStore;
+
+#[async_trait::async_trait]
+impl MigrationTrait for MoveOciBlobsToStore {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let rebuilt = Alias::new("oci_blobs_rebuilt");
+        manager
+            .create_table(
+                Table::create()
+                    .table(rebuilt.clone())
+                    .col(
+                        ColumnDef::new(OciBlobs::Id)
+                            .big_unsigned()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OciBlobs::Digest)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(OciBlobs::Data).binary().null())
+                    .col(ColumnDef::new(OciBlobSize::Size).big_integer().null())
+                    .col(ColumnDef::new(OciBlobs::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT INTO oci_blobs_rebuilt (id, digest, data, created_at) \
+                 SELECT id, digest, data, created_at FROM oci_blobs",
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(OciBlobs::Table).to_owned())
+            .await?;
+        manager
+            .rename_table(Table::rename().table(rebuilt, OciBlobs::Table).to_owned())
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The content of moved blobs is only in the blob store
+        Err(DbErr::Migration(
+            "uploaded blobs cannot be moved back into the database".to_string(),
+        ))
+    }
+}
+
+#[derive(Iden)]
+pub enum OciBlobSize {
+    Size,
+}
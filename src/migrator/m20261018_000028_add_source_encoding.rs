@@ -0,0 +1,39 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250605_000006_create_sources_table::Sources;
+
+/// Records the encoding of the wasm of a source, so blobs can be stored compressed
+///
+/// Blobs, that were stored before, keep a `NULL` encoding and are served as-is.
+#[derive(DeriveMigrationName)]
+pub struct AddSourceEncoding;
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddSourceEncoding {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sources::Table)
+                    .add_column(ColumnDef::new(SourceEncoding::Encoding).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sources::Table)
+                    .drop_column(SourceEncoding::Encoding)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum SourceEncoding {
+    Encoding,
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20261018_000023_create_oci_tables::OciBlobs;
+
+/// Records the encoding of uploaded OCI blobs, so they are stored compressed like the wasm of sources
+#[derive(DeriveMigrationName)]
+pub struct AddOciBlobEncoding;
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddOciBlobEncoding {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OciBlobs::Table)
+                    .add_column(ColumnDef::new(OciBlobEncoding::Encoding).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OciBlobs::Table)
+                    .drop_column(OciBlobEncoding::Encoding)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum OciBlobEncoding {
+    Encoding,
+}
@@ -8,6 +8,25 @@ mod m20250605_000007_create_pkgs_table;
 mod m20250605_000008_create_package_authors_tables;
 mod m20250605_000009_ceate_meta_table;
 mod m20250605_000010_create_index_table;
+mod m20261018_000011_link_package_authors_to_meta;
+mod m20261018_000012_add_index_package;
+mod m20261018_000013_unique_index_with_repository;
+mod m20261018_000014_add_yank_details;
+mod m20261018_000015_add_deprecation_details;
+mod m20261018_000016_create_tag_pointer_tables;
+mod m20261018_000017_create_tokens_table;
+mod m20261018_000018_add_published_by;
+mod m20261018_000019_create_namespace_access_tables;
+mod m20261018_000020_create_signature_tables;
+mod m20261018_000021_create_key_lifecycle_tables;
+mod m20261018_000022_create_transparency_log_table;
+mod m20261018_000023_create_oci_tables;
+mod m20261018_000024_create_upload_chunks_table;
+mod m20261018_000025_create_oci_referrers_table;
+mod m20261018_000026_add_source_size;
+mod m20261018_000027_move_oci_blobs_to_store;
+mod m20261018_000028_add_source_encoding;
+mod m20261018_000029_add_oci_blob_encoding;
 
 use sea_orm_migration::prelude::*;
 
@@ -27,6 +46,25 @@ impl MigratorTrait for Migrator {
             Box::new(m20250605_000008_create_package_authors_tables::CreatePackageAuthorsTable),
             Box::new(m20250605_000009_ceate_meta_table::CreateMetaTable),
             Box::new(m20250605_000010_create_index_table::CreateRegistryIndexTable),
+            Box::new(m20261018_000011_link_package_authors_to_meta::LinkPackageAuthorsToMeta),
+            Box::new(m20261018_000012_add_index_package::AddIndexPackage),
+            Box::new(m20261018_000013_unique_index_with_repository::UniqueIndexWithRepository),
+            Box::new(m20261018_000014_add_yank_details::AddYankDetails),
+            Box::new(m20261018_000015_add_deprecation_details::AddDeprecationDetails),
+            Box::new(m20261018_000016_create_tag_pointer_tables::CreateTagPointerTables),
+            Box::new(m20261018_000017_create_tokens_table::CreateTokensTable),
+            Box::new(m20261018_000018_add_published_by::AddPublishedBy),
+            Box::new(m20261018_000019_create_namespace_access_tables::CreateNamespaceAccessTables),
+            Box::new(m20261018_000020_create_signature_tables::CreateSignatureTables),
+            Box::new(m20261018_000021_create_key_lifecycle_tables::CreateKeyLifecycleTables),
+            Box::new(m20261018_000022_create_transparency_log_table::CreateTransparencyLogTable),
+            Box::new(m20261018_000023_create_oci_tables::CreateOciTables),
+            Box::new(m20261018_000024_create_upload_chunks_table::CreateUploadChunksTable),
+            Box::new(m20261018_000025_create_oci_referrers_table::CreateOciReferrersTable),
+            Box::new(m20261018_000026_add_source_size::AddSourceSize),
+            Box::new(m20261018_000027_move_oci_blobs_to_store::MoveOciBlobsToStore),
+            Box::new(m20261018_000028_add_source_encoding::AddSourceEncoding),
+            Box::new(m20261018_000029_add_oci_blob_encoding::AddOciBlobEncoding),
         ]
     }
 }
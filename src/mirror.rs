@@ -0,0 +1,525 @@
+//! Mirrors namespaces of another borderless registry into the local registry
+//!
+//! Mirroring is incremental: versions, that already exist locally, are not downloaded again -
+//! only their yank and deprecation state is synchronized.
+use std::{str::FromStr, time::Duration};
+
+use borderless_hash::Hash256;
+use sea_orm::TransactionTrait;
+use serde::de::DeserializeOwned;
+use tracing::{info, warn};
+
+use crate::{
+    api::{pagination::Page, tags::TagPage},
+    auth::Principal,
+    db::entities::{
+        index::{self, DeprecationInfo, RepositorySummary, TagInfo, YankInfo},
+        log_entry::{self, LogKind},
+        tag_pointer,
+    },
+    error::Error,
+    models::{OciIdentifier, Tag},
+    oci::{self, ImageManifest, OciArtifact},
+    publish_package, upstream, AppState,
+};
+
+/// Subject, that mirrored versions are published and updated by
+pub const MIRROR_SUBJECT: &str = "mirror";
+
+/// Copies packages from another registry over its http api
+pub struct Mirror {
+    state: AppState,
+    client: reqwest::Client,
+    base_url: String,
+    principal: Principal,
+}
+
+/// Number of versions per outcome of a mirror run
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MirrorReport {
+    /// Versions, that were copied into the local registry
+    pub published: usize,
+    /// Versions, whose yank or deprecation state was synchronized
+    pub updated: usize,
+    /// Versions, that were already up to date
+    pub unchanged: usize,
+    /// Versions without wasm code, which cannot be copied
+    pub skipped: usize,
+    /// Versions, that could not be mirrored
+    pub failed: usize,
+}
+
+enum Outcome {
+    Published,
+    Updated,
+    Unchanged,
+    Skipped,
+}
+
+impl Mirror {
+    pub fn new(state: AppState, url: &str, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("failed to build http client");
+        Self {
+            state,
+            client,
+            base_url: upstream::base_url(url),
+            principal: Principal::system(MIRROR_SUBJECT),
+        }
+    }
+
+    /// Mirrors all repositories of the given namespaces and their subtrees
+    ///
+    /// Versions, that fail to mirror, are reported but do not abort the run.
+    pub async fn run(&self, namespaces: &[String]) -> Result<MirrorReport, Error> {
+        let mut report = MirrorReport::default();
+        for namespace in namespaces {
+            for repo in self.repositories(namespace).await? {
+                for tag in self.tags(&repo.namespace, &repo.repository).await? {
+                    let oid = OciIdentifier::new(
+                        repo.namespace.clone(),
+                        repo.repository.clone(),
+                        Tag::parse(&tag.tag),
+                    );
+                    match self.mirror_version(&oid, &tag).await {
+                        Ok(Outcome::Published) => report.published += 1,
+                        Ok(Outcome::Updated) => report.updated += 1,
+                        Ok(Outcome::Unchanged) => report.unchanged += 1,
+                        Ok(Outcome::Skipped) => report.skipped += 1,
+                        Err(e) => {
+                            warn!("Failed to mirror {oid}: {e}");
+                            report.failed += 1;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Repeats the mirror run in the given interval
+    pub async fn run_periodically(self, namespaces: Vec<String>, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match self.run(&namespaces).await {
+                Ok(report) => info!("Mirror run finished: {report:?}"),
+                Err(e) => warn!("Mirror run failed: {e}"),
+            }
+        }
+    }
+
+    async fn mirror_version(&self, oid: &OciIdentifier, tag: &TagInfo) -> Result<Outcome, Error> {
+        let published = match index::Entity::find_digest(&self.state.db, oid).await? {
+            // Published versions are immutable - on both sides
+            Some(existing) if existing != tag.digest => {
+                return Err(Error::Conflict {
+                    oci: oid.to_string(),
+                    existing,
+                    attempted: tag.digest.clone(),
+                })
+            }
+            Some(_) => false,
+            None => {
+                let Some(manifest_digest) = &tag.manifest_digest else {
+                    warn!("Skip {oid}, it has no wasm code to mirror");
+                    return Ok(Outcome::Skipped);
+                };
+                self.copy_package(oid, tag, manifest_digest).await?;
+                true
+            }
+        };
+        let updated = self.sync_state(oid, tag).await?;
+        Ok(match (published, updated) {
+            (true, _) => Outcome::Published,
+            (false, true) => Outcome::Updated,
+            (false, false) => Outcome::Unchanged,
+        })
+    }
+
+    /// Downloads manifest, config and wasm of a version and publishes them locally
+    async fn copy_package(
+        &self,
+        oid: &OciIdentifier,
+        tag: &TagInfo,
+        manifest_digest: &str,
+    ) -> Result<(), Error> {
+        let name = oid.full_repository_path();
+        let (content, content_type) = self
+            .get_verified(&format!("/v2/{name}/manifests/{manifest_digest}"))
+            .await?;
+        let manifest = ImageManifest::parse(&content, content_type.as_deref())?;
+        manifest.check_package()?;
+        let (config, _) = self
+            .get_verified(&format!("/v2/{name}/blobs/{}", manifest.config.digest))
+            .await?;
+        let (wasm, _) = self
+            .get_verified(&format!("/v2/{name}/blobs/{}", manifest.layer().digest))
+            .await?;
+
+        // The layer digest is chosen by the upstream, so the wasm is checked against the listed version as well
+        let computed = String::from(Hash256::digest(&wasm));
+        if computed != tag.digest {
+            return Err(Error::DigestMismatch {
+                declared: tag.digest.clone(),
+                computed,
+            });
+        }
+        let pkg = oci::package_from_config(&config, &wasm)?;
+        let artifact = OciArtifact::new(manifest, content, config);
+        publish_package(&self.state, &self.principal, oid, None, pkg, Some(artifact)).await?;
+        info!("Mirrored {oid}");
+        Ok(())
+    }
+
+    /// Applies the yank and deprecation state of the upstream version
+    ///
+    /// Returns `true`, if the local version was changed.
+    async fn sync_state(&self, oid: &OciIdentifier, tag: &TagInfo) -> Result<bool, Error> {
+        let entry = index::Entity::find_by_oci(oid)
+            .one(&self.state.db)
+            .await?
+            .ok_or_else(|| Error::NotPublished(oid.to_string()))?;
+        let yank = entry.yank != tag.yank || entry.yank_reason != tag.yank_reason;
+        let deprecation = entry.deprecated != tag.deprecated
+            || entry.deprecation_message != tag.deprecation_message
+            || entry.deprecation_successor != tag.deprecation_successor;
+        if !yank && !deprecation {
+            return Ok(false);
+        }
+
+        let subject = &self.principal.subject;
+        let _guard = self.state.index_lock.lock().await;
+        let txn = self.state.db.begin().await?;
+        if yank {
+            let (info, kind) = match &tag.yank_reason {
+                Some(reason) if tag.yank => {
+                    let info = YankInfo {
+                        reason: reason.clone(),
+                        actor: Some(subject.clone()),
+                    };
+                    (Some(info), LogKind::Yank)
+                }
+                _ => (None, LogKind::Unyank),
+            };
+            let entry = index::Entity::set_yank(&txn, oid, info)
+                .await?
+                .ok_or_else(|| Error::NotPublished(oid.to_string()))?;
+            tag_pointer::Entity::refresh(&txn, &oid.namespace, &oid.repository).await?;
+            log_entry::Entity::append_index(&txn, kind, &entry, subject).await?;
+        }
+        if deprecation {
+            let (info, kind) = match &tag.deprecation_message {
+                Some(message) if tag.deprecated => {
+                    let successor = tag
+                        .deprecation_successor
+                        .as_deref()
+                        .map(OciIdentifier::from_str)
+                        .transpose()?;
+                    let info = DeprecationInfo {
+                        message: message.clone(),
+                        successor,
+                    };
+                    (Some(info), LogKind::Deprecate)
+                }
+                _ => (None, LogKind::Undeprecate),
+            };
+            for entry in index::Entity::set_deprecation(&txn, oid, false, info).await? {
+                log_entry::Entity::append_index(&txn, kind, &entry, subject).await?;
+            }
+        }
+        txn.commit().await?;
+        info!("Synchronized state of {oid}");
+        Ok(true)
+    }
+
+    async fn repositories(&self, namespace: &str) -> Result<Vec<RepositorySummary>, Error> {
+        let mut repositories = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut path = format!("/api/v0/catalog?prefix={}", urlencoding::encode(namespace));
+            if let Some(cursor) = &cursor {
+                path.push_str(&format!("&cursor={cursor}"));
+            }
+            let page: Page<RepositorySummary> = self.get_json(&path).await?;
+            repositories.extend(page.items);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(repositories),
+            }
+        }
+    }
+
+    async fn tags(&self, namespace: &str, repository: &str) -> Result<Vec<TagInfo>, Error> {
+        let mut tags = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut path = format!("/api/v0/tags/{namespace}/{repository}");
+            if let Some(cursor) = &cursor {
+                path.push_str(&format!("?cursor={cursor}"));
+            }
+            let page: TagPage = self.get_json(&path).await?;
+            tags.extend(page.tags);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tags),
+            }
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<reqwest::Response, Error> {
+        let url = format!("{}{path}", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| Error::Upstream(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Error::Upstream(format!(
+                "{url} responded with {}",
+                response.status()
+            )));
+        }
+        Ok(response)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        self.get(path)
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Upstream(e.to_string()))
+    }
+
+    /// Downloads a manifest or blob and checks it against the digest at the end of the path
+    async fn get_verified(&self, path: &str) -> Result<(Vec<u8>, Option<String>), Error> {
+        let response = self.get(path).await?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let data = upstream::read_capped(response, self.state.upstream.max_blob_size()).await?;
+        let expected = path.rsplit('/').next().unwrap_or_default();
+        let computed = oci::digest(&data);
+        if computed != expected {
+            return Err(Error::Upstream(format!(
+                "{path} served content with digest {computed}"
+            )));
+        }
+        Ok((data, content_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use axum::{
+        http::{Method, StatusCode},
+        Router,
+    };
+    use serde_json::{json, Value};
+
+    async fn tags(app: &Router, repository: &str) -> Value {
+        let (_, page) = send_json(
+            app,
+            Method::GET,
+            &format!("/api/v0/tags/{repository}"),
+            None,
+        )
+        .await;
+        page["tags"].clone()
+    }
+
+    fn mirror(state: AppState, url: &str) -> Mirror {
+        Mirror::new(state, url, Duration::from_secs(5))
+    }
+
+    #[tokio::test]
+    async fn mirrors_namespaces_incrementally() {
+        let upstream = test_app().await;
+        for oci in [
+            "borderless/counter:1.0.0",
+            "borderless/counter:1.1.0",
+            "borderless/team/ledger:0.1.0",
+            "other/widget:1.0.0",
+        ] {
+            let pkg = test_pkg(oci, oci.as_bytes());
+            publish(&upstream, oci, &pkg).await;
+        }
+        send(
+            &upstream,
+            Method::PUT,
+            "/api/v0/yank/borderless/counter:1.0.0",
+            Some(json!({ "reason": "broken storage layout" })),
+        )
+        .await;
+        send(
+            &upstream,
+            Method::PUT,
+            "/api/v0/deprecate/borderless/team/ledger:0.1.0",
+            Some(json!({ "message": "use counter", "successor": "borderless/counter:1.1.0" })),
+        )
+        .await;
+        let (url, _server) = serve(upstream.clone()).await;
+
+        let (app, state) = test_app_with_state().await;
+        let mirror = mirror(state, &url);
+        let namespaces = ["borderless".to_string()];
+        let report = mirror.run(&namespaces).await.unwrap();
+        assert_eq!(
+            report,
+            MirrorReport {
+                published: 3,
+                ..Default::default()
+            }
+        );
+
+        // Tags, digests, manifests and state are identical on both sides
+        for repository in ["borderless/counter", "borderless/team/ledger"] {
+            let (expected, mirrored) = (
+                tags(&upstream, repository).await,
+                tags(&app, repository).await,
+            );
+            for field in [
+                "tag",
+                "digest",
+                "manifest_digest",
+                "yank",
+                "yank_reason",
+                "deprecated",
+                "deprecation_message",
+                "deprecation_successor",
+            ] {
+                let values = |tags: &Value| {
+                    tags.as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|t| t[field].clone())
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(values(&expected), values(&mirrored), "{repository} {field}");
+            }
+        }
+        let (status, _) = send_json(&app, Method::GET, "/api/v0/tags/other/widget", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        // Floating tags follow the mirrored state
+        let response = get(&app, "/api/v0/packages/borderless/counter:latest").await;
+        assert_eq!(json_body(response).await["tag"], "1.1.0");
+
+        // Unchanged versions are not copied again
+        let report = mirror.run(&namespaces).await.unwrap();
+        assert_eq!(
+            report,
+            MirrorReport {
+                unchanged: 3,
+                ..Default::default()
+            }
+        );
+
+        // New versions and state changes are picked up by the next run
+        publish(
+            &upstream,
+            "borderless/counter:1.2.0",
+            &test_pkg("counter", b"\0asm-1.2.0"),
+        )
+        .await;
+        send(
+            &upstream,
+            Method::DELETE,
+            "/api/v0/yank/borderless/counter:1.0.0",
+            None,
+        )
+        .await;
+        send(
+            &upstream,
+            Method::DELETE,
+            "/api/v0/deprecate/borderless/team/ledger:0.1.0",
+            None,
+        )
+        .await;
+        let report = mirror.run(&namespaces).await.unwrap();
+        assert_eq!(
+            report,
+            MirrorReport {
+                published: 1,
+                updated: 2,
+                unchanged: 1,
+                ..Default::default()
+            }
+        );
+        let mirrored = tags(&app, "borderless/counter").await;
+        assert_eq!(mirrored[0]["yank"], false);
+        assert_eq!(mirrored.as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn conflicting_versions_are_reported() {
+        let upstream = test_app().await;
+        publish(
+            &upstream,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm-upstream"),
+        )
+        .await;
+        let (url, _server) = serve(upstream).await;
+
+        let (app, state) = test_app_with_state().await;
+        publish(
+            &app,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm-local"),
+        )
+        .await;
+        let report = mirror(state, &url)
+            .run(&["borderless".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            MirrorReport {
+                failed: 1,
+                ..Default::default()
+            }
+        );
+        // The local version is kept
+        let local = tags(&app, "borderless/counter").await;
+        assert_eq!(
+            local[0]["digest"],
+            String::from(Hash256::digest(b"\0asm-local"))
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_content_is_refused() {
+        let upstream = test_app().await;
+        publish(
+            &upstream,
+            "borderless/counter:1.0.0",
+            &test_pkg("counter", b"\0asm-larger-than-the-limit"),
+        )
+        .await;
+        let (url, _server) = serve(upstream).await;
+
+        let (app, state) = test_app_with_state().await;
+        let state = state.with_upstream(upstream::Upstream::for_tests(16));
+        let report = mirror(state, &url)
+            .run(&["borderless".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            MirrorReport {
+                failed: 1,
+                ..Default::default()
+            }
+        );
+        let (status, _) =
+            send_json(&app, Method::GET, "/api/v0/tags/borderless/counter", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
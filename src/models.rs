@@ -1,4 +1,6 @@
 use borderless_pkg::semver::SemVer;
+use std::cmp::Ordering;
+use std::fmt;
 use std::net::SocketAddr;
 use std::str::FromStr;
 use thiserror::Error;
@@ -14,6 +16,12 @@ pub enum Error {
     MissingNamespace,
 }
 
+/// Floating tag, that always points to the newest stable version
+pub const LATEST: &str = "latest";
+
+/// Floating tag, that always points to the newest prerelease version
+pub const NIGHTLY: &str = "nightly";
+
 /// The Tag can be a keyword like latest, nightly etc.
 /// if not the tag describe a version in SemVer format.
 #[derive(Debug, Clone, PartialEq)]
@@ -26,7 +34,7 @@ impl FromStr for Tag {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let keywords = vec!["latest", "nightly"];
+        let keywords = [LATEST, NIGHTLY];
         if keywords.contains(&s) {
             Ok(Tag::Keyword(s.to_string()))
         } else {
@@ -36,11 +44,50 @@ impl FromStr for Tag {
     }
 }
 
-impl ToString for Tag {
-    fn to_string(&self) -> String {
+impl Tag {
+    /// Parses a tag - tries SemVer first and falls back to a keyword
+    pub fn parse(s: &str) -> Tag {
+        match SemVer::from_str(s) {
+            Ok(version) => Tag::Version(version),
+            Err(_) => Tag::Keyword(s.to_string()),
+        }
+    }
+
+    /// Returns the keyword, if this is one of the floating tags [`LATEST`] or [`NIGHTLY`]
+    pub fn floating_keyword(&self) -> Option<&str> {
+        match self {
+            Tag::Keyword(k) if k == LATEST || k == NIGHTLY => Some(k),
+            _ => None,
+        }
+    }
+}
+
+/// The namespace itself and all namespaces above it, e.g. `org/team` -> `[org, org/team]`
+pub fn namespace_ancestors(namespace: &str) -> Vec<String> {
+    namespace
+        .match_indices('/')
+        .map(|(i, _)| namespace[..i].to_string())
+        .chain(std::iter::once(namespace.to_string()))
+        .collect()
+}
+
+/// Orders tags by SemVer precedence
+///
+/// Tags, that are no valid SemVer versions, are ordered after all versions (lexicographically).
+pub fn tag_precedence(a: &str, b: &str) -> Ordering {
+    match (semver::Version::parse(a), semver::Version::parse(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Tag::Keyword(s) => s.clone(),
-            Tag::Version(v) => v.to_string(),
+            Tag::Keyword(s) => write!(f, "{s}"),
+            Tag::Version(v) => write!(f, "{v}"),
         }
     }
 }
@@ -75,16 +122,16 @@ impl FromStr for Registry {
     }
 }
 
-impl ToString for Registry {
-    fn to_string(&self) -> String {
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Registry::Url(url) => {
                 // For URLs that were auto-prefixed with https://,
                 // we might want to return just the host:port format
                 // This is a design decision - keeping full URL for now
-                url.to_string()
+                write!(f, "{url}")
             }
-            Registry::SocketAddr(sock) => sock.to_string(),
+            Registry::SocketAddr(sock) => write!(f, "{sock}"),
         }
     }
 }
@@ -166,10 +213,7 @@ impl FromStr for OciIdentifier {
         }
 
         // Parse tag - try SemVer first, fall back to keyword
-        let tag = match SemVer::from_str(tag_str) {
-            Ok(version) => Tag::Version(version),
-            Err(_) => Tag::Keyword(tag_str.to_string()),
-        };
+        let tag = Tag::parse(tag_str);
 
         // Handle non-URL registries if we don't already have one
         let final_registry = if registry.is_none() && !parts.is_empty() {
@@ -214,28 +258,15 @@ impl FromStr for OciIdentifier {
     }
 }
 
-impl ToString for OciIdentifier {
-    fn to_string(&self) -> String {
-        let mut result = String::new();
-
+impl fmt::Display for OciIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Add registry if present
         if let Some(registry) = &self.registry {
-            result.push_str(&registry.to_string());
-            result.push('/');
+            write!(f, "{registry}/")?;
         }
 
-        // Add namespace
-        result.push_str(&self.namespace);
-        result.push('/');
-
-        // Add repository
-        result.push_str(&self.repository);
-        result.push(':');
-
-        // Add tag
-        result.push_str(&self.tag.to_string());
-
-        result
+        // Add namespace, repository and tag
+        write!(f, "{}/{}:{}", self.namespace, self.repository, self.tag)
     }
 }
 
@@ -271,6 +302,14 @@ impl OciIdentifier {
         format!("{}/{}", self.namespace, self.repository)
     }
 
+    /// Get the registry as string - or an empty string, if the identifier has no registry
+    pub fn registry_string(&self) -> String {
+        self.registry
+            .as_ref()
+            .map(|r| r.to_string())
+            .unwrap_or_default()
+    }
+
     /// Check if this identifier has a registry
     pub fn has_registry(&self) -> bool {
         self.registry.is_some()
@@ -583,6 +622,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_namespace_ancestors() {
+        assert_eq!(namespace_ancestors("org"), vec!["org"]);
+        assert_eq!(
+            namespace_ancestors("org/team/project"),
+            vec!["org", "org/team", "org/team/project"]
+        );
+    }
+
+    // ===== ORDERING TESTS =====
+    #[test]
+    fn tags_are_ordered_by_semver_precedence() {
+        let mut tags = vec![
+            "1.10.0",
+            "dev",
+            "1.2.0",
+            "1.10.0-rc.1",
+            "0.9.12",
+            "1.10.0-alpha",
+            "18",
+        ];
+        tags.sort_by(|a, b| tag_precedence(a, b));
+        assert_eq!(
+            tags,
+            vec![
+                "0.9.12",
+                "1.2.0",
+                "1.10.0-alpha",
+                "1.10.0-rc.1",
+                "1.10.0",
+                "18",
+                "dev"
+            ]
+        );
+    }
+
     // ===== CONSTRUCTOR TESTS =====
     #[test]
     fn test_constructors() {
@@ -0,0 +1,305 @@
+//! Mapping of packages to OCI artifacts
+//!
+//! A package is an OCI image manifest with the artifact type [`ARTIFACT_TYPE`]. Its config blob is the json
+//! representation of the package without the wasm code ([`package_config`]), and its only layer is the wasm module.
+//! OCI digests are sha256 digests (`sha256:<hex>`), which is what standard tooling understands - independent of the
+//! sha3 digests, that the registry uses otherwise.
+use std::collections::BTreeMap;
+
+use base64::prelude::*;
+use borderless_hash::Hash256;
+use borderless_pkg::{SourceType, WasmPkg};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::{blobs, error::Error};
+
+/// Media type of OCI image manifests
+pub const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// Media type of OCI image indexes
+pub const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+
+/// Artifact type of borderless packages
+pub const ARTIFACT_TYPE: &str = "application/vnd.borderless.package.v1";
+
+/// Media type of the package config
+pub const CONFIG_MEDIA_TYPE: &str = "application/vnd.borderless.package.config.v1+json";
+
+/// Media type of the wasm layer
+pub const WASM_MEDIA_TYPE: &str = "application/wasm";
+
+/// Returns the OCI digest (`sha256:<hex>`) of the given content
+pub fn digest(content: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(content)))
+}
+
+/// Key of an uploaded blob in the blob store - the hash of its `sha256:<hex>` digest
+///
+/// Sources are keyed by their sha3 digest, so both kinds of blobs share the store without colliding.
+pub fn blob_key(digest: &str) -> Result<Hash256, Error> {
+    let hex = digest.strip_prefix("sha256:").ok_or(Error::InvalidDigest)?;
+    blobs::parse_digest(hex)
+}
+
+/// Returns true, if the given string is a valid OCI digest
+pub fn is_digest(reference: &str) -> bool {
+    reference.split_once(':').is_some_and(|(algorithm, hex)| {
+        !algorithm.is_empty()
+            && !hex.is_empty()
+            && algorithm
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+._-".contains(c))
+            && hex
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "=_-".contains(c))
+    })
+}
+
+/// Content descriptor of a blob
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<BTreeMap<String, String>>,
+}
+
+impl Descriptor {
+    fn of(media_type: &str, content: &[u8]) -> Self {
+        Descriptor {
+            media_type: media_type.to_string(),
+            digest: digest(content),
+            size: content.len() as u64,
+            artifact_type: None,
+            annotations: None,
+        }
+    }
+}
+
+/// OCI image manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageManifest {
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    pub config: Descriptor,
+    pub layers: Vec<Descriptor>,
+    /// Manifest, that this artifact refers to (e.g. the package, that a signature or SBOM belongs to)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Descriptor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<BTreeMap<String, String>>,
+}
+
+impl ImageManifest {
+    /// Parses a pushed manifest
+    ///
+    /// `content_type` is the media type of the request, which is used if the manifest does not declare its own.
+    pub fn parse(content: &[u8], content_type: Option<&str>) -> Result<Self, Error> {
+        let manifest: ImageManifest =
+            serde_json::from_slice(content).map_err(|e| Error::InvalidManifest(e.to_string()))?;
+        if manifest.schema_version != 2 {
+            return Err(Error::InvalidManifest("schema version must be 2".into()));
+        }
+        let media_type = manifest.media_type.as_deref().or(content_type);
+        if media_type != Some(MANIFEST_MEDIA_TYPE) {
+            return Err(Error::InvalidManifest(format!(
+                "media type must be {MANIFEST_MEDIA_TYPE}"
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// Checks, that the manifest describes a package
+    pub fn check_package(&self) -> Result<(), Error> {
+        if self.config.media_type != CONFIG_MEDIA_TYPE {
+            return Err(Error::InvalidManifest(format!(
+                "config must be of type {CONFIG_MEDIA_TYPE}"
+            )));
+        }
+        match self.layers.as_slice() {
+            [layer] if layer.media_type == WASM_MEDIA_TYPE => Ok(()),
+            _ => Err(Error::InvalidManifest(format!(
+                "expected exactly one layer of type {WASM_MEDIA_TYPE}"
+            ))),
+        }
+    }
+
+    /// The wasm layer of the package
+    pub fn layer(&self) -> &Descriptor {
+        &self.layers[0]
+    }
+
+    /// Type of the artifact - which falls back to the media type of the config
+    pub fn artifact_type(&self) -> &str {
+        self.artifact_type
+            .as_deref()
+            .unwrap_or(&self.config.media_type)
+    }
+}
+
+/// OCI image index, which lists the referrers of a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageIndex {
+    pub schema_version: u32,
+    pub media_type: String,
+    pub manifests: Vec<Descriptor>,
+}
+
+impl ImageIndex {
+    pub fn new(manifests: Vec<Descriptor>) -> Self {
+        ImageIndex {
+            schema_version: 2,
+            media_type: INDEX_MEDIA_TYPE.to_string(),
+            manifests,
+        }
+    }
+}
+
+/// OCI representation of a package, as it is stored next to the registry index
+#[derive(Debug, Clone)]
+pub struct OciArtifact {
+    pub media_type: String,
+    /// Raw bytes of the manifest - its digest is computed over exactly these bytes
+    pub manifest: Vec<u8>,
+    pub digest: String,
+    pub config: Vec<u8>,
+    pub config_digest: String,
+    pub layer_digest: String,
+}
+
+impl OciArtifact {
+    /// Builds the artifact of a package, that was published through the registry api
+    ///
+    /// Returns `None` for packages without wasm code.
+    pub fn from_package(pkg: &WasmPkg) -> Option<Self> {
+        let SourceType::Wasm { wasm, .. } = &pkg.source.code else {
+            return None;
+        };
+        let config = package_config(pkg);
+        let manifest = ImageManifest {
+            schema_version: 2,
+            media_type: Some(MANIFEST_MEDIA_TYPE.to_string()),
+            artifact_type: Some(ARTIFACT_TYPE.to_string()),
+            config: Descriptor::of(CONFIG_MEDIA_TYPE, &config),
+            layers: vec![Descriptor::of(WASM_MEDIA_TYPE, wasm)],
+            subject: None,
+            annotations: None,
+        };
+        let content = serde_json::to_vec(&manifest).expect("manifests are serializable");
+        Some(Self::new(manifest, content, config))
+    }
+
+    /// Wraps a pushed manifest together with its config
+    pub fn new(manifest: ImageManifest, content: Vec<u8>, config: Vec<u8>) -> Self {
+        OciArtifact {
+            media_type: MANIFEST_MEDIA_TYPE.to_string(),
+            digest: digest(&content),
+            config_digest: manifest.config.digest.clone(),
+            layer_digest: manifest.layer().digest.clone(),
+            manifest: content,
+            config,
+        }
+    }
+}
+
+/// Json representation of a package without its wasm code
+pub fn package_config(pkg: &WasmPkg) -> Vec<u8> {
+    let mut config = serde_json::to_value(pkg).expect("packages are serializable");
+    if let Some(code) = config
+        .pointer_mut("/source/code")
+        .and_then(|c| c.as_object_mut())
+    {
+        code.remove("wasm");
+    }
+    serde_json::to_vec(&config).expect("packages are serializable")
+}
+
+/// Reassembles a package from its config and wasm layer
+pub fn package_from_config(config: &[u8], wasm: &[u8]) -> Result<WasmPkg, Error> {
+    let mut value: serde_json::Value = serde_json::from_slice(config)
+        .map_err(|e| Error::InvalidManifest(format!("invalid package config - {e}")))?;
+    let source = value
+        .get_mut("source")
+        .and_then(|s| s.as_object_mut())
+        .ok_or_else(|| Error::InvalidManifest("package config has no source".into()))?;
+    let code = source
+        .entry("code")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| Error::InvalidManifest("invalid source code".into()))?;
+    code.insert("wasm".to_string(), BASE64_STANDARD.encode(wasm).into());
+    let pkg: WasmPkg = serde_json::from_value(value)
+        .map_err(|e| Error::InvalidManifest(format!("invalid package config - {e}")))?;
+    match pkg.source.code {
+        SourceType::Wasm { .. } => Ok(pkg),
+        SourceType::Registry { .. } => Err(Error::InvalidManifest(
+            "package config must not reference another registry".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_pkg;
+
+    #[test]
+    fn package_round_trip() {
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        let artifact = OciArtifact::from_package(&pkg).unwrap();
+        assert_eq!(artifact.digest, digest(&artifact.manifest));
+        assert_eq!(artifact.layer_digest, digest(b"\0asm-counter"));
+
+        let manifest = ImageManifest::parse(&artifact.manifest, None).unwrap();
+        manifest.check_package().unwrap();
+        assert_eq!(manifest.artifact_type(), ARTIFACT_TYPE);
+        assert_eq!(manifest.config.digest, digest(&artifact.config));
+
+        let restored = package_from_config(&artifact.config, b"\0asm-counter").unwrap();
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&pkg).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_manifests() {
+        let pkg = test_pkg("counter", b"\0asm");
+        let artifact = OciArtifact::from_package(&pkg).unwrap();
+        let mut manifest: serde_json::Value = serde_json::from_slice(&artifact.manifest).unwrap();
+        manifest["layers"][0]["mediaType"] = "application/octet-stream".into();
+        let content = serde_json::to_vec(&manifest).unwrap();
+        let parsed = ImageManifest::parse(&content, None).unwrap();
+        assert!(parsed.check_package().is_err());
+
+        manifest["layers"][0]["mediaType"] = WASM_MEDIA_TYPE.into();
+        manifest.as_object_mut().unwrap().remove("mediaType");
+        let content = serde_json::to_vec(&manifest).unwrap();
+        assert!(ImageManifest::parse(&content, None).is_err());
+        assert!(ImageManifest::parse(&content, Some(MANIFEST_MEDIA_TYPE)).is_ok());
+
+        assert!(ImageManifest::parse(b"{}", None).is_err());
+        assert!(package_from_config(b"{\"name\":\"x\"}", b"\0asm").is_err());
+    }
+
+    #[test]
+    fn digests() {
+        assert_eq!(
+            digest(b""),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert!(is_digest("sha256:abc"));
+        assert!(!is_digest("1.0.0"));
+        assert!(!is_digest("sha256:"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Ed25519 package signatures
+//!
+//! Publishers sign the canonical manifest of a package (see [`canonical_manifest`]) with a key, that is registered
+//! for the namespace, and send the base64 encoded signature in the `x-package-signature` header.
+//! Downloads return one `x-package-signature: <fingerprint>:<signature>` header per signature, while the signed
+//! payloads are served by `/api/v0/signatures/{digest}` - so nodes can verify a package offline.
+use base64::prelude::*;
+use borderless_hash::Hash256;
+use borderless_pkg::WasmPkg;
+use ed25519_dalek::{Signature, VerifyingKey};
+use sea_orm::ConnectionTrait;
+
+use crate::{
+    db::entities::{namespace_policy, signing_key},
+    error::Error,
+};
+
+/// Header, that carries package signatures
+pub const SIGNATURE_HEADER: &str = "x-package-signature";
+
+/// A signature, that was verified against a key of the namespace
+#[derive(Debug, Clone)]
+pub struct VerifiedSignature {
+    pub key_id: i64,
+    pub signature: String,
+    pub payload: String,
+}
+
+/// Returns the canonical manifest of a package, which is the payload of its signature
+///
+/// The manifest is the json representation of the package without its code, with lexicographically sorted keys.
+/// It still contains the digest of the code, so the signature covers both.
+pub fn canonical_manifest(pkg: &WasmPkg) -> String {
+    let mut manifest = serde_json::to_value(pkg).expect("packages are serializable");
+    if let Some(source) = manifest.get_mut("source").and_then(|s| s.as_object_mut()) {
+        source.remove("code");
+    }
+    manifest.to_string()
+}
+
+/// Parses a base64 encoded ed25519 public key
+pub fn parse_public_key(encoded: &str) -> Result<VerifyingKey, Error> {
+    let bytes = BASE64_STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| Error::InvalidKey)?;
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| Error::InvalidKey)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| Error::InvalidKey)
+}
+
+/// Hex encoded sha3-256 hash of a public key
+pub fn fingerprint(key: &VerifyingKey) -> String {
+    String::from(Hash256::digest(key.as_bytes()))
+}
+
+/// Verifies the signature of a publish against the keys of the namespace and its policy
+///
+/// Returns `None`, if the package is unsigned and the namespace does not require signatures.
+pub async fn verify_publish(
+    db: &impl ConnectionTrait,
+    namespace: &str,
+    pkg: &WasmPkg,
+    signature: Option<&str>,
+) -> Result<Option<VerifiedSignature>, Error> {
+    let Some(encoded) = signature else {
+        let policy = namespace_policy::Entity::effective(db, namespace).await?;
+        if policy.is_some_and(|p| p.require_signature) {
+            return Err(Error::SignatureRequired(namespace.to_string()));
+        }
+        return Ok(None);
+    };
+
+    let bytes = BASE64_STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| Error::InvalidSignature)?;
+    let signature = Signature::from_slice(&bytes).map_err(|_| Error::InvalidSignature)?;
+    let payload = canonical_manifest(pkg);
+
+    for key in signing_key::Entity::find_trusted(db, namespace).await? {
+        let public_key = parse_public_key(&key.public_key)?;
+        if public_key
+            .verify_strict(payload.as_bytes(), &signature)
+            .is_ok()
+        {
+            return Ok(Some(VerifiedSignature {
+                key_id: key.id,
+                signature: BASE64_STANDARD.encode(signature.to_bytes()),
+                payload,
+            }));
+        }
+    }
+    Err(Error::InvalidSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_pkg;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn manifest_excludes_code_but_covers_digest() {
+        let pkg = test_pkg("counter", b"\0asm-counter");
+        let manifest = canonical_manifest(&pkg);
+        assert!(!manifest.contains("\"code\""));
+        assert!(manifest.contains("\"digest\""));
+        assert_ne!(
+            manifest,
+            canonical_manifest(&test_pkg("counter", b"\0asm-other"))
+        );
+    }
+
+    #[test]
+    fn public_keys() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let encoded = BASE64_STANDARD.encode(key.verifying_key().as_bytes());
+        let parsed = parse_public_key(&encoded).unwrap();
+        assert_eq!(parsed, key.verifying_key());
+        assert_eq!(fingerprint(&parsed).len(), 64);
+        assert!(parse_public_key("AAAA").is_err());
+
+        let payload = canonical_manifest(&test_pkg("counter", b"\0asm"));
+        let signature = key.sign(payload.as_bytes());
+        assert!(parsed.verify_strict(payload.as_bytes(), &signature).is_ok());
+    }
+}
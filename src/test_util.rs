@@ -0,0 +1,213 @@
+//! Shared helpers for the in-process API tests
+use async_trait::async_trait;
+use axum::{
+    body::{to_bytes, Body},
+    http::{Method, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use borderless_hash::Hash256;
+use borderless_pkg::{PkgMeta, PkgType, Registry, SemVer, Source, SourceType, WasmPkg};
+use serde_json::Value;
+use std::sync::Arc;
+use tower::util::ServiceExt;
+
+use crate::{
+    auth::Scope,
+    blobs::{BlobStore, KvBlobStore},
+    db::{self, entities::token},
+    error::Error,
+    router,
+    upstream::Upstream,
+    AppState,
+};
+
+/// Secret of the admin token, that [`send`] and [`publish`] authenticate with
+pub const TEST_TOKEN: &str = "brt_test";
+
+/// Subject of [`TEST_TOKEN`]
+pub const TEST_SUBJECT: &str = "tester";
+
+/// Creates the API router on top of a fresh in-memory database
+pub async fn test_app() -> Router {
+    test_app_with_state().await.0
+}
+
+/// Like [`test_app`], but also returns the state to inspect the database
+pub async fn test_app_with_state() -> (Router, AppState) {
+    let dir = tempfile::tempdir().expect("failed to create blob directory");
+    let store = KvBlobStore::open(dir.path()).expect("failed to open blob store");
+    test_app_with_blobs(Arc::new(TempBlobStore { store, _dir: dir })).await
+}
+
+/// Blob store in a temporary directory, that is removed together with the store
+#[derive(Debug)]
+struct TempBlobStore {
+    store: KvBlobStore,
+    // Dropped after the store, so the database is closed before its directory is removed
+    _dir: tempfile::TempDir,
+}
+
+#[async_trait]
+impl BlobStore for TempBlobStore {
+    async fn put(&self, digest: &Hash256, data: Vec<u8>) -> Result<(), Error> {
+        self.store.put(digest, data).await
+    }
+
+    async fn get(&self, digest: &Hash256) -> Result<Vec<u8>, Error> {
+        self.store.get(digest).await
+    }
+
+    async fn contains(&self, digest: &Hash256) -> Result<bool, Error> {
+        self.store.contains(digest).await
+    }
+
+    async fn delete(&self, digest: &Hash256) -> Result<(), Error> {
+        self.store.delete(digest).await
+    }
+
+    async fn list(&self) -> Result<Vec<Hash256>, Error> {
+        self.store.list().await
+    }
+}
+
+/// Same as [`test_app_with_state`], but on top of the given blob store
+pub async fn test_app_with_blobs(blobs: Arc<dyn BlobStore>) -> (Router, AppState) {
+    let db = db::setup_database(&db::DbConfig::in_memory())
+        .await
+        .expect("failed to setup database");
+    let admin = token::NewToken {
+        name: "test".to_string(),
+        subject: TEST_SUBJECT.to_string(),
+        scopes: vec![Scope::Admin],
+        namespaces: None,
+        expires_at: None,
+    };
+    token::Entity::create(&db, admin, TEST_TOKEN)
+        .await
+        .expect("failed to create test token");
+    let state = AppState::new(db, blobs).with_upstream(Upstream::for_tests(1024 * 1024));
+    (router(state.clone()), state)
+}
+
+/// Creates a contract package with the given name and wasm bytes
+pub fn test_pkg(name: &str, wasm: &[u8]) -> WasmPkg {
+    WasmPkg {
+        name: name.to_string(),
+        app_name: None,
+        app_module: None,
+        capabilities: None,
+        pkg_type: PkgType::Contract,
+        meta: PkgMeta {
+            description: Some(format!("The {name} contract")),
+            ..Default::default()
+        },
+        source: Source {
+            version: SemVer::default(),
+            digest: Hash256::digest(&wasm),
+            code: SourceType::Wasm {
+                wasm: wasm.to_vec(),
+                git_info: None,
+            },
+        },
+    }
+}
+
+/// Sends a request with an optional json body as admin and returns the status and raw response body
+pub async fn send(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Vec<u8>) {
+    send_as(app, Some(TEST_TOKEN), method, uri, body).await
+}
+
+/// Like [`send`], but authenticates with the given token secret (or not at all)
+pub async fn send_as(
+    app: &Router,
+    token: Option<&str>,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Vec<u8>) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let body = match body {
+        Some(json) => {
+            request = request.header("content-type", "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let response = app
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, bytes.to_vec())
+}
+
+/// Sends a request and parses the response body as json
+pub async fn send_json(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let (status, bytes) = send(app, method, uri, body).await;
+    let json = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).expect("response is not valid json")
+    };
+    (status, json)
+}
+
+/// Sends a plain GET request and returns the full response
+pub async fn get(app: &Router, uri: &str) -> Response {
+    app.clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+/// Consumes the response and parses its body as json
+pub async fn json_body(response: Response) -> Value {
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).expect("response is not valid json")
+}
+
+/// Serves the router on a local port, until the returned task is aborted
+pub async fn serve(app: Router) -> (String, tokio::task::JoinHandle<()>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (url, server)
+}
+
+/// Creates a package, whose code lives in the registry at the given url
+pub fn upstream_pkg(name: &str, wasm: &[u8], url: &str) -> WasmPkg {
+    let mut pkg = test_pkg(name, wasm);
+    pkg.source.code = SourceType::Registry {
+        registry: Registry {
+            registry_type: None,
+            registry_hostname: url.to_string(),
+            namespace: "borderless".to_string(),
+        },
+    };
+    pkg
+}
+
+/// Publishes a package under the given oci identifier
+pub async fn publish(app: &Router, oci: &str, pkg: &WasmPkg) -> StatusCode {
+    let uri = format!("/api/v0/publish/{oci}");
+    let body = serde_json::to_value(pkg).unwrap();
+    send(app, Method::PUT, &uri, Some(body)).await.0
+}
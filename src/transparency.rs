@@ -0,0 +1,303 @@
+//! Merkle tree of the transparency log
+//!
+//! The tree follows RFC 6962 (with sha3-256), so auditors can use the well known algorithms to verify
+//! inclusion and consistency proofs. Leaves are hashed with a `0x00` prefix, inner nodes with a `0x01` prefix.
+//! Tree heads are signed with the ed25519 log key of the registry (see [`TreeHead::message`]).
+use std::{fs::OpenOptions, io::Write, os::unix::fs::OpenOptionsExt, path::Path};
+
+use base64::prelude::*;
+use borderless_hash::Hash256;
+use ed25519_dalek::{Signer, SigningKey};
+use rand::{rngs::OsRng, RngCore};
+use serde::Serialize;
+use tracing::info;
+
+/// Root of the log at a given size
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeHead {
+    pub tree_size: u64,
+    /// Hex encoded root hash
+    pub root_hash: String,
+    /// Unix timestamp (in seconds) of the signature
+    pub timestamp: i64,
+}
+
+impl TreeHead {
+    /// The signed message: `<tree_size>:<root_hash>:<timestamp>`
+    pub fn message(&self) -> String {
+        format!("{}:{}:{}", self.tree_size, self.root_hash, self.timestamp)
+    }
+
+    /// Signs the tree head with the log key and returns the base64 encoded signature
+    pub fn sign(&self, key: &SigningKey) -> String {
+        BASE64_STANDARD.encode(key.sign(self.message().as_bytes()).to_bytes())
+    }
+}
+
+/// Generates a random log key
+pub fn generate_key() -> SigningKey {
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    SigningKey::from_bytes(&seed)
+}
+
+/// Loads the log key from a file with the base64 encoded seed - or creates the file, if it does not exist
+pub fn load_or_create_key(path: &Path) -> anyhow::Result<SigningKey> {
+    if path.exists() {
+        let encoded = std::fs::read_to_string(path)?;
+        let seed: [u8; 32] = BASE64_STANDARD
+            .decode(encoded.trim())?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("log key must be a base64 encoded 32 byte seed"))?;
+        return Ok(SigningKey::from_bytes(&seed));
+    }
+    let key = generate_key();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Only the registry user may read the key - and a key, that appeared in the meantime, is never overwritten
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(BASE64_STANDARD.encode(key.to_bytes()).as_bytes())?;
+    info!("Created new log key at {}", path.display());
+    Ok(key)
+}
+
+/// Hash of a leaf of the tree
+pub fn leaf_hash(data: &[u8]) -> Hash256 {
+    Hash256::digest_w_x00(&data)
+}
+
+fn node_hash(left: &Hash256, right: &Hash256) -> Hash256 {
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(left.as_ref());
+    bytes[32..].copy_from_slice(right.as_ref());
+    Hash256::digest_w_x01(&bytes)
+}
+
+/// Largest power of two, that is smaller than `n` (for `n > 1`)
+fn split(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// Root hash of a tree over the given leaf hashes
+pub fn root_hash(leaves: &[Hash256]) -> Hash256 {
+    match leaves.len() {
+        0 => Hash256::empty(),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            node_hash(&root_hash(&leaves[..k]), &root_hash(&leaves[k..]))
+        }
+    }
+}
+
+/// Audit path of the leaf at `index` (RFC 6962, section 2.1.1)
+pub fn inclusion_proof(leaves: &[Hash256], index: usize) -> Vec<Hash256> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split(n);
+    if index < k {
+        let mut path = inclusion_proof(&leaves[..k], index);
+        path.push(root_hash(&leaves[k..]));
+        path
+    } else {
+        let mut path = inclusion_proof(&leaves[k..], index - k);
+        path.push(root_hash(&leaves[..k]));
+        path
+    }
+}
+
+/// Proof, that the tree over the first `first` leaves is a prefix of the tree over all `leaves` (RFC 6962, section 2.1.2)
+pub fn consistency_proof(leaves: &[Hash256], first: usize) -> Vec<Hash256> {
+    if first == 0 || first > leaves.len() {
+        return Vec::new();
+    }
+    subproof(leaves, first, true)
+}
+
+fn subproof(leaves: &[Hash256], m: usize, complete: bool) -> Vec<Hash256> {
+    let n = leaves.len();
+    if m == n {
+        return if complete {
+            Vec::new()
+        } else {
+            vec![root_hash(leaves)]
+        };
+    }
+    let k = split(n);
+    if m <= k {
+        let mut proof = subproof(&leaves[..k], m, complete);
+        proof.push(root_hash(&leaves[k..]));
+        proof
+    } else {
+        let mut proof = subproof(&leaves[k..], m - k, false);
+        proof.push(root_hash(&leaves[..k]));
+        proof
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn leaves(n: usize) -> Vec<Hash256> {
+        (0..n)
+            .map(|i| leaf_hash(format!("leaf-{i}").as_bytes()))
+            .collect()
+    }
+
+    /// Verification of an inclusion proof (RFC 9162, section 2.1.3.2)
+    fn verify_inclusion(
+        index: usize,
+        size: usize,
+        leaf: Hash256,
+        proof: &[Hash256],
+        root: Hash256,
+    ) -> bool {
+        if index >= size {
+            return false;
+        }
+        let (mut fn_, mut sn) = (index, size - 1);
+        let mut r = leaf;
+        for p in proof {
+            if sn == 0 {
+                return false;
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                r = node_hash(p, &r);
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                r = node_hash(&r, p);
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        sn == 0 && r == root
+    }
+
+    /// Verification of a consistency proof (RFC 9162, section 2.1.4.2)
+    fn verify_consistency(
+        first: usize,
+        second: usize,
+        first_root: Hash256,
+        second_root: Hash256,
+        proof: &[Hash256],
+    ) -> bool {
+        if first == second {
+            return proof.is_empty() && first_root == second_root;
+        }
+        if proof.is_empty() {
+            return false;
+        }
+        let mut proof = proof.to_vec();
+        if first.is_power_of_two() {
+            proof.insert(0, first_root);
+        }
+        let (mut fn_, mut sn) = (first - 1, second - 1);
+        while fn_ & 1 == 1 {
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        let (mut fr, mut sr) = (proof[0], proof[0]);
+        for c in &proof[1..] {
+            if sn == 0 {
+                return false;
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                fr = node_hash(c, &fr);
+                sr = node_hash(c, &sr);
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                sr = node_hash(&sr, c);
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        sn == 0 && fr == first_root && sr == second_root
+    }
+
+    #[test]
+    fn inclusion_proofs_verify() {
+        for size in 1..=17 {
+            let leaves = leaves(size);
+            let root = root_hash(&leaves);
+            for index in 0..size {
+                let proof = inclusion_proof(&leaves, index);
+                assert!(verify_inclusion(index, size, leaves[index], &proof, root));
+                assert!(!verify_inclusion(
+                    index,
+                    size,
+                    leaf_hash(b"other"),
+                    &proof,
+                    root
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn consistency_proofs_verify() {
+        let all = leaves(17);
+        for second in 1..=all.len() {
+            let second_root = root_hash(&all[..second]);
+            for first in 1..=second {
+                let first_root = root_hash(&all[..first]);
+                let proof = consistency_proof(&all[..second], first);
+                assert!(verify_consistency(
+                    first,
+                    second,
+                    first_root,
+                    second_root,
+                    &proof
+                ));
+                if first < second {
+                    assert!(!verify_consistency(
+                        first,
+                        second,
+                        leaf_hash(b"forged"),
+                        second_root,
+                        &proof
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn key_file_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys/log.key");
+        let created = load_or_create_key(&path).unwrap();
+        let loaded = load_or_create_key(&path).unwrap();
+        assert_eq!(created.to_bytes(), loaded.to_bytes());
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        std::fs::write(&path, "not a key").unwrap();
+        assert!(load_or_create_key(&path).is_err());
+    }
+
+    #[test]
+    fn known_roots() {
+        assert_eq!(root_hash(&[]), Hash256::empty());
+        let l = leaves(3);
+        assert_eq!(root_hash(&l), node_hash(&node_hash(&l[0], &l[1]), &l[2]));
+    }
+}
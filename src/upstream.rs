@@ -0,0 +1,246 @@
+//! Pull-through cache for packages, whose code lives in another registry
+use std::time::Duration;
+
+use borderless_hash::Hash256;
+use sea_orm::{ConnectionTrait, EntityTrait};
+use tracing::info;
+use url::Url;
+
+use crate::{
+    blobs::{self, BlobStore},
+    db::entities::{registry, source},
+    error::Error,
+};
+
+/// Registry type of upstreams, that serve blobs over the borderless registry api
+pub const BORDERLESS_REGISTRY: &str = "borderless";
+
+/// Default limit for the size of blobs, that are downloaded from other registries
+pub const DEFAULT_MAX_BLOB_SIZE: usize = 64 * 1024 * 1024;
+
+/// Http client for upstream registries
+///
+/// The registry of a package is chosen by its publisher, so blobs are only pulled
+/// over https from hosts, that the operator allowed.
+#[derive(Clone, Debug)]
+pub struct Upstream {
+    client: reqwest::Client,
+    /// Hosts (optionally with a port), that blobs may be pulled from
+    allowed_hosts: Vec<String>,
+    /// Largest blob, that is accepted from an upstream
+    max_blob_size: usize,
+    /// Permits plain http, which only the tests need
+    allow_http: bool,
+}
+
+impl Default for Upstream {
+    /// Client without any allowed host - every pull is refused
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30), Vec::new(), DEFAULT_MAX_BLOB_SIZE)
+    }
+}
+
+impl Upstream {
+    pub fn new(timeout: Duration, allowed_hosts: Vec<String>, max_blob_size: usize) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            // Redirects could lead to hosts, that are not allowed
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("failed to build http client");
+        Self {
+            client,
+            allowed_hosts,
+            max_blob_size,
+            allow_http: false,
+        }
+    }
+
+    /// Client, that may pull from local test servers over plain http
+    #[cfg(test)]
+    pub fn for_tests(max_blob_size: usize) -> Self {
+        Self {
+            allow_http: true,
+            ..Self::new(
+                Duration::from_secs(5),
+                vec!["127.0.0.1".to_string()],
+                max_blob_size,
+            )
+        }
+    }
+
+    /// Largest blob, that is accepted from an upstream
+    pub fn max_blob_size(&self) -> usize {
+        self.max_blob_size
+    }
+
+    /// Checks the scheme and host of an upstream url against the configuration
+    fn check_allowed(&self, url: &Url) -> Result<(), Error> {
+        if url.scheme() != "https" && !(self.allow_http && url.scheme() == "http") {
+            return Err(Error::Upstream(format!("{url} is not served over https")));
+        }
+        let host = url.host_str().unwrap_or_default();
+        let host_with_port = url.port().map(|port| format!("{host}:{port}"));
+        let allowed = self
+            .allowed_hosts
+            .iter()
+            .any(|allowed| allowed == host || Some(allowed) == host_with_port.as_ref());
+        if !allowed {
+            return Err(Error::Upstream(format!(
+                "{host} is not an allowed upstream registry"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fetches the wasm of a source from its registry and caches it in the blob store
+    pub async fn pull(
+        &self,
+        db: &impl ConnectionTrait,
+        blobs: &dyn BlobStore,
+        source: &source::Model,
+        digest: &Hash256,
+    ) -> Result<Vec<u8>, Error> {
+        let registry_id = source.registry_id.ok_or(Error::NoPkg(*digest))?;
+        let registry = registry::Entity::find_by_id(registry_id)
+            .one(db)
+            .await?
+            .ok_or(Error::NoPkg(*digest))?;
+
+        let wasm = self.fetch_blob(&registry, digest).await?;
+        let encoding = blobs::store_wasm(blobs, digest, wasm.clone()).await?;
+        source::Entity::set_blob(db, source.id, wasm.len(), encoding).await?;
+        info!(
+            "Cached wasm blob {} from {} ({} bytes)",
+            source.digest,
+            registry.hostname,
+            wasm.len()
+        );
+        Ok(wasm)
+    }
+
+    /// Downloads a wasm blob from the registry and verifies its digest
+    ///
+    /// Sources only record the digest of the wasm, so the blob is fetched over the borderless api.
+    /// Registries without a type are assumed to speak it as well.
+    pub async fn fetch_blob(
+        &self,
+        registry: &registry::Model,
+        digest: &Hash256,
+    ) -> Result<Vec<u8>, Error> {
+        match registry.registry_type.as_deref() {
+            None | Some(BORDERLESS_REGISTRY) => (),
+            Some(other) => {
+                return Err(Error::Upstream(format!(
+                    "unsupported registry type '{other}'"
+                )))
+            }
+        }
+        let url = format!(
+            "{}/api/v0/blobs/{}",
+            base_url(&registry.hostname),
+            String::from(*digest)
+        );
+        let url = Url::parse(&url).map_err(|e| Error::Upstream(format!("{url} - {e}")))?;
+        self.check_allowed(&url)?;
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| Error::Upstream(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Error::Upstream(format!(
+                "{url} responded with {}",
+                response.status()
+            )));
+        }
+        let wasm = read_capped(response, self.max_blob_size).await?;
+
+        // Never trust the upstream either
+        let computed = Hash256::digest(&wasm);
+        if computed != *digest {
+            return Err(Error::Upstream(format!(
+                "{url} served a blob with digest {}",
+                String::from(computed)
+            )));
+        }
+        Ok(wasm)
+    }
+}
+
+/// Reads the body of a response, but stops as soon as it exceeds the limit
+pub async fn read_capped(mut response: reqwest::Response, limit: usize) -> Result<Vec<u8>, Error> {
+    let url = response.url().to_string();
+    let too_large = || Error::Upstream(format!("{url} exceeds {limit} bytes"));
+    if response
+        .content_length()
+        .is_some_and(|len| len > limit as u64)
+    {
+        return Err(too_large());
+    }
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| Error::Upstream(e.to_string()))?
+    {
+        if data.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Base url of a registry - hostnames without a scheme are reached over https
+pub fn base_url(hostname: &str) -> String {
+    let hostname = hostname.trim_end_matches('/');
+    if hostname.contains("://") {
+        hostname.to_string()
+    } else {
+        format!("https://{hostname}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_urls() {
+        assert_eq!(
+            base_url("registry.borderless.dev"),
+            "https://registry.borderless.dev"
+        );
+        assert_eq!(base_url("http://127.0.0.1:3000/"), "http://127.0.0.1:3000");
+    }
+
+    #[test]
+    fn only_allowed_hosts_over_https() {
+        let upstream = Upstream::new(
+            Duration::from_secs(1),
+            vec![
+                "registry.borderless.dev".to_string(),
+                "10.0.0.1:8443".to_string(),
+            ],
+            DEFAULT_MAX_BLOB_SIZE,
+        );
+        let allowed = |url: &str| upstream.check_allowed(&Url::parse(url).unwrap()).is_ok();
+        assert!(allowed("https://registry.borderless.dev/api/v0/blobs/ab"));
+        assert!(allowed(
+            "https://registry.borderless.dev:8443/api/v0/blobs/ab"
+        ));
+        assert!(allowed("https://10.0.0.1:8443/api/v0/blobs/ab"));
+        assert!(!allowed("https://10.0.0.1/api/v0/blobs/ab"));
+        assert!(!allowed("http://registry.borderless.dev/api/v0/blobs/ab"));
+        assert!(!allowed("https://169.254.169.254/latest/meta-data"));
+        assert!(!allowed(
+            "https://registry.borderless.dev.evil.com/api/v0/blobs/ab"
+        ));
+        assert!(!allowed("https://evil.com@169.254.169.254/api/v0/blobs/ab"));
+        assert!(Upstream::default()
+            .check_allowed(&Url::parse("https://registry.borderless.dev").unwrap())
+            .is_err());
+    }
+}